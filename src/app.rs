@@ -1,6 +1,6 @@
 use crate::input::{
     VimInput, InputState, Mode, CommandState,
-    ResolvedCommand, EditorCommand
+    ResolvedCommand, EditorCommand, LocalCommand, Motion, Operation, Operator, TextObject,
 };
 
 use crate::widgets::{
@@ -8,9 +8,26 @@ use crate::widgets::{
     commandline::CommandLine,
 };
 
-use crate::window::WindowManager;
+use crate::registers::Registers;
+use crate::window::{Context, WindowManager};
+use crate::args::Args;
+use crate::effect::EffectSpec;
+use crate::audio::{
+    AudioCommand, AudioEngine,
+    backend::BackendKind,
+    export::{self, WavFormat},
+    renderer::Renderer,
+    transport::{LoopRegion, PlayState, TransportCommand},
+};
+use crate::project::{
+    Project, TrackId, file, midi,
+    playback::ProjectSync,
+    quantize::Humanize,
+    routing::{BusId, MASTER, Output, Send},
+    tempo::{MAX_TEMPO, MIN_TEMPO, TimeSignature},
+};
 
-use color_eyre::eyre::{Ok, Result};
+use color_eyre::eyre::{eyre, Result};
 
 use ratatui::{
     DefaultTerminal, Frame,
@@ -18,24 +35,69 @@ use ratatui::{
     layout::{ Direction, Layout, Constraint },
 };
 
-use std::time::Duration;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 pub struct AppState {
     pub running: bool,
     pub mode: Mode,
     pub input_state: InputState,
     pub command_state: CommandState,
-    pub windows: WindowManager
+    pub windows: WindowManager,
+    pub audio: AudioEngine,
+    pub project: Project,
+    sync: ProjectSync,
+    /// Where the project was last written to or read from.
+    pub path: Option<PathBuf>,
+    /// Project revision as of the last write or read.
+    saved_revision: u64,
+    /// Feedback from the last command, shown in the command line.
+    pub message: Option<String>,
+    pub registers: Registers,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(args: &Args) -> Result<Self> {
+        // Losing sound should never stop anyone from editing.
+        let (audio, message) = match AudioEngine::new(&args.audio) {
+            Ok(audio) => (audio, None),
+            Err(err) => (
+                AudioEngine::new(&BackendKind::Null)?,
+                Some(format!("Audio backend '{}' failed ({err}), running silent", args.audio)),
+            ),
+        };
+
+        let project = Project::with_default_tracks();
+
+        let mut state = Self {
             running: true,
             mode: Mode::Normal,
             input_state: InputState::new(),
             command_state: CommandState::default(),
             windows: WindowManager::new(),
+            audio,
+            saved_revision: project.revision(),
+            project,
+            sync: ProjectSync::new(),
+            path: None,
+            message,
+            registers: Registers::default(),
+        };
+
+        state.sync_audio();
+        Ok(state)
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.project.revision() != self.saved_revision
+    }
+
+    /// Send whatever changed in the project over to the audio thread.
+    pub fn sync_audio(&mut self) {
+        if let Err(err) = self.sync.sync(&self.project, &self.audio) {
+            self.message = Some(format!("Could not load instrument: {err:#}"));
         }
     }
 }
@@ -47,45 +109,577 @@ impl App {
             if event::poll(Duration::from_millis(16))? &&
                 let Event::Key(key) = event::read()? {
                 Self::handle_keyevent(state, key);
+
+                // Everything typed in one go in insert mode is undone as one.
+                if matches!(state.mode, Mode::Insert) {
+                    state.project.settle();
+                } else {
+                    state.project.commit();
+                }
+
+                state.sync_audio();
             }
 
             terminal.draw(|frame| Self::render(frame, state))?;
+            state.audio.collect_garbage();
         }
 
         Ok(())
     }
 
     fn handle_keyevent(state: &mut AppState, key: KeyEvent) {
+        state.message = None;
+
         if state.windows.is_popup_active()
             && key.code == KeyCode::Esc {
             state.windows.pop_popup();
         }
 
-        if let Some(cmd) = VimInput::handle_keypress(state, key.code) {
+        if let Some(cmd) = VimInput::handle_keypress(state, key) {
             match cmd {
                 ResolvedCommand::Editor(editor_cmd) => {
                     Self::execute_editor_command(state, editor_cmd);
                 },
 
-                ResolvedCommand::Local(local_cmd) => {
-                    state.windows.handle_input(local_cmd);
-                },
+                ResolvedCommand::Local(local_cmd) => Self::local_command(state, local_cmd),
             }
         }
+
+        // A selection lasts as long as visual mode, or a command typed in it.
+        if matches!(state.mode, Mode::Normal | Mode::Insert) {
+            Self::local_command(state, LocalCommand::EndVisual);
+        }
+    }
+
+    fn local_command(state: &mut AppState, command: LocalCommand) {
+        let mut ctx = Context {
+            project: &mut state.project,
+            registers: &mut state.registers,
+            transport: state.audio.transport(),
+            meters: state.sync.meters(),
+        };
+
+        if let Some(editor_cmd) = state.windows.handle_input(command, &mut ctx) {
+            Self::execute_editor_command(state, editor_cmd);
+        }
     }
 
     fn execute_editor_command(state: &mut AppState, command: EditorCommand) {
         match command {
-            EditorCommand::Quit => state.running = false,
+            EditorCommand::Quit { force } => {
+                if state.is_modified() && !force {
+                    state.message = Some("No write since last change (add ! to override)".to_string());
+                } else {
+                    state.running = false;
+                }
+            },
 
             EditorCommand::Split { direction } => { 
                 state.windows.push_popup(SplitSelect::new(direction));
             },
-            
-            _ => ()
+
+            EditorCommand::Open { kind, direction } => {
+                state.windows.pop_popup();
+                state.windows.split_current_window(direction, kind.build(&state.project));
+            },
+
+            EditorCommand::Bpm { bpm, bar } => {
+                if !(MIN_TEMPO..=MAX_TEMPO).contains(&(bpm as f64)) {
+                    state.message = Some(format!("Tempo has to be from {MIN_TEMPO} to {MAX_TEMPO} BPM"));
+                    return;
+                }
+
+                let Some(tick) = Self::bar_start(state, bar) else { return };
+                state.project.tempo_map_mut().set_tempo(tick, bpm as f64);
+
+                state.message = Some(match bar {
+                    Some(bar) => format!("Tempo set to {bpm} BPM from bar {bar}"),
+                    None => format!("Tempo set to {bpm} BPM"),
+                });
+            },
+
+            EditorCommand::Meter { numerator, denominator, bar } => {
+                let Some(tick) = Self::bar_start(state, bar) else { return };
+                let signature = TimeSignature { numerator, denominator };
+                state.project.tempo_map_mut().set_meter(tick, signature);
+
+                state.message = Some(match bar {
+                    Some(bar) => format!("Time signature set to {numerator}/{denominator} from bar {bar}"),
+                    None => format!("Time signature set to {numerator}/{denominator}"),
+                });
+            },
+
+            EditorCommand::Delete { count, motion, register } => {
+                Self::operate(state, Operation { operator: Operator::Delete, count, motion, register });
+            },
+
+            EditorCommand::Yank { count, motion, register } => {
+                Self::operate(state, Operation { operator: Operator::Yank, count, motion, register });
+            },
+
+            EditorCommand::Paste { count, motion, register } => {
+                Self::operate(state, Operation { operator: Operator::Paste, count, motion, register });
+            },
+
+            EditorCommand::Transpose { semitones, count, motion } => {
+                let operator = Operator::Transpose { semitones };
+                Self::operate(state, Operation { operator, count, motion, register: None });
+            },
+
+            EditorCommand::Velocity { steps, count, motion } => {
+                let operator = Operator::Velocity { steps };
+                Self::operate(state, Operation { operator, count, motion, register: None });
+            },
+
+            EditorCommand::Nudge { steps, count, motion } => {
+                let operator = Operator::Nudge { steps };
+                Self::operate(state, Operation { operator, count, motion, register: None });
+            },
+
+            EditorCommand::Mute { count, motion } => {
+                Self::operate(state, Operation { operator: Operator::Mute, count, motion, register: None });
+            },
+
+            EditorCommand::Solo { count, motion } => {
+                Self::operate(state, Operation { operator: Operator::Solo, count, motion, register: None });
+            },
+
+            EditorCommand::MuteTrack { track } => {
+                let Some(id) = Self::find_channel(state, track.as_deref()) else { return };
+
+                let (name, muted) = match state.project.track(id) {
+                    Some(_) => {
+                        let Some(track) = state.project.track_mut(id) else { return };
+                        track.muted = !track.muted;
+                        (&track.name, track.muted)
+                    }
+
+                    None => {
+                        let Some(bus) = state.project.bus_mut(id) else { return };
+                        bus.muted = !bus.muted;
+                        (&bus.name, bus.muted)
+                    }
+                };
+
+                let verb = if muted { "muted" } else { "unmuted" };
+                state.message = Some(format!("{name} {verb}"));
+            },
+
+            EditorCommand::SoloTrack { track, exclusive } => {
+                let Some(id) = Self::find_track(state, track.as_deref()) else { return };
+                let Some(solo) = state.project.toggle_solo(id, exclusive) else { return };
+
+                let name = &state.project.track(id).unwrap().name;
+                state.message = Some(match (solo, exclusive) {
+                    (true, true) => format!("{name} soloed alone"),
+                    (true, false) => format!("{name} soloed"),
+                    (false, _) => format!("{name} unsoloed"),
+                });
+            },
+
+            EditorCommand::SoloSafe { track } => {
+                let Some(id) = Self::find_track(state, track.as_deref()) else { return };
+                let Some(track) = state.project.track_mut(id) else { return };
+                track.solo_safe = !track.solo_safe;
+
+                let verb = if track.solo_safe { "is solo safe" } else { "is no longer solo safe" };
+                state.message = Some(format!("{} {verb}", track.name));
+            },
+
+            EditorCommand::Arm { track } => {
+                let Some(id) = Self::find_track(state, track.as_deref()) else { return };
+                let Some(track) = state.project.track_mut(id) else { return };
+                track.armed = !track.armed;
+
+                let verb = if track.armed { "armed" } else { "disarmed" };
+                state.message = Some(format!("{} {verb}", track.name));
+            },
+
+            EditorCommand::AddBus { name } => {
+                if state.project.find_channel(&name).is_some() {
+                    state.message = Some(format!("There already is a '{name}'"));
+                    return;
+                }
+
+                state.project.add_bus(&name);
+                state.message = Some(format!("Added bus {name}"));
+            },
+
+            EditorCommand::RemoveBus { name } => {
+                let bus = state.project.find_channel(&name)
+                    .and_then(|id| state.project.remove_bus(id));
+
+                state.message = Some(match bus {
+                    Some(bus) => format!("Removed bus {}", bus.name),
+                    None => format!("No bus '{name}' to remove"),
+                });
+            },
+
+            EditorCommand::Output { bus } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(output) = Self::find_bus(state, &bus) else { return };
+                let output = if output == MASTER { Output::Master } else { Output::Bus(output) };
+
+                state.message = Some(match state.project.set_output(id, output) {
+                    Ok(()) => format!("{} goes to {bus}", state.project.channel_name(id).unwrap_or("?")),
+                    Err(err) => format!("Cannot route there: {err}"),
+                });
+            },
+
+            EditorCommand::Send { bus, level, pre_fader } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(to) = Self::find_bus(state, &bus) else { return };
+                let name = state.project.channel_name(id).unwrap_or("?").to_string();
+
+                state.message = Some(match level {
+                    Some(level) => {
+                        let send = Send { bus: to, level, pre_fader };
+                        match state.project.set_send(id, send) {
+                            Ok(()) => format!("{name} sends to {bus} at {level:+.1}dB"),
+                            Err(err) => format!("Cannot send there: {err}"),
+                        }
+                    }
+
+                    None if state.project.remove_send(id, to) => format!("{name} no longer sends to {bus}"),
+                    None => format!("{name} does not send to {bus}"),
+                });
+            },
+
+            EditorCommand::Insert { effect } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(inserts) = state.project.inserts_mut(id) else { return };
+                inserts.push(EffectSpec::new(effect));
+
+                let number = inserts.len();
+                let name = state.project.channel_name(id).unwrap_or("?");
+                state.message = Some(format!("Inserted {} on {name} as {number}", effect.name()));
+            },
+
+            EditorCommand::RemoveInsert { insert } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let removed = insert.checked_sub(1).and_then(|index| state.project.remove_insert(id, index));
+
+                state.message = Some(match removed {
+                    Some(effect) => format!("Removed {}", effect.kind.name()),
+                    None => format!("No effect {insert}"),
+                });
+            },
+
+            EditorCommand::Bypass { insert } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(inserts) = state.project.inserts_mut(id) else { return };
+
+                state.message = Some(match insert.checked_sub(1).and_then(|index| inserts.get_mut(index)) {
+                    Some(effect) => {
+                        effect.bypassed = !effect.bypassed;
+                        let verb = if effect.bypassed { "bypassed" } else { "back on" };
+                        format!("{} {verb}", effect.kind.name())
+                    }
+
+                    None => format!("No effect {insert}"),
+                });
+            },
+
+            EditorCommand::Param { insert, name, value } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(inserts) = state.project.inserts_mut(id) else { return };
+
+                state.message = Some(match insert.checked_sub(1).and_then(|index| inserts.get_mut(index)) {
+                    Some(effect) => match effect.set(&name, value) {
+                        Some(value) => format!("{} {name} set to {value}", effect.kind.name()),
+                        None => {
+                            let names: Vec<&str> = effect.kind.params().iter().map(|param| param.name).collect();
+                            format!("{} has {}", effect.kind.name(), names.join(", "))
+                        }
+                    },
+
+                    None => format!("No effect {insert}"),
+                });
+            },
+
+            EditorCommand::Automate { target } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let name = state.project.channel_name(id).unwrap_or("?").to_string();
+
+                state.message = Some(match state.project.automate(id, target.clone()) {
+                    Some(_) => format!("Automating {} on {name}", target.name()),
+                    None => format!("{name} has no {}", target.name()),
+                });
+            },
+
+            EditorCommand::Unautomate { target } => {
+                let Some(id) = Self::find_channel(state, None) else { return };
+                let Some(lane) = state.project.automation(id)
+                    .and_then(|lanes| lanes.iter().position(|lane| lane.target == target))
+                else {
+                    state.message = Some(format!("{} is not automated", target.name()));
+                    return;
+                };
+
+                if let Some(lanes) = state.project.automation_mut(id) {
+                    lanes.remove(lane);
+                }
+                state.message = Some(format!("{} no longer automated", target.name()));
+            },
+
+            EditorCommand::Quantize { settings, count, motion } => {
+                let operator = Operator::Quantize(settings);
+                let motion = motion.unwrap_or_else(|| Self::selection_or_pattern(state));
+                Self::operate(state, Operation { operator, count, motion, register: None });
+            },
+
+            EditorCommand::Humanize { timing, velocity, seed } => {
+                let seed = seed.unwrap_or_else(|| {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                    now.as_secs() ^ now.subsec_nanos() as u64
+                });
+
+                let operator = Operator::Humanize(Humanize { timing, velocity, seed });
+                let motion = Self::selection_or_pattern(state);
+                Self::operate(state, Operation { operator, count: 1, motion, register: None });
+            },
+
+            EditorCommand::Undo { count } => {
+                let done = state.project.undo(count);
+                state.message = Some(match done {
+                    0 => "Already at oldest change".to_string(),
+                    1 => "1 change undone".to_string(),
+                    _ => format!("{done} changes undone"),
+                });
+            },
+
+            EditorCommand::Redo { count } => {
+                let done = state.project.redo(count);
+                state.message = Some(match done {
+                    0 => "Already at newest change".to_string(),
+                    1 => "1 change redone".to_string(),
+                    _ => format!("{done} changes redone"),
+                });
+            },
+
+            EditorCommand::Play => Self::transport(state, TransportCommand::Play),
+            EditorCommand::Pause => Self::transport(state, TransportCommand::Pause),
+            EditorCommand::Stop => Self::transport(state, TransportCommand::Stop),
+
+            EditorCommand::TogglePlayback => {
+                let command = match state.audio.transport().state {
+                    PlayState::Playing => TransportCommand::Pause,
+                    _ => TransportCommand::Play,
+                };
+
+                Self::transport(state, command);
+            },
+
+            EditorCommand::Loop { bars } => {
+                let region = match bars {
+                    Some((first, last)) if first > last => {
+                        state.message = Some(format!("Bar {first} comes after bar {last}"));
+                        return;
+                    }
+
+                    Some((first, last)) => {
+                        let Some(start) = Self::bar_start(state, Some(first)) else { return };
+
+                        // Up to where the bar after the last one starts.
+                        let Some(end) = state.project.tempo_map().bar_tick(last) else {
+                            state.message = Some(format!("Bar {last} is out of range"));
+                            return;
+                        };
+
+                        Some(LoopRegion { start, end })
+                    }
+
+                    None => None,
+                };
+
+                state.project.set_loop_region(region);
+                state.message = Some(match bars {
+                    Some((first, last)) if first == last => format!("Looping bar {first}"),
+                    Some((first, last)) => format!("Looping bars {first}-{last}"),
+                    None => "Loop off".to_string(),
+                });
+            },
+
+            EditorCommand::Write { path, quit } => {
+                match Self::write(state, path) {
+                    Ok(path) => {
+                        state.message = Some(format!("\"{}\" written", path.display()));
+
+                        if quit {
+                            state.running = false;
+                        }
+                    }
+
+                    Err(err) => state.message = Some(format!("Write failed: {err}")),
+                }
+            },
+
+            EditorCommand::Edit { path, force } => {
+                state.message = Some(match Self::edit(state, path, force) {
+                    Ok(path) => format!("\"{}\" loaded", path.display()),
+                    Err(err) => format!("Edit failed: {err}"),
+                });
+            },
+
+            EditorCommand::Export { path, format } => {
+                state.message = Some(match Self::export(state, &path, format) {
+                    Ok(seconds) => format!(
+                        "Exported {seconds:.1}s of {format} audio to {}", path.display()
+                    ),
+                    Err(err) => format!("Export failed: {err}"),
+                });
+            },
+
+            EditorCommand::Import { path } => {
+                state.message = Some(match midi::import(&mut state.project, &path) {
+                    Ok(tracks) => format!("Imported {} tracks from {}", tracks.len(), path.display()),
+                    Err(err) => format!("Import failed: {err}"),
+                });
+            },
+
+            EditorCommand::ExportMidi { path } => {
+                state.message = Some(match midi::export(&state.project, &path) {
+                    Ok(()) => format!("Exported MIDI to {}", path.display()),
+                    Err(err) => format!("Export failed: {err}"),
+                });
+            },
         };
     }
 
+    /// Operators act on whatever the focused window has under its cursor.
+    fn operate(state: &mut AppState, operation: Operation) {
+        let mut ctx = Context {
+            project: &mut state.project,
+            registers: &mut state.registers,
+            transport: state.audio.transport(),
+            meters: state.sync.meters(),
+        };
+
+        state.message = state.windows.operate(operation, &mut ctx);
+    }
+
+    /// A track by number counting from one or by name, or the one being
+    /// edited in the focused window. Tells the user when there is none.
+    fn find_track(state: &mut AppState, track: Option<&str>) -> Option<TrackId> {
+        let tracks = state.project.tracks();
+        let found = match track {
+            Some(track) => match track.parse::<usize>() {
+                Ok(number) => number.checked_sub(1).and_then(|index| tracks.get(index)),
+                Err(_) => tracks.iter().find(|t| t.name.eq_ignore_ascii_case(track)),
+            }.map(|track| track.id),
+
+            None => state.windows.track(&state.project),
+        };
+
+        if found.is_none() {
+            state.message = Some(match track {
+                Some(track) => format!("No track '{track}'"),
+                None => "No track here".to_string(),
+            });
+        }
+
+        found
+    }
+
+    /// A track by number or a track or bus by name, or the one in the
+    /// focused window.
+    fn find_channel(state: &mut AppState, name: Option<&str>) -> Option<usize> {
+        let found = match name {
+            Some(name) if name.parse::<usize>().is_ok() => return Self::find_track(state, Some(name)),
+            Some(name) => state.project.find_channel(name),
+            None => state.windows.channel(&state.project),
+        };
+
+        if found.is_none() {
+            state.message = Some(match name {
+                Some(name) => format!("No track or bus '{name}'"),
+                None => "No track or bus here".to_string(),
+            });
+        }
+
+        found
+    }
+
+    /// Tick a bar counting from one starts at, or the start of the song
+    /// without one. Tells the user when the bar is too far out.
+    fn bar_start(state: &mut AppState, bar: Option<u32>) -> Option<u32> {
+        let Some(bar) = bar else { return Some(0) };
+        let tick = state.project.tempo_map().bar_tick(bar - 1);
+
+        if tick.is_none() {
+            state.message = Some(format!("Bar {bar} is out of range"));
+        }
+
+        tick
+    }
+
+    fn find_bus(state: &mut AppState, name: &str) -> Option<BusId> {
+        let found = state.project.find_channel(name)
+            .filter(|id| state.project.bus(*id).is_some());
+
+        if found.is_none() {
+            state.message = Some(format!("No bus '{name}'"));
+        }
+
+        found
+    }
+
+    /// What commands that change notes work on: the selection when there
+    /// is one, otherwise the whole pattern.
+    fn selection_or_pattern(state: &AppState) -> Motion {
+        match state.windows.selection(&state.project) {
+            Some(_) => Motion::Selection,
+            None => Motion::Inner(TextObject::Pattern),
+        }
+    }
+
+    fn transport(state: &AppState, command: TransportCommand) {
+        // Nothing depends on it getting there, pressing again will do.
+        state.audio.send(AudioCommand::Transport(command));
+    }
+
+    fn write(state: &mut AppState, path: Option<PathBuf>) -> Result<PathBuf> {
+        let path = path.or_else(|| state.path.clone())
+            .ok_or_else(|| eyre!("no file name"))?;
+
+        file::save(&path, &state.project, &state.windows.layout())?;
+
+        state.path = Some(path.clone());
+        state.saved_revision = state.project.revision();
+        Ok(path)
+    }
+
+    /// Replace the project and window layout with the ones saved in a file.
+    fn edit(state: &mut AppState, path: Option<PathBuf>, force: bool) -> Result<PathBuf> {
+        if state.is_modified() && !force {
+            return Err(eyre!("no write since last change (add ! to override)"));
+        }
+
+        let path = path.or_else(|| state.path.clone())
+            .ok_or_else(|| eyre!("no file name"))?;
+
+        let (project, layout) = file::load(&path)?;
+
+        state.windows = WindowManager::from_layout(&layout, &project);
+        state.saved_revision = project.revision();
+        state.project = project;
+        state.sync.invalidate();
+        state.path = Some(path.clone());
+        Ok(path)
+    }
+
+    /// Render the session offline to a WAV file, returning its length in
+    /// seconds.
+    fn export(state: &AppState, path: &Path, format: WavFormat) -> Result<f64> {
+        let sample_rate = state.audio.sample_rate();
+        let graph = state.project.build_graph(sample_rate);
+        let end = Some(state.project.length());
+        let mut renderer = Renderer::offline(sample_rate, graph, state.project.tempo_map().clone(), end);
+
+        let frames = export::export_wav(&mut renderer, path, format)?;
+        Ok(frames as f64 / sample_rate as f64)
+    }
+
     fn render(frame: &mut Frame, state: &mut AppState) {
         let base_layout = Layout::default()
             .direction(Direction::Vertical)
@@ -97,6 +691,12 @@ impl App {
 
         CommandLine::render(frame, base_layout[1], state);
 
-        state.windows.render_layout(frame, base_layout[0]);
+        let ctx = Context {
+            project: &mut state.project,
+            registers: &mut state.registers,
+            transport: state.audio.transport(),
+            meters: state.sync.meters(),
+        };
+        state.windows.render_layout(frame, base_layout[0], &ctx);
     }
 }
@@ -0,0 +1,73 @@
+use color_eyre::eyre::{eyre, Result};
+
+use crate::audio::backend::BackendKind;
+
+const USAGE: &str = "usage: daw [--audio cpal|null|file:<path>]";
+
+/// Command line options. The `DAW_AUDIO` environment variable sets the
+/// default audio backend, and `--audio` overrides it.
+pub struct Args {
+    pub audio: BackendKind,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::var("DAW_AUDIO").ok(), std::env::args().skip(1))
+    }
+
+    /// Options from the value of `DAW_AUDIO`, if set, and the arguments
+    /// after the program name.
+    fn parse_from(env: Option<String>, mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut audio = match env {
+            Some(value) => Self::parse_backend(&value)?,
+            None => BackendKind::Cpal,
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "--audio" {
+                let value = args.next().ok_or_else(|| eyre!(USAGE))?;
+                audio = Self::parse_backend(&value)?;
+            } else if let Some(value) = arg.strip_prefix("--audio=") {
+                audio = Self::parse_backend(value)?;
+            } else {
+                return Err(eyre!("unexpected argument '{arg}'\n{USAGE}"));
+            }
+        }
+
+        Ok(Self { audio })
+    }
+
+    fn parse_backend(value: &str) -> Result<BackendKind> {
+        value.parse().map_err(|err: String| eyre!(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn parse(env: Option<&str>, args: &[&str]) -> Result<BackendKind> {
+        let args = args.iter().map(|arg| arg.to_string());
+        Ok(Args::parse_from(env.map(str::to_string), args)?.audio)
+    }
+
+    #[test]
+    fn flag_overrides_environment() {
+        assert_eq!(parse(None, &[]).unwrap(), BackendKind::Cpal);
+        assert_eq!(parse(Some("null"), &[]).unwrap(), BackendKind::Null);
+        assert_eq!(parse(Some("null"), &["--audio", "cpal"]).unwrap(), BackendKind::Cpal);
+        assert_eq!(parse(None, &["--audio=file:out.wav"]).unwrap(), BackendKind::File {
+            path: PathBuf::from("out.wav"),
+        });
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(parse(Some("speakers"), &[]).is_err());
+        assert!(parse(None, &["--audio"]).is_err());
+        assert!(parse(None, &["--audio=file:"]).is_err());
+        assert!(parse(None, &["--verbose"]).is_err());
+    }
+}
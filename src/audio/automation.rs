@@ -0,0 +1,68 @@
+use crate::dsp::db_to_gain;
+use crate::effect::Param;
+use crate::project::automation::Envelope;
+
+/// The most frames parameters other than the fader go without being set
+/// again while they move. Breakpoints still land on their exact frame.
+pub const CONTROL_FRAMES: usize = 32;
+
+/// What a lane sets on a channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destination {
+    Gain,
+    Pan,
+    /// By its place in the instrument's parameters.
+    Instrument(usize),
+    Insert { insert: usize, param: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomationLane {
+    pub destination: Destination,
+    /// For turning the envelope's positions into values.
+    pub param: Param,
+    pub envelope: Envelope,
+}
+
+/// Everything automated on a channel, ready for the audio thread.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Automation {
+    pub lanes: Vec<AutomationLane>,
+}
+
+impl Automation {
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    /// Where a destination is at a song tick, if anything moves it.
+    pub fn value(&self, destination: Destination, tick: f64) -> Option<f64> {
+        let lane = self.lanes.iter().find(|lane| lane.destination == destination)?;
+        Some(lane.param.denormalize(lane.envelope.value_at(tick)?))
+    }
+
+    /// Whether the fader has to be worked out frame by frame.
+    pub fn moves_fader(&self) -> bool {
+        self.lanes.iter().any(|lane| matches!(lane.destination, Destination::Gain | Destination::Pan))
+    }
+
+    /// Linear gain and pan at a song tick, where they are automated.
+    pub fn fader(&self, tick: f64) -> (Option<f32>, Option<f32>) {
+        let gain = self.value(Destination::Gain, tick).map(|db| db_to_gain(db) as f32);
+        let pan = self.value(Destination::Pan, tick).map(|pan| pan as f32);
+        (gain, pan)
+    }
+
+    /// Frames that can be rendered from `tick` before the next breakpoint
+    /// of any lane, at most `frames` and at least one.
+    pub fn frames_until_point(&self, tick: f64, ticks_per_frame: f64, frames: usize) -> usize {
+        if ticks_per_frame <= 0.0 {
+            return frames;
+        }
+
+        self.lanes.iter()
+            .filter_map(|lane| lane.envelope.next_point(tick))
+            .map(|next| ((next as f64 - tick) / ticks_per_frame).ceil().max(1.0) as usize)
+            .fold(frames, usize::min)
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    FromSample, SizedSample,
+    traits::{DeviceTrait, HostTrait, StreamTrait}
+};
+
+use color_eyre::eyre::{eyre, Result};
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::audio::{Frame, MAX_BLOCK, renderer::Renderer};
+
+/// Sample rate used by backends that are not tied to a device.
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const BLOCK_SIZE: usize = 512;
+
+/// Where the rendered audio goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    /// The default cpal output device.
+    Cpal,
+    /// Render in real time and throw the result away.
+    Null,
+    /// Render in real time into a WAV file, or raw 32-bit float
+    /// little-endian samples for any other extension.
+    File { path: PathBuf },
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpal" | "device" => Ok(BackendKind::Cpal),
+            "null" | "none" => Ok(BackendKind::Null),
+
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(BackendKind::File {
+                    path: PathBuf::from(path),
+                }),
+
+                _ => Err(format!(
+                    "unknown audio backend '{s}', expected cpal, null or file:<path>"
+                )),
+            }
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackendKind::Cpal => write!(f, "cpal"),
+            BackendKind::Null => write!(f, "null"),
+            BackendKind::File { path } => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+/// A running output. The renderer is driven for as long as it is alive.
+pub trait Backend {
+    fn sample_rate(&self) -> u32;
+}
+
+pub fn start(kind: &BackendKind, renderer: Renderer) -> Result<Box<dyn Backend>> {
+    Ok(match kind {
+        BackendKind::Cpal => Box::new(CpalBackend::start(renderer)?),
+        BackendKind::Null => Box::new(ClockedBackend::null(renderer)),
+        BackendKind::File { path } => Box::new(ClockedBackend::file(renderer, path)?),
+    })
+}
+
+pub struct CpalBackend {
+    _stream: cpal::Stream,
+    sample_rate: u32,
+}
+
+impl CpalBackend {
+    pub fn start(mut renderer: Renderer) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = host.default_output_device()
+            .ok_or_else(|| eyre!("no output device available"))?;
+
+        let config = device.default_output_config()?;
+        let sample_rate = config.sample_rate().0;
+        renderer.set_sample_rate(sample_rate);
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::I8 => build_stream::<i8>(&device, &config.into(), renderer),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), renderer),
+            cpal::SampleFormat::I32 => build_stream::<i32>(&device, &config.into(), renderer),
+            cpal::SampleFormat::I64 => build_stream::<i64>(&device, &config.into(), renderer),
+
+            cpal::SampleFormat::U8 => build_stream::<u8>(&device, &config.into(), renderer),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), renderer),
+            cpal::SampleFormat::U32 => build_stream::<u32>(&device, &config.into(), renderer),
+            cpal::SampleFormat::U64 => build_stream::<u64>(&device, &config.into(), renderer),
+
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), renderer),
+            cpal::SampleFormat::F64 => build_stream::<f64>(&device, &config.into(), renderer),
+
+            sample_format => return Err(eyre!("unsupported sample format '{sample_format}'")),
+        }?;
+
+        stream.play()?;
+
+        Ok(Self {
+            _stream: stream,
+            sample_rate,
+        })
+    }
+}
+
+impl Backend for CpalBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut renderer: Renderer,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let mut buffer: Vec<Frame> = vec![[0.0; 2]; MAX_BLOCK];
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            // Devices pick their own block size, so long ones are rendered
+            // in parts rather than growing the buffer in the callback.
+            for part in data.chunks_mut(MAX_BLOCK * channels) {
+                let frames = part.len() / channels;
+                renderer.render(&mut buffer[..frames]);
+                write_frames(part, channels, &buffer[..frames]);
+            }
+        },
+        |err| eprintln!("Audio error: {err}"),
+        None,
+    )?;
+
+    Ok(stream)
+}
+
+/// Interleave stereo frames into a device buffer with any channel count.
+/// Mono devices get the average of both sides, extra channels are silent.
+fn write_frames<T>(data: &mut [T], channels: usize, frames: &[Frame])
+where
+    T: SizedSample + FromSample<f32>,
+{
+    for (out, [left, right]) in data.chunks_mut(channels).zip(frames) {
+        match out {
+            [mono] => *mono = T::from_sample((left + right) * 0.5),
+
+            [l, r, rest @ ..] => {
+                *l = T::from_sample(*left);
+                *r = T::from_sample(*right);
+
+                for sample in rest {
+                    *sample = T::EQUILIBRIUM;
+                }
+            }
+
+            [] => (),
+        }
+    }
+}
+
+/// Drives the renderer from its own thread at the speed a sound card would,
+/// so the transport clock keeps running without any hardware.
+pub struct ClockedBackend {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    sample_rate: u32,
+}
+
+impl ClockedBackend {
+    pub fn null(renderer: Renderer) -> Self {
+        Self::spawn(renderer, FileSink::None)
+    }
+
+    pub fn file(renderer: Renderer, path: &Path) -> Result<Self> {
+        let sink = FileSink::create(path, DEFAULT_SAMPLE_RATE)
+            .map_err(|err| eyre!("could not create '{}': {err}", path.display()))?;
+
+        Ok(Self::spawn(renderer, sink))
+    }
+
+    fn spawn(mut renderer: Renderer, mut sink: FileSink) -> Self {
+        let sample_rate = DEFAULT_SAMPLE_RATE;
+        renderer.set_sample_rate(sample_rate);
+
+        let running = Arc::new(AtomicBool::new(true));
+        let block_duration = Duration::from_secs_f64(BLOCK_SIZE as f64 / sample_rate as f64);
+
+        let thread = {
+            let running = running.clone();
+
+            thread::spawn(move || {
+                let mut block = [[0.0; 2]; BLOCK_SIZE];
+                let mut deadline = Instant::now();
+
+                while running.load(Ordering::Relaxed) {
+                    renderer.render(&mut block);
+
+                    // A failing disk should not stop the clock.
+                    if sink.write(&block).is_err() {
+                        sink = FileSink::None;
+                    }
+
+                    deadline += block_duration;
+                    if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                        thread::sleep(wait);
+                    }
+                }
+
+                let _ = sink.finish();
+            })
+        };
+
+        Self {
+            running,
+            thread: Some(thread),
+            sample_rate,
+        }
+    }
+}
+
+impl Backend for ClockedBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl Drop for ClockedBackend {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+enum FileSink {
+    None,
+    Wav(WavWriter<BufWriter<File>>),
+    Raw(BufWriter<File>),
+}
+
+impl FileSink {
+    fn create(path: &Path, sample_rate: u32) -> Result<Self> {
+        let is_wav = path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+
+        if is_wav {
+            let spec = WavSpec {
+                channels: 2,
+                sample_rate,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            };
+
+            Ok(FileSink::Wav(WavWriter::create(path, spec)?))
+        } else {
+            Ok(FileSink::Raw(BufWriter::new(File::create(path)?)))
+        }
+    }
+
+    fn write(&mut self, block: &[Frame]) -> Result<()> {
+        match self {
+            FileSink::None => (),
+
+            FileSink::Wav(writer) => {
+                for sample in block.iter().flatten() {
+                    writer.write_sample(*sample)?;
+                }
+            }
+
+            FileSink::Raw(writer) => {
+                for sample in block.iter().flatten() {
+                    writer.write_all(&sample.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            FileSink::None => (),
+            FileSink::Wav(writer) => writer.finalize()?,
+            FileSink::Raw(mut writer) => writer.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_round_trip_through_strings() {
+        for kind in [
+            BackendKind::Cpal,
+            BackendKind::Null,
+            BackendKind::File { path: PathBuf::from("takes/out.wav") },
+        ] {
+            assert_eq!(kind.to_string().parse::<BackendKind>(), Ok(kind));
+        }
+
+        assert_eq!("device".parse(), Ok(BackendKind::Cpal));
+        assert_eq!("none".parse(), Ok(BackendKind::Null));
+        assert!("file:".parse::<BackendKind>().is_err());
+    }
+
+    const FRAMES: [Frame; 2] = [[0.5, -0.5], [1.0, 0.0]];
+
+    /// Write `FRAMES` through a sink for a file in the temp directory and
+    /// hand back its path.
+    fn write(name: &str, is_wav: bool) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let mut sink = FileSink::create(&path, DEFAULT_SAMPLE_RATE).unwrap();
+        assert_eq!(matches!(sink, FileSink::Wav(_)), is_wav, "{name}");
+
+        sink.write(&FRAMES).unwrap();
+        sink.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn wav_files_get_a_header() {
+        for name in ["daw_sink.wav", "daw_sink_upper.WAV"] {
+            let path = write(name, true);
+            let mut reader = hound::WavReader::open(&path).unwrap();
+
+            assert_eq!(reader.spec(), WavSpec {
+                channels: 2,
+                sample_rate: DEFAULT_SAMPLE_RATE,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            });
+
+            let samples: Vec<f32> = reader.samples().map(|sample| sample.unwrap()).collect();
+            assert_eq!(samples, FRAMES.concat());
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn other_files_get_bare_samples() {
+        let path = write("daw_sink.raw", false);
+        let bytes: Vec<u8> = FRAMES.iter().flatten().flat_map(|sample| sample.to_le_bytes()).collect();
+
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use color_eyre::eyre::{eyre, Result};
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::audio::{Frame, renderer::Renderer};
+
+const BLOCK_SIZE: usize = 1024;
+
+/// Once every node has finished, keep rendering until the output has been
+/// silent for a whole block so release and effect tails are not cut off.
+const SILENCE_THRESHOLD: f32 = 1.0e-5;
+const MAX_TAIL_SECONDS: u64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WavFormat {
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl WavFormat {
+    fn spec(self, sample_rate: u32) -> WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            WavFormat::Int16 => (16, SampleFormat::Int),
+            WavFormat::Int24 => (24, SampleFormat::Int),
+            WavFormat::Float32 => (32, SampleFormat::Float),
+        };
+
+        WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+}
+
+impl FromStr for WavFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "16" => Ok(WavFormat::Int16),
+            "24" => Ok(WavFormat::Int24),
+            "32f" | "f32" | "float" => Ok(WavFormat::Float32),
+            _ => Err(format!("unknown wav format '{s}', expected 16, 24 or 32f")),
+        }
+    }
+}
+
+impl fmt::Display for WavFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WavFormat::Int16 => write!(f, "16-bit"),
+            WavFormat::Int24 => write!(f, "24-bit"),
+            WavFormat::Float32 => write!(f, "32-bit float"),
+        }
+    }
+}
+
+/// Render everything in `renderer` to a stereo WAV file as fast as possible.
+/// Returns the number of frames written.
+pub fn export_wav(
+    renderer: &mut Renderer,
+    path: &Path,
+    format: WavFormat,
+) -> Result<u64> {
+    let sample_rate = renderer.sample_rate();
+    let length = renderer.length();
+    let max_frames = length + MAX_TAIL_SECONDS * sample_rate as u64;
+
+    let mut writer = WavWriter::create(path, format.spec(sample_rate))
+        .map_err(|err| eyre!("could not create '{}': {err}", path.display()))?;
+
+    let mut block = [[0.0; 2]; BLOCK_SIZE];
+    let mut written = 0;
+
+    while written < max_frames {
+        let frames = (max_frames - written).min(BLOCK_SIZE as u64) as usize;
+        let block = &mut block[..frames];
+
+        renderer.render(block);
+        write_block(&mut writer, block, format)?;
+        written += frames as u64;
+
+        if written >= length && is_silent(block) {
+            break;
+        }
+    }
+
+    writer.finalize()?;
+    Ok(written)
+}
+
+fn write_block<W>(
+    writer: &mut WavWriter<W>,
+    block: &[Frame],
+    format: WavFormat,
+) -> Result<()>
+where
+    W: std::io::Write + std::io::Seek,
+{
+    for sample in block.iter().flatten() {
+        let sample = sample.clamp(-1.0, 1.0);
+
+        match format {
+            WavFormat::Int16 => writer.write_sample((sample * i16::MAX as f32) as i16)?,
+            WavFormat::Int24 => writer.write_sample((sample * 8_388_607.0) as i32)?,
+            WavFormat::Float32 => writer.write_sample(sample)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn is_silent(block: &[Frame]) -> bool {
+    block.iter()
+        .flatten()
+        .all(|sample| sample.abs() < SILENCE_THRESHOLD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::audio::{
+        graph::{Graph, Node, ProcessContext},
+        mixer::{Channel, LevelMeter, Strip},
+    };
+    use crate::project::tempo::TempoMap;
+
+    /// Plays a half-scale square wave for a fixed number of frames.
+    struct Burst {
+        frames: u64,
+    }
+
+    impl Node for Burst {
+        fn process(&mut self, ctx: &ProcessContext, out: &mut [Frame]) {
+            for (i, frame) in out.iter_mut().enumerate() {
+                let t = ctx.clock + i as u64;
+                if t < self.frames {
+                    let value = if (t / 50).is_multiple_of(2) { 0.5 } else { -0.5 };
+                    *frame = [value, -value];
+                }
+            }
+        }
+
+        fn length(&self, _ctx: &ProcessContext) -> u64 {
+            self.frames
+        }
+    }
+
+    fn render(format: WavFormat, name: &str) -> (WavSpec, Vec<f32>) {
+        let mut graph = Graph::new();
+        let burst = Box::new(Burst { frames: 3000 });
+        let meter = Arc::new(LevelMeter::new());
+        graph.add_channel(Box::new(Channel::new(0, burst, Vec::new(), Box::default(), Strip::default(), meter)));
+
+        let mut renderer = Renderer::offline(48000, graph, TempoMap::default(), None);
+        let path = std::env::temp_dir().join(name);
+        export_wav(&mut renderer, &path, format).unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
+            SampleFormat::Int => {
+                let scale = (1 << (spec.bits_per_sample - 1)) as f32;
+                reader.samples::<i32>().map(|s| s.unwrap() as f32 / scale).collect()
+            }
+        };
+
+        std::fs::remove_file(&path).unwrap();
+        (spec, samples)
+    }
+
+    #[test]
+    fn exports_every_format_deterministically() {
+        for (format, name) in [
+            (WavFormat::Int16, "daw_export_16.wav"),
+            (WavFormat::Int24, "daw_export_24.wav"),
+            (WavFormat::Float32, "daw_export_32f.wav"),
+        ] {
+            let (spec, samples) = render(format, name);
+
+            assert_eq!(spec, format.spec(48000));
+            // The burst ends mid-block, so one silent block follows it.
+            assert_eq!(samples.len(), 2 * 3 * BLOCK_SIZE + 2 * BLOCK_SIZE);
+            assert!((samples[0] - 0.5).abs() < 1.0e-3);
+            assert!((samples[1] + 0.5).abs() < 1.0e-3);
+            assert!((samples[2 * 50] + 0.5).abs() < 1.0e-3);
+            assert!(samples[2 * 3000..].iter().all(|s| *s == 0.0));
+        }
+    }
+}
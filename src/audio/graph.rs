@@ -0,0 +1,253 @@
+use std::mem;
+
+use crate::audio::{
+    Frame, Garbage,
+    automation::Automation,
+    mixer::{Channel, Insert, Strip},
+    sequencer::Sequence,
+};
+
+/// Per-block information handed to every node by the renderer.
+pub struct ProcessContext {
+    pub sample_rate: u32,
+    /// Frames rendered since the engine started, at the start of this block.
+    pub clock: u64,
+    /// Song position at the start of this block, in ticks.
+    pub tick: f64,
+    /// How far the song position moves with every frame at the current tempo.
+    pub ticks_per_frame: f64,
+    /// Beats per minute at the song position, stopped or not.
+    pub tempo: f64,
+}
+
+/// Messages addressed to a single node.
+pub enum NodeMessage {
+    Sequence(Box<Sequence>),
+    SetStrip(Strip),
+    /// A whole new insert chain.
+    SetInserts(Vec<Insert>),
+    SetParam { insert: usize, param: usize, value: f64 },
+    Bypass { insert: usize, bypassed: bool },
+    /// Set a parameter of the instrument, by its place in its parameters.
+    SetInstrumentParam { param: usize, value: f64 },
+    SetAutomation(Box<Automation>),
+}
+
+/// Anything that can produce audio on the audio thread.
+///
+/// `process` is called from the real-time callback: it must not block,
+/// lock or allocate. `out` holds whatever is routed into the node, which
+/// is silence for anything but a bus.
+pub trait Node: Send {
+    fn process(&mut self, ctx: &ProcessContext, out: &mut [Frame]);
+
+    /// Anything the node replaces is handed back to be freed on the UI
+    /// thread.
+    fn handle(&mut self, _message: NodeMessage) -> Option<Garbage> {
+        None
+    }
+
+    /// Set a parameter by its place in the node's parameters, for
+    /// automation.
+    fn set_param(&mut self, _param: usize, _value: f64) {}
+
+    /// Frames from the start of playback until this node has nothing left
+    /// to play. Offline renders stop once every node has finished.
+    fn length(&self, _ctx: &ProcessContext) -> u64 {
+        0
+    }
+}
+
+/// Where a channel's output goes, and what it sends elsewhere.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Route {
+    pub id: usize,
+    /// The channel to mix into, straight out without one.
+    pub output: Option<usize>,
+    pub sends: Vec<SendRoute>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SendRoute {
+    pub to: usize,
+    /// Linear gain.
+    pub gain: f32,
+    pub pre_fader: bool,
+}
+
+/// Every channel's route, in the order the channels have to render in for
+/// each of them to have all of its input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Routing {
+    pub routes: Vec<Route>,
+}
+
+/// The channels the renderer mixes down to the output.
+pub struct Graph {
+    /// In render order, so that channels only ever feed later ones. Boxed
+    /// so that they come and go without allocating on the audio thread.
+    #[allow(clippy::vec_box)]
+    channels: Vec<Box<Channel>>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self {
+            channels: Vec::with_capacity(64),
+        }
+    }
+
+    /// A channel replacing one with the same id takes over its route and
+    /// place in the render order.
+    pub fn add_channel(&mut self, mut channel: Box<Channel>) -> Option<Box<Channel>> {
+        match self.channels.iter_mut().find(|old| old.id() == channel.id()) {
+            Some(old) => {
+                mem::swap(&mut old.route, &mut channel.route);
+                Some(mem::replace(old, channel))
+            }
+
+            None => {
+                self.channels.push(channel);
+                None
+            }
+        }
+    }
+
+    pub fn remove_channel(&mut self, id: usize) -> Option<Box<Channel>> {
+        let index = self.channels.iter().position(|channel| channel.id() == id)?;
+        Some(self.channels.remove(index))
+    }
+
+    pub fn send(&mut self, id: usize, message: NodeMessage) -> Option<Garbage> {
+        let channel = self.channels.iter_mut().find(|channel| channel.id() == id)?;
+        channel.handle(message)
+    }
+
+    /// Put the channels in the new order and routes. Returns the routes
+    /// they had, to be freed elsewhere.
+    pub fn set_routing(&mut self, mut routing: Box<Routing>) -> Box<Routing> {
+        // Channels without a route have not been routed yet, and go last.
+        self.channels.sort_unstable_by_key(|channel| {
+            routing.routes.iter()
+                .position(|route| route.id == channel.id())
+                .unwrap_or(usize::MAX)
+        });
+
+        for route in routing.routes.iter_mut() {
+            let id = route.id;
+            if let Some(channel) = self.channels.iter_mut().find(|channel| channel.id() == id) {
+                mem::swap(&mut channel.route, route);
+            }
+        }
+
+        routing
+    }
+
+    pub fn length(&self, ctx: &ProcessContext) -> u64 {
+        self.channels.iter()
+            .map(|channel| channel.length(ctx))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Mix `from` into `to`.
+    fn mix(to: &mut [Frame], from: &[Frame], gain: f32) {
+        for (mix, [left, right]) in to.iter_mut().zip(from.iter()) {
+            mix[0] += left * gain;
+            mix[1] += right * gain;
+        }
+    }
+
+    /// Mix a channel's buffer into those of the later channels it sends to.
+    fn send_to(channel: &Channel, later: &mut [Box<Channel>], frames: usize, pre_fader: bool) {
+        let sends = channel.route.sends.iter().filter(|send| send.pre_fader == pre_fader);
+
+        for send in sends {
+            if let Some(to) = later.iter_mut().find(|to| to.id() == send.to) {
+                Self::mix(&mut to.buffer[..frames], &channel.buffer[..frames], send.gain);
+            }
+        }
+    }
+
+    pub fn process(&mut self, ctx: &ProcessContext, out: &mut [Frame]) {
+        let frames = out.len();
+        out.fill([0.0; 2]);
+
+        for channel in self.channels.iter_mut() {
+            channel.clear(frames);
+        }
+
+        for i in 0..self.channels.len() {
+            let (done, later) = self.channels.split_at_mut(i + 1);
+            let channel = &mut done[i];
+
+            channel.process(ctx, frames);
+            Self::send_to(channel, later, frames, true);
+            channel.apply_fader(ctx, frames);
+            Self::send_to(channel, later, frames, false);
+
+            // Anything routed somewhere that is not there plays straight out.
+            let output = channel.route.output
+                .and_then(|id| later.iter_mut().find(|to| to.id() == id));
+
+            match output {
+                Some(to) => Self::mix(&mut to.buffer[..frames], &channel.buffer[..frames], 1.0),
+                None => Self::mix(out, &channel.buffer[..frames], 1.0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::audio::mixer::{BusNode, LevelMeter};
+
+    struct Constant;
+
+    impl Node for Constant {
+        fn process(&mut self, _ctx: &ProcessContext, out: &mut [Frame]) {
+            out.fill([1.0, 1.0]);
+        }
+    }
+
+    fn channel(id: usize, node: Box<dyn Node>, gain: f32) -> Box<Channel> {
+        let strip = Strip { gain, ..Strip::default() };
+        let meter = Arc::new(LevelMeter::new());
+        Box::new(Channel::new(id, node, Vec::new(), Box::default(), strip, meter))
+    }
+
+    #[test]
+    fn routes_through_buses_and_sends() {
+        let mut graph = Graph::new();
+
+        // Added in the wrong order, the routing sorts them out.
+        graph.add_channel(channel(2, Box::new(BusNode), 1.0));
+        graph.add_channel(channel(1, Box::new(BusNode), 0.5));
+        graph.add_channel(channel(0, Box::new(Constant), 0.5));
+
+        let send = |pre_fader| SendRoute { to: 2, gain: 0.5, pre_fader };
+        let routing = |pre_fader| Box::new(Routing {
+            routes: vec![
+                Route { id: 0, output: Some(1), sends: vec![send(pre_fader)] },
+                Route { id: 1, output: None, sends: Vec::new() },
+                Route { id: 2, output: None, sends: Vec::new() },
+            ],
+        });
+
+        let ctx = ProcessContext { sample_rate: 48000, clock: 0, tick: 0.0, ticks_per_frame: 0.0, tempo: 120.0 };
+        let mut out = [[0.0; 2]; 16];
+
+        // Through the group at half and half again, and a quarter sent.
+        graph.set_routing(routing(false));
+        graph.process(&ctx, &mut out);
+        assert_eq!(out[0], [0.5, 0.5]);
+
+        // Before the fader the send is twice as loud.
+        graph.set_routing(routing(true));
+        graph.process(&ctx, &mut out);
+        assert_eq!(out[0], [0.75, 0.75]);
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use crate::audio::{
+    Frame, Garbage, MAX_BLOCK,
+    automation::{Automation, CONTROL_FRAMES, Destination},
+    graph::{Node, NodeMessage, ProcessContext, Route},
+};
+use crate::effect::Effect;
+
+/// Fader settings of a channel, as the audio thread uses them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strip {
+    /// Linear gain.
+    pub gain: f32,
+    /// From -1 for hard left to 1 for hard right.
+    pub pan: f32,
+    /// Silences the channel whatever the gain is, automated or not.
+    pub muted: bool,
+}
+
+impl Default for Strip {
+    fn default() -> Self {
+        Self { gain: 1.0, pan: 0.0, muted: false }
+    }
+}
+
+impl Strip {
+    /// Gains of the left and right side. Panning turns the other side down,
+    /// so a centred channel plays at unity.
+    fn gains(&self) -> [f32; 2] {
+        let gain = if self.muted { 0.0 } else { self.gain };
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [gain * (1.0 - pan).min(1.0), gain * (1.0 + pan).min(1.0)]
+    }
+}
+
+/// Levels of both sides of a channel, linear.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Levels {
+    pub peak: [f32; 2],
+    pub rms: [f32; 2],
+}
+
+/// Levels measured on the audio thread, readable from the UI without
+/// locking.
+pub struct LevelMeter {
+    /// Bits of the highest peak since the UI last looked. Bits of positive
+    /// floats sort the same way as the floats do.
+    peak: [AtomicU32; 2],
+    /// Bits of the RMS level of the last block.
+    rms: [AtomicU32; 2],
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self {
+            peak: Default::default(),
+            rms: Default::default(),
+        }
+    }
+
+    fn publish(&self, block: &[Frame]) {
+        if block.is_empty() {
+            return;
+        }
+
+        for side in 0..2 {
+            let peak = block.iter().fold(0.0f32, |peak, frame| peak.max(frame[side].abs()));
+            let power = block.iter().map(|frame| frame[side] * frame[side]).sum::<f32>();
+            let rms = (power / block.len() as f32).sqrt();
+
+            self.peak[side].fetch_max(peak.to_bits(), Ordering::Relaxed);
+            self.rms[side].store(rms.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Levels since the last call, starting the peaks over.
+    pub fn take(&self) -> Levels {
+        let peak = |side: usize| f32::from_bits(self.peak[side].swap(0, Ordering::Relaxed));
+        let rms = |side: usize| f32::from_bits(self.rms[side].load(Ordering::Relaxed));
+
+        Levels {
+            peak: [peak(0), peak(1)],
+            rms: [rms(0), rms(1)],
+        }
+    }
+}
+
+/// Meters of every channel by node id, for the UI to read.
+pub type Meters = HashMap<usize, Arc<LevelMeter>>;
+
+/// An effect in a channel's insert chain.
+pub struct Insert {
+    pub effect: Box<dyn Effect>,
+    pub bypassed: bool,
+}
+
+/// A node with its inserts, automation, fader and meter, and where its
+/// output goes.
+pub struct Channel {
+    id: usize,
+    node: Box<dyn Node>,
+    inserts: Vec<Insert>,
+    automation: Box<Automation>,
+    sample_rate: u32,
+    strip: Strip,
+    meter: Arc<LevelMeter>,
+    pub(super) route: Route,
+    /// What is routed into the channel, then what comes out of it.
+    pub(super) buffer: Vec<Frame>,
+}
+
+impl Channel {
+    pub fn new(
+        id: usize,
+        node: Box<dyn Node>,
+        inserts: Vec<Insert>,
+        automation: Box<Automation>,
+        strip: Strip,
+        meter: Arc<LevelMeter>,
+    ) -> Self {
+        Self {
+            id,
+            node,
+            inserts,
+            automation,
+            sample_rate: 0,
+            strip,
+            meter,
+            route: Route::default(),
+            buffer: vec![[0.0; 2]; MAX_BLOCK],
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Silence the input for a block of `frames`, at most `MAX_BLOCK`.
+    pub(super) fn clear(&mut self, frames: usize) {
+        let frames = frames.min(self.buffer.len());
+        self.buffer[..frames].fill([0.0; 2]);
+    }
+
+    /// Run the node over what was routed in, then the inserts, all before
+    /// the fader. With automation the block is split up so that parameters
+    /// follow their lanes.
+    pub(super) fn process(&mut self, ctx: &ProcessContext, frames: usize) {
+        if ctx.sample_rate != self.sample_rate {
+            self.sample_rate = ctx.sample_rate;
+            for insert in self.inserts.iter_mut() {
+                insert.effect.set_sample_rate(ctx.sample_rate as f64);
+            }
+        }
+
+        if self.automation.is_empty() {
+            self.process_part(ctx, 0, frames);
+            return;
+        }
+
+        let mut done = 0;
+
+        while done < frames {
+            let tick = ctx.tick + done as f64 * ctx.ticks_per_frame;
+            let part = (frames - done).min(CONTROL_FRAMES);
+            let part = self.automation.frames_until_point(tick, ctx.ticks_per_frame, part);
+
+            self.automate(tick);
+
+            let ctx = ProcessContext {
+                sample_rate: ctx.sample_rate,
+                clock: ctx.clock + done as u64,
+                tick,
+                ticks_per_frame: ctx.ticks_per_frame,
+                tempo: ctx.tempo,
+            };
+            self.process_part(&ctx, done, done + part);
+            done += part;
+        }
+    }
+
+    fn process_part(&mut self, ctx: &ProcessContext, start: usize, end: usize) {
+        let out = &mut self.buffer[start..end];
+        self.node.process(ctx, out);
+
+        for insert in self.inserts.iter_mut().filter(|insert| !insert.bypassed) {
+            insert.effect.set_tempo(ctx.tempo);
+            insert.effect.process(out);
+        }
+    }
+
+    /// Set every automated parameter but the fader to where it is at a
+    /// song tick.
+    fn automate(&mut self, tick: f64) {
+        for lane in self.automation.lanes.iter() {
+            let Some(position) = lane.envelope.value_at(tick) else { continue };
+            let value = lane.param.denormalize(position);
+
+            match lane.destination {
+                Destination::Instrument(param) => self.node.set_param(param, value),
+
+                Destination::Insert { insert, param } => {
+                    if let Some(insert) = self.inserts.get_mut(insert) {
+                        insert.effect.set_param(param, value);
+                    }
+                }
+
+                Destination::Gain | Destination::Pan => (),
+            }
+        }
+    }
+
+    /// Automated, the fader moves with every frame.
+    pub(super) fn apply_fader(&mut self, ctx: &ProcessContext, frames: usize) {
+        let out = &mut self.buffer[..frames];
+
+        if self.automation.moves_fader() {
+            for (i, frame) in out.iter_mut().enumerate() {
+                let (gain, pan) = self.automation.fader(ctx.tick + i as f64 * ctx.ticks_per_frame);
+                let strip = Strip {
+                    gain: gain.unwrap_or(self.strip.gain),
+                    pan: pan.unwrap_or(self.strip.pan),
+                    ..self.strip
+                };
+                let [left, right] = strip.gains();
+
+                frame[0] *= left;
+                frame[1] *= right;
+            }
+        } else {
+            let [left, right] = self.strip.gains();
+
+            for frame in out.iter_mut() {
+                frame[0] *= left;
+                frame[1] *= right;
+            }
+        }
+
+        self.meter.publish(out);
+    }
+
+    pub fn handle(&mut self, message: NodeMessage) -> Option<Garbage> {
+        match message {
+            NodeMessage::SetStrip(strip) => {
+                self.strip = strip;
+                None
+            }
+
+            NodeMessage::SetInserts(inserts) => {
+                Some(Garbage::Inserts(std::mem::replace(&mut self.inserts, inserts)))
+            }
+
+            NodeMessage::SetParam { insert, param, value } => {
+                if let Some(insert) = self.inserts.get_mut(insert) {
+                    insert.effect.set_param(param, value);
+                }
+                None
+            }
+
+            NodeMessage::Bypass { insert, bypassed } => {
+                if let Some(insert) = self.inserts.get_mut(insert) {
+                    insert.bypassed = bypassed;
+                    insert.effect.reset();
+                }
+                None
+            }
+
+            NodeMessage::SetInstrumentParam { param, value } => {
+                self.node.set_param(param, value);
+                None
+            }
+
+            NodeMessage::SetAutomation(automation) => {
+                Some(Garbage::Automation(std::mem::replace(&mut self.automation, automation)))
+            }
+
+            message => self.node.handle(message),
+        }
+    }
+
+    pub fn length(&self, ctx: &ProcessContext) -> u64 {
+        self.node.length(ctx)
+    }
+}
+
+/// What buses play: whatever is routed into them.
+pub struct BusNode;
+
+impl Node for BusNode {
+    fn process(&mut self, _ctx: &ProcessContext, _out: &mut [Frame]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::automation::AutomationLane;
+    use crate::project::automation::{Curve, Envelope, GAIN, PAN, Point};
+
+    struct Constant;
+
+    impl Node for Constant {
+        fn process(&mut self, _ctx: &ProcessContext, out: &mut [Frame]) {
+            out.fill([0.5, 0.5]);
+        }
+    }
+
+    fn process(channel: &mut Channel) -> Frame {
+        let ctx = ProcessContext { sample_rate: 48000, clock: 0, tick: 0.0, ticks_per_frame: 0.0, tempo: 120.0 };
+        channel.clear(64);
+        channel.process(&ctx, 64);
+        channel.apply_fader(&ctx, 64);
+        channel.buffer[0]
+    }
+
+    #[test]
+    fn applies_gain_and_pan() {
+        let meter = Arc::new(LevelMeter::new());
+        let mut channel = Channel::new(0, Box::new(Constant), Vec::new(), Box::default(), Strip::default(), meter);
+        assert_eq!(process(&mut channel), [0.5, 0.5]);
+
+        channel.handle(NodeMessage::SetStrip(Strip { gain: 0.5, pan: 0.5, muted: false }));
+        assert_eq!(process(&mut channel), [0.125, 0.25]);
+    }
+
+    #[test]
+    fn meters_hold_peaks_until_read() {
+        let meter = Arc::new(LevelMeter::new());
+        let mut channel = Channel::new(0, Box::new(Constant), Vec::new(), Box::default(), Strip::default(), meter.clone());
+        process(&mut channel);
+
+        channel.handle(NodeMessage::SetStrip(Strip { gain: 0.5, pan: 0.0, muted: false }));
+        process(&mut channel);
+
+        assert_eq!(meter.take(), Levels { peak: [0.5, 0.5], rms: [0.25, 0.25] });
+        assert_eq!(meter.take().peak, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn automation_lands_on_the_frame() {
+        // Hard left until tick 40, then hard right.
+        let mut envelope = Envelope::default();
+        envelope.insert(Point { tick: 0, value: 0.0, curve: Curve::Step });
+        envelope.insert(Point { tick: 40, value: 1.0, curve: Curve::Step });
+
+        let lane = AutomationLane { destination: Destination::Pan, param: PAN, envelope };
+        let automation = Box::new(Automation { lanes: vec![lane] });
+
+        let meter = Arc::new(LevelMeter::new());
+        let mut channel = Channel::new(0, Box::new(Constant), Vec::new(), automation, Strip::default(), meter);
+
+        let ctx = ProcessContext { sample_rate: 48000, clock: 0, tick: 0.0, ticks_per_frame: 0.5, tempo: 120.0 };
+        channel.clear(128);
+        channel.process(&ctx, 128);
+        channel.apply_fader(&ctx, 128);
+
+        assert_eq!(channel.buffer[79], [0.5, 0.0]);
+        assert_eq!(channel.buffer[80], [0.0, 0.5]);
+    }
+
+    #[test]
+    fn automation_cannot_unmute() {
+        let mut envelope = Envelope::default();
+        envelope.insert(Point { tick: 0, value: 1.0, curve: Curve::Linear });
+
+        let lane = AutomationLane { destination: Destination::Gain, param: GAIN, envelope };
+        let automation = Box::new(Automation { lanes: vec![lane] });
+
+        let meter = Arc::new(LevelMeter::new());
+        let strip = Strip { muted: true, ..Strip::default() };
+        let mut channel = Channel::new(0, Box::new(Constant), Vec::new(), automation, strip, meter);
+
+        assert_eq!(process(&mut channel), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,113 @@
+pub mod automation;
+pub mod backend;
+pub mod export;
+pub mod graph;
+pub mod mixer;
+pub mod renderer;
+pub mod sequencer;
+pub mod track;
+pub mod transport;
+
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+use crate::project::tempo::TempoMap;
+
+use automation::Automation;
+use backend::{Backend, BackendKind};
+use graph::{NodeMessage, Routing};
+use mixer::{Channel, Insert};
+use renderer::Renderer;
+use sequencer::Sequence;
+use transport::{TransportClock, TransportCommand, TransportStatus};
+
+/// One stereo sample frame, `[left, right]`.
+pub type Frame = [f32; 2];
+
+/// Most frames rendered in one go. Buffers on the audio thread are made
+/// this long up front, and longer blocks are rendered a part at a time.
+pub const MAX_BLOCK: usize = 4096;
+
+/// Messages sent from the UI thread to the renderer on the audio thread.
+pub enum AudioCommand {
+    AddChannel(Box<Channel>),
+    RemoveChannel { id: usize },
+    Message { id: usize, message: NodeMessage },
+    SetRouting(Box<Routing>),
+    SetTempoMap(Box<TempoMap>),
+    Transport(TransportCommand),
+}
+
+/// Heap allocations the audio thread is done with, sent back so that they
+/// are freed on the UI thread.
+pub enum Garbage {
+    Automation(Box<Automation>),
+    Channel(Box<Channel>),
+    Routing(Box<Routing>),
+    Inserts(Vec<Insert>),
+    Sequence(Box<Sequence>),
+    TempoMap(Box<TempoMap>),
+}
+
+/// Handle to the real-time audio engine.
+///
+/// The renderer lives inside the backend; everything the UI wants to change
+/// is sent over `sender`, and anything the renderer no longer needs comes
+/// back through `garbage` so that it is freed off the audio thread.
+pub struct AudioEngine {
+    backend: Box<dyn Backend>,
+    sender: Sender<AudioCommand>,
+    garbage: Receiver<Garbage>,
+    transport: Arc<TransportClock>,
+}
+
+impl AudioEngine {
+    pub fn new(kind: &BackendKind) -> Result<Self> {
+        let (sender, receiver) = bounded::<AudioCommand>(1024);
+        let (garbage_sender, garbage) = bounded::<Garbage>(1024);
+
+        let transport = Arc::new(TransportClock::new());
+
+        let renderer = Renderer::new(receiver, garbage_sender, transport.clone());
+        let backend = backend::start(kind, renderer)?;
+
+        Ok(Self {
+            backend,
+            sender,
+            garbage,
+            transport,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.backend.sample_rate()
+    }
+
+    /// Play state and song position as of the last rendered block.
+    pub fn transport(&self) -> TransportStatus {
+        self.transport.status()
+    }
+
+    /// Queue a command for the audio thread. Commands are dropped rather
+    /// than blocking the UI if the renderer has fallen far behind, and
+    /// then this returns false.
+    pub fn send(&self, command: AudioCommand) -> bool {
+        self.sender.try_send(command).is_ok()
+    }
+
+    /// Free whatever the renderer has handed back. Call once per UI frame.
+    pub fn collect_garbage(&self) {
+        while let Ok(garbage) = self.garbage.try_recv() {
+            match garbage {
+                Garbage::Automation(automation) => drop(automation),
+                Garbage::Channel(channel) => drop(channel),
+                Garbage::Routing(routing) => drop(routing),
+                Garbage::Inserts(inserts) => drop(inserts),
+                Garbage::Sequence(sequence) => drop(sequence),
+                Garbage::TempoMap(tempo_map) => drop(tempo_map),
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{mem, sync::Arc};
+
+use crossbeam_channel::{bounded, never, Receiver, Sender};
+
+use crate::audio::{
+    AudioCommand, Frame, Garbage, MAX_BLOCK,
+    graph::{Graph, ProcessContext},
+    transport::{PlayState, Transport, TransportClock, TransportCommand},
+};
+use crate::project::{PPQ, tempo::TempoMap};
+
+/// Owns the graph on the audio thread and applies commands from the UI
+/// between blocks.
+pub struct Renderer {
+    commands: Receiver<AudioCommand>,
+    garbage: Sender<Garbage>,
+    graph: Graph,
+    sample_rate: u32,
+    clock: u64,
+    transport: Transport,
+    tempo_map: Box<TempoMap>,
+}
+
+impl Renderer {
+    /// The backend sets the sample rate before the first block.
+    pub fn new(
+        commands: Receiver<AudioCommand>,
+        garbage: Sender<Garbage>,
+        clock: Arc<TransportClock>,
+    ) -> Self {
+        Self {
+            commands,
+            garbage,
+            graph: Graph::new(),
+            sample_rate: 0,
+            clock: 0,
+            transport: Transport::new(PlayState::Stopped, clock),
+            tempo_map: Box::default(),
+        }
+    }
+
+    /// A renderer that is not connected to the UI, for rendering a graph
+    /// faster than real time. It starts out playing, and stops at `end`.
+    pub fn offline(sample_rate: u32, graph: Graph, tempo_map: TempoMap, end: Option<u32>) -> Self {
+        // Nothing will ever arrive, and removed nodes are simply dropped.
+        let (garbage, _) = bounded(0);
+
+        let mut transport = Transport::new(PlayState::Playing, Arc::new(TransportClock::new()));
+        transport.handle(TransportCommand::SetEnd(end));
+
+        Self {
+            commands: never(),
+            garbage,
+            graph,
+            sample_rate,
+            clock: 0,
+            transport,
+            tempo_map: Box::new(tempo_map),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Frames until the song has ended and every node has finished. Nodes
+    /// only know about the tempo the song starts at.
+    pub fn length(&self) -> u64 {
+        let ctx = ProcessContext { ticks_per_frame: self.ticks_per_frame(), ..self.context() };
+        let end = self.transport.end().map_or(0, |end| {
+            (self.tempo_map.seconds_at(end as f64) * self.sample_rate as f64).ceil() as u64
+        });
+
+        self.graph.length(&ctx).max(end)
+    }
+
+    /// At the tempo in effect at the current song position.
+    fn ticks_per_frame(&self) -> f64 {
+        let tempo = self.tempo_map.tempo_at(self.transport.tick() as u32);
+        tempo / 60.0 * PPQ as f64 / self.sample_rate as f64
+    }
+
+    /// Frames that can be rendered at the current tempo.
+    fn frames_until_tempo_change(&self, ticks_per_frame: f64, frames: usize) -> usize {
+        let next = self.tempo_map.next_tempo_change(self.transport.tick())
+            .filter(|_| ticks_per_frame > 0.0);
+
+        match next {
+            Some(tick) => {
+                let until = ((tick as f64 - self.transport.tick()) / ticks_per_frame).ceil();
+                (until.max(1.0) as usize).min(frames)
+            }
+
+            None => frames,
+        }
+    }
+
+    /// The song position only moves while the transport is playing.
+    fn context(&self) -> ProcessContext {
+        ProcessContext {
+            sample_rate: self.sample_rate,
+            clock: self.clock,
+            tick: self.transport.tick(),
+            ticks_per_frame: if self.transport.is_playing() { self.ticks_per_frame() } else { 0.0 },
+            tempo: self.tempo_map.tempo_at(self.transport.tick() as u32),
+        }
+    }
+
+    pub fn render(&mut self, out: &mut [Frame]) {
+        self.handle_commands();
+
+        // Split the block wherever the transport loops or stops and the
+        // tempo changes, so that every node sees a continuous stretch of
+        // the song at a steady tempo, and into parts that fit the buffers.
+        let mut done = 0;
+
+        while done < out.len() {
+            let ctx = self.context();
+            let frames = (out.len() - done).min(MAX_BLOCK);
+            let frames = self.transport.frames_until_jump(ctx.ticks_per_frame, frames);
+            let frames = self.frames_until_tempo_change(ctx.ticks_per_frame, frames);
+
+            self.graph.process(&ctx, &mut out[done..done + frames]);
+
+            self.clock += frames as u64;
+            self.transport.advance(frames, ctx.ticks_per_frame);
+            done += frames;
+        }
+
+        self.transport.publish();
+    }
+
+    fn handle_commands(&mut self) {
+        while let Ok(command) = self.commands.try_recv() {
+            let garbage = match command {
+                AudioCommand::AddChannel(channel) => {
+                    self.graph.add_channel(channel).map(Garbage::Channel)
+                }
+
+                AudioCommand::RemoveChannel { id } => {
+                    self.graph.remove_channel(id).map(Garbage::Channel)
+                }
+
+                AudioCommand::Message { id, message } => self.graph.send(id, message),
+
+                AudioCommand::SetRouting(routing) => {
+                    Some(Garbage::Routing(self.graph.set_routing(routing)))
+                }
+
+                AudioCommand::SetTempoMap(tempo_map) => {
+                    Some(Garbage::TempoMap(mem::replace(&mut self.tempo_map, tempo_map)))
+                }
+
+                AudioCommand::Transport(command) => {
+                    self.transport.handle(command);
+                    None
+                }
+            };
+
+            // If the UI is not collecting, it is dropped here instead.
+            if let Some(garbage) = garbage {
+                let _ = self.garbage.try_send(garbage);
+            }
+        }
+    }
+}
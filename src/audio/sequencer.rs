@@ -0,0 +1,285 @@
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteEvent {
+    On { pitch: u8, velocity: u8 },
+    Off { pitch: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequencedNote {
+    pub pitch: u8,
+    pub velocity: u8,
+    pub start: u32,
+    pub length: u32,
+}
+
+/// Notes flattened into time-ordered events, ready for the audio thread.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sequence {
+    events: Vec<(u32, NoteEvent)>,
+    /// Playback wraps back to tick 0 after this many ticks.
+    loop_length: Option<u32>,
+    end: u32,
+}
+
+impl Sequence {
+    pub fn new<I>(notes: I, loop_length: Option<u32>) -> Self
+    where I: IntoIterator<Item = SequencedNote> {
+        let loop_length = loop_length.filter(|length| *length > 0);
+        let mut events = Vec::new();
+        let mut end = 0;
+
+        for note in notes {
+            let mut off = note.start + note.length.max(1);
+
+            // Notes hanging over the loop point are cut at it.
+            if let Some(loop_length) = loop_length {
+                if note.start >= loop_length {
+                    continue;
+                }
+
+                off = off.min(loop_length);
+            }
+
+            events.push((note.start, NoteEvent::On { pitch: note.pitch, velocity: note.velocity }));
+            events.push((off, NoteEvent::Off { pitch: note.pitch }));
+            end = end.max(off);
+        }
+
+        // Note-offs go before note-ons on the same tick so that repeated
+        // notes retrigger instead of being cut short.
+        events.sort_by_key(|(tick, event)| (*tick, matches!(event, NoteEvent::On { .. })));
+
+        Self { events, loop_length, end }
+    }
+
+    /// Ticks until the sequence has played through once.
+    pub fn length(&self) -> u32 {
+        self.loop_length.unwrap_or(self.end)
+    }
+
+    fn to_local(&self, tick: f64) -> f64 {
+        match self.loop_length {
+            Some(length) => tick.rem_euclid(length as f64),
+            None => tick,
+        }
+    }
+
+    fn seek(&self, tick: f64) -> usize {
+        self.events.partition_point(|(event_tick, _)| (*event_tick as f64) < tick)
+    }
+
+    /// One bit per pitch turned on and not off again before `cursor`.
+    fn held(&self, cursor: usize) -> u128 {
+        self.events[..cursor].iter().fold(0, |held, (_, event)| match *event {
+            NoteEvent::On { pitch, .. } => held | 1 << (pitch & 0x7f),
+            NoteEvent::Off { pitch } => held & !(1 << (pitch & 0x7f)),
+        })
+    }
+}
+
+/// Largest mismatch between where playback was expected to continue and
+/// where it does before it counts as a jump.
+const JUMP_TOLERANCE: f64 = 1.0e-3;
+
+/// Plays a sequence on the audio thread, turning the song position of each
+/// block into note events at exact frame offsets.
+pub struct Sequencer {
+    sequence: Box<Sequence>,
+    cursor: usize,
+    /// Local tick the cursor is valid for.
+    position: f64,
+    /// One bit per MIDI pitch that has been turned on and not off again.
+    sounding: u128,
+    /// Sounding pitches to release before playing on.
+    release: u128,
+}
+
+impl Sequencer {
+    pub fn new(sequence: Box<Sequence>) -> Self {
+        Self {
+            sequence,
+            cursor: 0,
+            position: 0.0,
+            sounding: 0,
+            release: 0,
+        }
+    }
+
+    pub fn sequence(&self) -> &Sequence {
+        &self.sequence
+    }
+
+    /// Swap in a new sequence, returning the old one so that it can be
+    /// freed off the audio thread. Notes the new sequence still holds at
+    /// the current position keep sounding, and its note-offs end them.
+    pub fn set_sequence(&mut self, sequence: Box<Sequence>) -> Box<Sequence> {
+        let old = mem::replace(&mut self.sequence, sequence);
+        self.cursor = self.sequence.seek(self.position);
+        self.release |= self.sounding & !self.sequence.held(self.cursor);
+        old
+    }
+
+    /// Emit every event between song tick `start` and `frames` frames
+    /// later, together with the frame offset it falls on.
+    pub fn advance<F>(&mut self, start: f64, ticks_per_frame: f64, frames: usize, mut emit: F)
+    where F: FnMut(usize, NoteEvent) {
+        let mut local = self.sequence.to_local(start);
+
+        if (local - self.position).abs() > JUMP_TOLERANCE {
+            self.cursor = self.sequence.seek(local);
+            self.release = self.sounding;
+        }
+
+        let release = mem::take(&mut self.release);
+        self.release_pitches(release, &mut emit);
+
+        // Nothing is left hanging while the transport is stopped.
+        if ticks_per_frame <= 0.0 {
+            self.release_pitches(self.sounding, &mut emit);
+            self.position = local;
+            return;
+        }
+
+        let mut remaining = frames as f64 * ticks_per_frame;
+        let mut played = 0.0;
+
+        loop {
+            let loop_end = self.sequence.loop_length.map(|length| length as f64);
+            let wraps = loop_end.is_some_and(|end| local + remaining >= end);
+            let segment_end = if wraps { loop_end.unwrap() } else { local + remaining };
+
+            while let Some((tick, event)) = self.sequence.events.get(self.cursor).copied() {
+                let tick = tick as f64;
+
+                // Events right on the loop point still belong to this lap.
+                if tick >= segment_end && !(wraps && tick <= segment_end) {
+                    break;
+                }
+
+                let offset = ((played + (tick - local).max(0.0)) / ticks_per_frame) as usize;
+                self.track(event);
+                emit(offset.min(frames), event);
+                self.cursor += 1;
+            }
+
+            played += segment_end - local;
+            remaining -= segment_end - local;
+
+            if !wraps {
+                local = segment_end;
+                break;
+            }
+
+            local = 0.0;
+            self.cursor = 0;
+
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+
+        self.position = local;
+    }
+
+    fn track(&mut self, event: NoteEvent) {
+        match event {
+            NoteEvent::On { pitch, .. } => self.sounding |= 1 << (pitch & 0x7f),
+            NoteEvent::Off { pitch } => self.sounding &= !(1 << (pitch & 0x7f)),
+        }
+    }
+
+    fn release_pitches<F>(&mut self, mut pitches: u128, emit: &mut F)
+    where F: FnMut(usize, NoteEvent) {
+        pitches &= self.sounding;
+
+        while pitches != 0 {
+            let pitch = pitches.trailing_zeros() as u8;
+            pitches &= !(1 << pitch);
+            self.sounding &= !(1 << pitch);
+            emit(0, NoteEvent::Off { pitch });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(pitch: u8, start: u32, length: u32) -> SequencedNote {
+        SequencedNote { pitch, velocity: 100, start, length }
+    }
+
+    fn events(sequencer: &mut Sequencer, start: f64, frames: usize) -> Vec<(usize, NoteEvent)> {
+        let mut events = Vec::new();
+        sequencer.advance(start, 1.0, frames, |offset, event| events.push((offset, event)));
+        events
+    }
+
+    #[test]
+    fn new_sequence_keeps_held_notes() {
+        let sequence = Sequence::new([note(60, 0, 100), note(64, 0, 100)], None);
+        let mut sequencer = Sequencer::new(Box::new(sequence));
+        events(&mut sequencer, 0.0, 10);
+
+        // 64 is gone, 60 is still there and now ends sooner.
+        sequencer.set_sequence(Box::new(Sequence::new([note(60, 0, 20)], None)));
+
+        assert_eq!(events(&mut sequencer, 10.0, 20), [
+            (0, NoteEvent::Off { pitch: 64 }),
+            (10, NoteEvent::Off { pitch: 60 }),
+        ]);
+    }
+
+    #[test]
+    fn events_land_on_their_frame() {
+        let sequence = Sequence::new([note(60, 10, 5)], None);
+        let mut sequencer = Sequencer::new(Box::new(sequence));
+
+        let mut events = Vec::new();
+        sequencer.advance(0.0, 0.5, 64, |offset, event| events.push((offset, event)));
+
+        assert_eq!(events, [
+            (20, NoteEvent::On { pitch: 60, velocity: 100 }),
+            (30, NoteEvent::Off { pitch: 60 }),
+        ]);
+    }
+
+    #[test]
+    fn loop_wraps_within_a_block() {
+        let sequence = Sequence::new([note(60, 0, 4), note(62, 8, 8)], Some(16));
+        let mut sequencer = Sequencer::new(Box::new(sequence));
+        events(&mut sequencer, 0.0, 12);
+
+        // The note hanging over the loop point ends on it, and the lap
+        // after starts again from tick 0.
+        assert_eq!(events(&mut sequencer, 12.0, 8), [
+            (4, NoteEvent::Off { pitch: 62 }),
+            (4, NoteEvent::On { pitch: 60, velocity: 100 }),
+        ]);
+
+        // Song ticks past the loop map back into it.
+        assert_eq!(events(&mut sequencer, 20.0, 4), [(0, NoteEvent::Off { pitch: 60 })]);
+    }
+
+    #[test]
+    fn jumping_releases_sounding_notes() {
+        let sequence = Sequence::new([note(60, 0, 100), note(62, 50, 10)], None);
+        let mut sequencer = Sequencer::new(Box::new(sequence));
+        events(&mut sequencer, 0.0, 10);
+
+        // Jumping into the middle of a note does not start it.
+        assert_eq!(events(&mut sequencer, 55.0, 10), [
+            (0, NoteEvent::Off { pitch: 60 }),
+            (5, NoteEvent::Off { pitch: 62 }),
+        ]);
+        assert_eq!(sequencer.sounding, 0);
+
+        // Stopping releases everything too.
+        events(&mut sequencer, 0.0, 1);
+        let mut stopped = Vec::new();
+        sequencer.advance(1.0, 0.0, 10, |offset, event| stopped.push((offset, event)));
+        assert_eq!(stopped, [(0, NoteEvent::Off { pitch: 60 })]);
+    }
+}
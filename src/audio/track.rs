@@ -0,0 +1,67 @@
+use crate::audio::{
+    Frame, Garbage,
+    graph::{Node, NodeMessage, ProcessContext},
+    sequencer::{NoteEvent, Sequence, Sequencer},
+};
+use crate::instrument::Instrument;
+
+/// Plays a sequence through an instrument.
+pub struct InstrumentTrack {
+    sequencer: Sequencer,
+    instrument: Box<dyn Instrument>,
+    sample_rate: u32,
+}
+
+impl InstrumentTrack {
+    pub fn new(instrument: Box<dyn Instrument>, sequence: Sequence) -> Self {
+        Self {
+            sequencer: Sequencer::new(Box::new(sequence)),
+            instrument,
+            sample_rate: 0,
+        }
+    }
+}
+
+impl Node for InstrumentTrack {
+    fn process(&mut self, ctx: &ProcessContext, out: &mut [Frame]) {
+        if ctx.sample_rate != self.sample_rate {
+            self.sample_rate = ctx.sample_rate;
+            self.instrument.set_sample_rate(ctx.sample_rate as f64);
+        }
+
+        let instrument = &mut self.instrument;
+        let mut rendered = 0;
+
+        // Render up to each event so that notes start on the exact frame.
+        self.sequencer.advance(ctx.tick, ctx.ticks_per_frame, out.len(), |offset, event| {
+            instrument.render(&mut out[rendered..offset]);
+            rendered = offset;
+
+            match event {
+                NoteEvent::On { pitch, velocity } => instrument.note_on(pitch, velocity),
+                NoteEvent::Off { pitch } => instrument.note_off(pitch),
+            }
+        });
+
+        instrument.render(&mut out[rendered..]);
+    }
+
+    fn handle(&mut self, message: NodeMessage) -> Option<Garbage> {
+        match message {
+            NodeMessage::Sequence(sequence) => {
+                Some(Garbage::Sequence(self.sequencer.set_sequence(sequence)))
+            }
+
+            // The channel takes care of the mixer.
+            _ => None,
+        }
+    }
+
+    fn set_param(&mut self, param: usize, value: f64) {
+        self.instrument.set_param(param, value);
+    }
+
+    fn length(&self, ctx: &ProcessContext) -> u64 {
+        (self.sequencer.sequence().length() as f64 / ctx.ticks_per_frame).ceil() as u64
+    }
+}
@@ -0,0 +1,282 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU8, AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayState {
+    Stopped,
+    Paused,
+    Playing,
+}
+
+/// Song ticks that playback keeps wrapping around while inside them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub start: u32,
+    pub end: u32,
+}
+
+pub enum TransportCommand {
+    Play,
+    Pause,
+    /// Stop and go back to the start of the loop, or of the song.
+    Stop,
+    SetLoop(Option<LoopRegion>),
+    /// Playback stops once the song position reaches the end of the song.
+    SetEnd(Option<u32>),
+}
+
+/// Where the transport was at the end of the last block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransportStatus {
+    pub state: PlayState,
+    pub tick: f64,
+}
+
+impl TransportStatus {
+    pub fn is_stopped(&self) -> bool {
+        self.state == PlayState::Stopped
+    }
+}
+
+/// The transport as last seen by the audio thread, readable from the UI
+/// without locking.
+pub struct TransportClock {
+    state: AtomicU8,
+    /// Bits of the song position as an `f64`.
+    tick: AtomicU64,
+}
+
+impl TransportClock {
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU8::new(PlayState::Stopped as u8),
+            tick: AtomicU64::new(0.0f64.to_bits()),
+        }
+    }
+
+    pub fn status(&self) -> TransportStatus {
+        let state = match self.state.load(Ordering::Relaxed) {
+            s if s == PlayState::Playing as u8 => PlayState::Playing,
+            s if s == PlayState::Paused as u8 => PlayState::Paused,
+            _ => PlayState::Stopped,
+        };
+
+        TransportStatus {
+            state,
+            tick: f64::from_bits(self.tick.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn publish(&self, status: TransportStatus) {
+        self.state.store(status.state as u8, Ordering::Relaxed);
+        self.tick.store(status.tick.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Play state and song position, owned by the renderer.
+pub struct Transport {
+    state: PlayState,
+    tick: f64,
+    loop_region: Option<LoopRegion>,
+    end: Option<u32>,
+    clock: Arc<TransportClock>,
+}
+
+impl Transport {
+    pub fn new(state: PlayState, clock: Arc<TransportClock>) -> Self {
+        Self {
+            state,
+            tick: 0.0,
+            loop_region: None,
+            end: None,
+            clock,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == PlayState::Playing
+    }
+
+    pub fn tick(&self) -> f64 {
+        self.tick
+    }
+
+    pub fn end(&self) -> Option<u32> {
+        self.end
+    }
+
+    pub fn handle(&mut self, command: TransportCommand) {
+        match command {
+            TransportCommand::Play => {
+                if self.end.is_some_and(|end| self.tick >= end as f64) {
+                    self.tick = 0.0;
+                }
+
+                self.state = PlayState::Playing;
+            }
+
+            TransportCommand::Pause => {
+                if self.state == PlayState::Playing {
+                    self.state = PlayState::Paused;
+                }
+            }
+
+            TransportCommand::Stop => self.stop(),
+
+            TransportCommand::SetLoop(region) => {
+                self.loop_region = region.filter(|region| region.start < region.end);
+            }
+
+            TransportCommand::SetEnd(end) => self.end = end,
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state = PlayState::Stopped;
+        self.tick = self.loop_region.map_or(0, |region| region.start) as f64;
+    }
+
+    /// The next tick playback jumps or stops at, if any.
+    fn boundary(&self) -> Option<u32> {
+        match self.loop_region {
+            Some(region) if (region.start as f64..region.end as f64).contains(&self.tick) => {
+                Some(region.end)
+            }
+
+            _ => self.end,
+        }
+    }
+
+    /// How many of `frames` can be rendered before the position has to
+    /// jump, so that loops and the song end land on an exact frame.
+    pub fn frames_until_jump(&self, ticks_per_frame: f64, frames: usize) -> usize {
+        let boundary = self.boundary().filter(|_| self.is_playing() && ticks_per_frame > 0.0);
+
+        match boundary {
+            Some(boundary) => {
+                let until = ((boundary as f64 - self.tick) / ticks_per_frame).ceil();
+                (until.max(1.0) as usize).min(frames)
+            }
+
+            None => frames,
+        }
+    }
+
+    /// Move on by `frames`, which must not go past the next jump.
+    pub fn advance(&mut self, frames: usize, ticks_per_frame: f64) {
+        if !self.is_playing() {
+            return;
+        }
+
+        let boundary = self.boundary();
+        self.tick += frames as f64 * ticks_per_frame;
+
+        match boundary {
+            Some(boundary) if self.tick >= boundary as f64 => match self.loop_region {
+                Some(region) if region.end == boundary => self.tick = region.start as f64,
+                _ => self.stop(),
+            },
+
+            _ => (),
+        }
+    }
+
+    pub fn publish(&self) {
+        self.clock.publish(TransportStatus {
+            state: self.state,
+            tick: self.tick,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport() -> Transport {
+        let mut transport = Transport::new(PlayState::Stopped, Arc::new(TransportClock::new()));
+        transport.handle(TransportCommand::SetEnd(Some(100)));
+        transport
+    }
+
+    /// Render `frames` in as many pieces as the transport asks for,
+    /// returning the size of each.
+    fn run(transport: &mut Transport, frames: usize, ticks_per_frame: f64) -> Vec<usize> {
+        let mut pieces = Vec::new();
+        let mut done = 0;
+
+        while done < frames {
+            let piece = transport.frames_until_jump(ticks_per_frame, frames - done);
+            transport.advance(piece, ticks_per_frame);
+            pieces.push(piece);
+            done += piece;
+        }
+
+        pieces
+    }
+
+    #[test]
+    fn only_moves_while_playing() {
+        let mut transport = transport();
+        run(&mut transport, 10, 1.0);
+        assert_eq!(transport.tick(), 0.0);
+
+        transport.handle(TransportCommand::Play);
+        run(&mut transport, 10, 1.0);
+        assert_eq!(transport.tick(), 10.0);
+
+        transport.handle(TransportCommand::Pause);
+        run(&mut transport, 10, 1.0);
+        assert_eq!(transport.tick(), 10.0);
+
+        transport.handle(TransportCommand::Stop);
+        assert_eq!(transport.tick(), 0.0);
+    }
+
+    #[test]
+    fn wraps_around_the_loop_on_an_exact_frame() {
+        let mut transport = transport();
+        transport.handle(TransportCommand::SetLoop(Some(LoopRegion { start: 20, end: 30 })));
+        transport.handle(TransportCommand::Stop);
+        transport.handle(TransportCommand::Play);
+
+        assert_eq!(run(&mut transport, 16, 2.0), [5, 5, 5, 1]);
+        assert_eq!(transport.tick(), 22.0);
+        assert!(transport.is_playing());
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_song() {
+        let mut transport = transport();
+        transport.handle(TransportCommand::Play);
+
+        assert_eq!(run(&mut transport, 64, 3.0), [34, 30]);
+        assert_eq!(transport.tick(), 0.0);
+        assert!(!transport.is_playing());
+    }
+
+    #[test]
+    fn plays_past_a_loop_it_started_after() {
+        let mut transport = transport();
+        transport.handle(TransportCommand::Play);
+        run(&mut transport, 40, 1.0);
+        transport.handle(TransportCommand::SetLoop(Some(LoopRegion { start: 0, end: 30 })));
+
+        run(&mut transport, 20, 1.0);
+        assert_eq!(transport.tick(), 60.0);
+    }
+
+    #[test]
+    fn publishes_to_the_clock() {
+        let clock = Arc::new(TransportClock::new());
+        let mut transport = Transport::new(PlayState::Playing, clock.clone());
+        transport.advance(48, 0.5);
+        transport.publish();
+
+        assert_eq!(clock.status(), TransportStatus { state: PlayState::Playing, tick: 24.0 });
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+/// Attack, decay and release times are in seconds, sustain is a level.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdsrParams {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f64,
+    pub release: f64,
+}
+
+impl Default for AdsrParams {
+    fn default() -> Self {
+        Self {
+            attack: 0.005,
+            decay: 0.2,
+            sustain: 0.7,
+            release: 0.2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Level below which a releasing envelope counts as finished.
+const SILENT: f64 = 1.0e-4;
+
+/// Linear attack with exponential decay and release. The exponential
+/// stages reach -80 dB of their distance to the target in the given time.
+#[derive(Clone, Debug)]
+pub struct Adsr {
+    params: AdsrParams,
+    sample_rate: f64,
+    stage: Stage,
+    level: f64,
+    attack_step: f64,
+    decay_coef: f64,
+    release_coef: f64,
+}
+
+impl Adsr {
+    pub fn new(params: AdsrParams, sample_rate: f64) -> Self {
+        let mut adsr = Self {
+            params,
+            sample_rate,
+            stage: Stage::Idle,
+            level: 0.0,
+            attack_step: 0.0,
+            decay_coef: 0.0,
+            release_coef: 0.0,
+        };
+
+        adsr.update_coefficients();
+        adsr
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    fn update_coefficients(&mut self) {
+        let samples = |seconds: f64| (seconds * self.sample_rate).max(1.0);
+        let coef = |seconds: f64| 1.0 - SILENT.powf(1.0 / samples(seconds));
+
+        self.attack_step = 1.0 / samples(self.params.attack);
+        self.decay_coef = coef(self.params.decay);
+        self.release_coef = coef(self.params.release);
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.stage != Stage::Idle
+    }
+
+    /// Restarts the attack from the current level, so retriggering a
+    /// sounding voice does not click.
+    pub fn gate_on(&mut self) {
+        self.stage = Stage::Attack;
+    }
+
+    pub fn gate_off(&mut self) {
+        if self.stage != Stage::Idle {
+            self.stage = Stage::Release;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.stage = Stage::Idle;
+        self.level = 0.0;
+    }
+
+    pub fn next(&mut self) -> f64 {
+        match self.stage {
+            Stage::Idle => (),
+
+            Stage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+
+            Stage::Decay => {
+                let sustain = self.params.sustain;
+                self.level += (sustain - self.level) * self.decay_coef;
+                if (self.level - sustain).abs() < SILENT {
+                    self.level = sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+
+            Stage::Sustain => self.level = self.params.sustain,
+
+            Stage::Release => {
+                self.level -= self.level * self.release_coef;
+                if self.level < SILENT {
+                    self.reset();
+                }
+            }
+        }
+
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chosen with the stage times so that sample counts and attack steps
+    /// are exact in binary.
+    const SAMPLE_RATE: f64 = 1024.0;
+
+    const PARAMS: AdsrParams = AdsrParams {
+        attack: 8.0 / SAMPLE_RATE,
+        decay: 128.0 / SAMPLE_RATE,
+        sustain: 0.5,
+        release: 256.0 / SAMPLE_RATE,
+    };
+
+    fn run(adsr: &mut Adsr, samples: usize) -> f64 {
+        (0..samples).map(|_| adsr.next()).last().unwrap_or(0.0)
+    }
+
+    #[test]
+    fn stages_take_their_time() {
+        let mut adsr = Adsr::new(PARAMS, SAMPLE_RATE);
+        assert_eq!(adsr.next(), 0.0);
+
+        adsr.gate_on();
+        assert_eq!(run(&mut adsr, 7), 0.875);
+        assert_eq!(adsr.stage(), Stage::Attack);
+        assert_eq!(run(&mut adsr, 1), 1.0);
+        assert_eq!(adsr.stage(), Stage::Decay);
+
+        run(&mut adsr, 64);
+        assert_eq!(adsr.stage(), Stage::Decay);
+        run(&mut adsr, 64);
+        assert_eq!(adsr.stage(), Stage::Sustain);
+        assert_eq!(run(&mut adsr, 1000), 0.5);
+
+        adsr.gate_off();
+        run(&mut adsr, 128);
+        assert_eq!(adsr.stage(), Stage::Release);
+        run(&mut adsr, 128);
+        assert!(!adsr.is_active());
+    }
+
+    #[test]
+    fn releasing_early_falls_from_where_it_is() {
+        let mut adsr = Adsr::new(PARAMS, SAMPLE_RATE);
+
+        adsr.gate_on();
+        let level = run(&mut adsr, 4);
+        adsr.gate_off();
+
+        assert!(adsr.next() < level);
+        assert_eq!(adsr.stage(), Stage::Release);
+    }
+}
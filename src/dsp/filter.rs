@@ -0,0 +1,235 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterMode {
+    #[default]
+    Lowpass,
+    Bandpass,
+    Highpass,
+}
+
+/// Resonant state-variable filter using the trapezoidal (zero-delay
+/// feedback) topology, which stays stable under fast cutoff modulation.
+#[derive(Clone, Debug)]
+pub struct Svf {
+    mode: FilterMode,
+    sample_rate: f64,
+    /// Kept for working the coefficients out again at another sample rate.
+    cutoff: f64,
+    resonance: f64,
+    k: f64,
+    a1: f64,
+    a2: f64,
+    a3: f64,
+    ic1eq: f64,
+    ic2eq: f64,
+}
+
+impl Svf {
+    pub fn new(mode: FilterMode, sample_rate: f64) -> Self {
+        let mut svf = Self {
+            mode,
+            sample_rate,
+            cutoff: 1000.0,
+            resonance: 0.0,
+            k: 2.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        };
+
+        svf.set(svf.cutoff, svf.resonance);
+        svf
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.set(self.cutoff, self.resonance);
+    }
+
+    /// `resonance` goes from 0 (no peak) to 1 (self-oscillation).
+    pub fn set(&mut self, cutoff: f64, resonance: f64) {
+        self.cutoff = cutoff;
+        self.resonance = resonance;
+
+        let cutoff = cutoff.clamp(10.0, self.sample_rate * 0.49);
+        let g = (PI * cutoff / self.sample_rate).tan();
+
+        self.k = 2.0 - 2.0 * resonance.clamp(0.0, 0.99);
+        self.a1 = 1.0 / (1.0 + g * (g + self.k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    pub fn process(&mut self, input: f64) -> f64 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        match self.mode {
+            FilterMode::Lowpass => v2,
+            FilterMode::Bandpass => v1,
+            FilterMode::Highpass => input - self.k * v1 - v2,
+        }
+    }
+}
+
+/// Shapes of the equaliser filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BiquadShape {
+    Peak,
+    LowShelf,
+    HighShelf,
+}
+
+/// Second order filter with the equaliser shapes from the Audio EQ
+/// Cookbook, in transposed direct form II.
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Passes everything through until it is set.
+    pub fn new() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn set(&mut self, shape: BiquadShape, freq: f64, q: f64, gain_db: f64, sample_rate: f64) {
+        let freq = freq.clamp(10.0, sample_rate * 0.49);
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(0.01));
+
+        let (b0, b1, b2, a0, a1, a2) = match shape {
+            BiquadShape::Peak => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+
+            BiquadShape::LowShelf => {
+                let root = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + root),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - root),
+                    (a + 1.0) + (a - 1.0) * cos + root,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - root,
+                )
+            }
+
+            BiquadShape::HighShelf => {
+                let root = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + root),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - root),
+                    (a + 1.0) - (a - 1.0) * cos + root,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - root,
+                )
+            }
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    pub fn process(&mut self, input: f64) -> f64 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Peak level of a unit sine once the filter has settled.
+    fn response(svf: &mut Svf, freq: f64) -> f64 {
+        let sample_rate = svf.sample_rate;
+        let sine = |i: usize| (2.0 * PI * freq * i as f64 / sample_rate).sin();
+        let settle = sample_rate as usize / 10;
+
+        for i in 0..settle {
+            svf.process(sine(i));
+        }
+
+        (settle..settle * 2)
+            .map(|i| svf.process(sine(i)).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn passes_and_stops_either_side_of_the_cutoff() {
+        let mut lowpass = Svf::new(FilterMode::Lowpass, SAMPLE_RATE);
+        lowpass.set(1000.0, 0.0);
+        assert!((response(&mut lowpass, 50.0) - 1.0).abs() < 0.01);
+        assert!(response(&mut lowpass, 15000.0) < 0.01);
+
+        let mut highpass = Svf::new(FilterMode::Highpass, SAMPLE_RATE);
+        highpass.set(1000.0, 0.0);
+        assert!(response(&mut highpass, 50.0) < 0.01);
+        assert!((response(&mut highpass, 15000.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn cutoff_holds_across_sample_rates() {
+        // Without resonance the cutoff is 6 dB down.
+        let mut svf = Svf::new(FilterMode::Lowpass, SAMPLE_RATE);
+        svf.set(1000.0, 0.0);
+        assert!((response(&mut svf, 1000.0) - 0.5).abs() < 0.01);
+
+        svf.set_sample_rate(2.0 * SAMPLE_RATE);
+        svf.reset();
+        assert!((response(&mut svf, 1000.0) - 0.5).abs() < 0.01);
+
+        // Resonance peaks at the cutoff.
+        svf.set(1000.0, 0.9);
+        assert!(response(&mut svf, 1000.0) > 4.0);
+    }
+}
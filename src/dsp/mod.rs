@@ -0,0 +1,34 @@
+pub mod envelope;
+pub mod filter;
+pub mod oscillator;
+pub mod random;
+
+/// Equal-tempered frequency of a (possibly fractional) MIDI pitch, A4 = 69.
+pub fn midi_to_freq(pitch: f64) -> f64 {
+    440.0 * ((pitch - 69.0) / 12.0).exp2()
+}
+
+/// Linear gain of a level in decibels.
+pub fn db_to_gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Level in decibels of a linear gain, with silence at negative infinity.
+pub fn gain_to_db(gain: f64) -> f64 {
+    20.0 * gain.log10()
+}
+
+const PITCH_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Scientific pitch name of a MIDI pitch, middle C (60) is C4.
+pub fn pitch_name(pitch: u8) -> String {
+    let octave = pitch as i32 / 12 - 1;
+    format!("{}{octave}", PITCH_NAMES[pitch as usize % 12])
+}
+
+/// Whether a MIDI pitch falls on a black key.
+pub fn is_black_key(pitch: u8) -> bool {
+    PITCH_NAMES[pitch as usize % 12].ends_with('#')
+}
@@ -0,0 +1,510 @@
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dsp::random::Rng;
+
+/// A periodic signal generator producing samples in `[-1, 1]`.
+///
+/// Oscillators keep their own clock, so `next` advances by exactly one
+/// sample at the current sample rate.
+pub trait Oscillator: Send {
+    fn set_sample_rate(&mut self, sample_rate: f64);
+    fn set_frequency(&mut self, freq: f64);
+    /// Jump to a position in the cycle, in `[0, 1)`.
+    fn set_phase(&mut self, phase: f64);
+    fn next(&mut self) -> f64;
+
+    /// Only waveforms with hard edges have anything to band-limit.
+    fn set_quality(&mut self, _quality: Quality) {}
+
+    /// Fraction of each cycle a pulse wave is high for, clamped away from
+    /// 0 and 1 where it would fall silent. Only pulse waves have one.
+    fn set_pulse_width(&mut self, _pulse_width: f64) {}
+}
+
+/// How waveforms with discontinuities are generated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    /// Straight from the phase. Cheap, but aliases badly at high pitches.
+    Naive,
+    /// Discontinuities are smoothed with polynomial band-limited steps
+    /// (PolyBLEP) and ramps (PolyBLAMP).
+    #[default]
+    PolyBlep,
+}
+
+/// Residual of a band-limited step of height 2 at `t = 0`, where `t` is
+/// the phase since the edge and `dt` the phase increment per sample.
+fn poly_blep(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = t / dt;
+        2.0 * t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Integral of `poly_blep`: the residual of a band-limited corner where
+/// the slope changes by 2 per sample.
+fn poly_blamp(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = 1.0 - t / dt;
+        t * t * t / 3.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt + 1.0;
+        t * t * t / 3.0
+    } else {
+        0.0
+    }
+}
+
+/// The oscillator shapes instruments can choose between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,
+    #[default]
+    Saw,
+    ReverseSaw,
+    Square,
+    Triangle,
+    Noise,
+}
+
+impl Waveform {
+    pub fn build(self, freq: f64, sample_rate: f64, seed: u64) -> Box<dyn Oscillator> {
+        match self {
+            Waveform::Sine => Box::new(Sine::new(freq, sample_rate)),
+            Waveform::Saw => Box::new(SawWave::new(freq, sample_rate)),
+            Waveform::ReverseSaw => Box::new(SawWave::reversed(freq, sample_rate)),
+            Waveform::Square => Box::new(Square::new(freq, sample_rate)),
+            Waveform::Triangle => Box::new(Triangle::new(freq, sample_rate)),
+            Waveform::Noise => Box::new(Noise::new(sample_rate, sample_rate, seed)),
+        }
+    }
+}
+
+/// Phase accumulator shared by every oscillator. Phase is kept in `[0, 1)`
+/// rather than radians so that wrapping is exact.
+#[derive(Clone, Debug)]
+pub struct Phasor {
+    phase: f64,
+    freq: f64,
+    sample_rate: f64,
+    increment: f64,
+}
+
+impl Phasor {
+    pub fn new(freq: f64, sample_rate: f64) -> Self {
+        Self {
+            phase: 0.0,
+            freq,
+            sample_rate,
+            increment: freq / sample_rate,
+        }
+    }
+
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.increment = self.freq / sample_rate;
+    }
+
+    pub fn set_frequency(&mut self, freq: f64) {
+        self.freq = freq;
+        self.increment = freq / self.sample_rate;
+    }
+
+    pub fn set_phase(&mut self, phase: f64) {
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Returns the phase before advancing, and whether the cycle wrapped.
+    pub fn tick(&mut self) -> (f64, bool) {
+        let phase = self.phase;
+        self.phase += self.increment;
+
+        let wrapped = self.phase >= 1.0;
+        if wrapped {
+            self.phase -= self.phase.floor();
+        }
+
+        (phase, wrapped)
+    }
+}
+
+macro_rules! impl_phasor_setters {
+    () => {
+        fn set_sample_rate(&mut self, sample_rate: f64) {
+            self.phasor.set_sample_rate(sample_rate);
+        }
+
+        fn set_frequency(&mut self, freq: f64) {
+            self.phasor.set_frequency(freq);
+        }
+
+        fn set_phase(&mut self, phase: f64) {
+            self.phasor.set_phase(phase);
+        }
+    };
+}
+
+pub struct Sine {
+    phasor: Phasor,
+}
+
+impl Sine {
+    pub fn new(freq: f64, sample_rate: f64) -> Self {
+        Self { phasor: Phasor::new(freq, sample_rate) }
+    }
+}
+
+impl Oscillator for Sine {
+    impl_phasor_setters!();
+
+    fn next(&mut self) -> f64 {
+        let (phase, _) = self.phasor.tick();
+        (phase * TAU).sin()
+    }
+}
+
+/// Rising ramp, or a falling one when `reversed`.
+pub struct SawWave {
+    phasor: Phasor,
+    reversed: bool,
+    quality: Quality,
+}
+
+impl SawWave {
+    pub fn new(freq: f64, sample_rate: f64) -> Self {
+        Self {
+            phasor: Phasor::new(freq, sample_rate),
+            reversed: false,
+            quality: Quality::default(),
+        }
+    }
+
+    pub fn reversed(freq: f64, sample_rate: f64) -> Self {
+        Self {
+            reversed: true,
+            ..Self::new(freq, sample_rate)
+        }
+    }
+}
+
+impl Oscillator for SawWave {
+    impl_phasor_setters!();
+
+    fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+    }
+
+    fn next(&mut self) -> f64 {
+        let dt = self.phasor.increment();
+        let (phase, _) = self.phasor.tick();
+        let mut value = 2.0 * phase - 1.0;
+
+        if self.quality == Quality::PolyBlep {
+            value -= poly_blep(phase, dt);
+        }
+
+        if self.reversed { -value } else { value }
+    }
+}
+
+/// Pulse wave that is high for `pulse_width` of each cycle.
+pub struct Square {
+    phasor: Phasor,
+    pulse_width: f64,
+    quality: Quality,
+}
+
+impl Square {
+    pub fn new(freq: f64, sample_rate: f64) -> Self {
+        Self {
+            phasor: Phasor::new(freq, sample_rate),
+            pulse_width: 0.5,
+            quality: Quality::default(),
+        }
+    }
+}
+
+impl Oscillator for Square {
+    impl_phasor_setters!();
+
+    fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+    }
+
+    fn set_pulse_width(&mut self, pulse_width: f64) {
+        self.pulse_width = pulse_width.clamp(0.01, 0.99);
+    }
+
+    fn next(&mut self) -> f64 {
+        let dt = self.phasor.increment();
+        let (phase, _) = self.phasor.tick();
+        let mut value = if phase < self.pulse_width { 1.0 } else { -1.0 };
+
+        if self.quality == Quality::PolyBlep {
+            let falling = (phase - self.pulse_width).rem_euclid(1.0);
+            value += poly_blep(phase, dt) - poly_blep(falling, dt);
+        }
+
+        value
+    }
+}
+
+pub struct Triangle {
+    phasor: Phasor,
+    quality: Quality,
+}
+
+impl Triangle {
+    pub fn new(freq: f64, sample_rate: f64) -> Self {
+        Self {
+            phasor: Phasor::new(freq, sample_rate),
+            quality: Quality::default(),
+        }
+    }
+}
+
+impl Oscillator for Triangle {
+    impl_phasor_setters!();
+
+    fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+    }
+
+    fn next(&mut self) -> f64 {
+        // Starts at zero and rising, like the sine.
+        let dt = self.phasor.increment();
+        let (phase, _) = self.phasor.tick();
+        let shifted = (phase + 0.25).fract();
+        let mut value = 1.0 - 4.0 * (shifted - 0.5).abs();
+
+        // The slope flips by 8 per cycle at the peak and the trough.
+        if self.quality == Quality::PolyBlep {
+            let peak = (phase + 0.75).fract();
+            let trough = shifted;
+            value += 4.0 * dt * (poly_blamp(trough, dt) - poly_blamp(peak, dt));
+        }
+
+        value
+    }
+}
+
+/// Sample-and-hold noise that picks a new random value `freq` times per
+/// second. At the sample rate or above it is plain white noise.
+pub struct Noise {
+    phasor: Phasor,
+    rng: Rng,
+    value: f64,
+}
+
+impl Noise {
+    pub fn new(freq: f64, sample_rate: f64, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let value = rng.next_bipolar();
+
+        Self {
+            phasor: Phasor::new(freq, sample_rate),
+            rng,
+            value,
+        }
+    }
+}
+
+impl Oscillator for Noise {
+    impl_phasor_setters!();
+
+    fn next(&mut self) -> f64 {
+        let value = self.value;
+
+        let (_, wrapped) = self.phasor.tick();
+        if wrapped {
+            self.value = self.rng.next_bipolar();
+        }
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Count upward zero crossings over one second of output.
+    fn measure_frequency(osc: &mut dyn Oscillator) -> usize {
+        let mut previous = osc.next();
+        let mut crossings = 0;
+
+        for _ in 1..SAMPLE_RATE as usize {
+            let sample = osc.next();
+            if previous < 0.0 && sample >= 0.0 {
+                crossings += 1;
+            }
+            previous = sample;
+        }
+
+        crossings
+    }
+
+    fn assert_frequency(osc: &mut dyn Oscillator, freq: f64) {
+        let measured = measure_frequency(osc) as f64;
+        assert!(
+            (measured - freq).abs() <= 1.0,
+            "expected {freq} Hz, measured {measured} Hz"
+        );
+    }
+
+    #[test]
+    fn sine_frequency() {
+        for freq in [55.0, 440.0, 1234.5, 5000.0] {
+            assert_frequency(&mut Sine::new(freq, SAMPLE_RATE), freq);
+        }
+    }
+
+    #[test]
+    fn saw_frequency() {
+        for freq in [55.0, 440.0, 1234.5, 5000.0] {
+            assert_frequency(&mut SawWave::new(freq, SAMPLE_RATE), freq);
+            assert_frequency(&mut SawWave::reversed(freq, SAMPLE_RATE), freq);
+        }
+    }
+
+    #[test]
+    fn reversed_saw_falls() {
+        let mut saw = SawWave::new(100.0, SAMPLE_RATE);
+        let mut reversed = SawWave::reversed(100.0, SAMPLE_RATE);
+
+        for _ in 0..1000 {
+            assert_eq!(saw.next(), -reversed.next());
+        }
+    }
+
+    #[test]
+    fn square_frequency_and_pulse_width() {
+        for freq in [55.0, 440.0, 1234.5] {
+            assert_frequency(&mut Square::new(freq, SAMPLE_RATE), freq);
+        }
+
+        let mut square = Square::new(100.0, SAMPLE_RATE);
+        square.set_pulse_width(0.25);
+        assert_frequency(&mut square, 100.0);
+
+        let high = (0..SAMPLE_RATE as usize)
+            .filter(|_| square.next() > 0.0)
+            .count() as f64;
+        assert!((high / SAMPLE_RATE - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn triangle_frequency_and_shape() {
+        for freq in [55.0, 440.0, 1234.5, 5000.0] {
+            assert_frequency(&mut Triangle::new(freq, SAMPLE_RATE), freq);
+        }
+
+        let mut triangle = Triangle::new(1.0, 4.0);
+        triangle.set_quality(Quality::Naive);
+        let samples: Vec<f64> = (0..4).map(|_| triangle.next()).collect();
+        assert_eq!(samples, vec![0.0, 1.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn noise_hold_rate_and_range() {
+        let mut noise = Noise::new(1000.0, SAMPLE_RATE, 7);
+        let mut previous = noise.next();
+        let mut changes = 0;
+
+        for _ in 1..SAMPLE_RATE as usize {
+            let sample = noise.next();
+            assert!((-1.0..1.0).contains(&sample));
+            if sample != previous {
+                changes += 1;
+            }
+            previous = sample;
+        }
+
+        assert!((changes as f64 - 1000.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn phase_and_frequency_setters() {
+        let mut sine = Sine::new(1.0, 4.0);
+        sine.set_phase(0.25);
+        assert!((sine.next() - 1.0).abs() < 1e-12);
+
+        sine.set_sample_rate(SAMPLE_RATE);
+        sine.set_frequency(440.0);
+        assert_frequency(&mut sine, 440.0);
+    }
+
+    /// Fraction of the signal's energy that is not at a harmonic of `freq`.
+    ///
+    /// The render is one tenth of a second long and `freq` a multiple of
+    /// 10 Hz, so every harmonic and every aliased partial folded back from
+    /// above Nyquist lands exactly on a DFT bin and nothing leaks.
+    fn aliasing_ratio(osc: &mut dyn Oscillator, freq: f64) -> f64 {
+        let n = SAMPLE_RATE as usize / 10;
+        let fundamental_bin = (freq / 10.0) as usize;
+        let samples: Vec<f64> = (0..n).map(|_| osc.next()).collect();
+        let twiddles: Vec<(f64, f64)> = (0..n)
+            .map(|i| (TAU * i as f64 / n as f64).sin_cos())
+            .collect();
+
+        let mut harmonic = 0.0;
+        let mut aliased = 0.0;
+
+        for bin in 1..n / 2 {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (i, sample) in samples.iter().enumerate() {
+                let (sin, cos) = twiddles[(bin * i) % n];
+                re += sample * cos;
+                im -= sample * sin;
+            }
+
+            let power = re * re + im * im;
+            if bin % fundamental_bin == 0 {
+                harmonic += power;
+            } else {
+                aliased += power;
+            }
+        }
+
+        aliased / (harmonic + aliased)
+    }
+
+    #[test]
+    fn poly_blep_reduces_aliasing() {
+        // High enough that plenty of harmonics fold back below Nyquist.
+        const FREQ: f64 = 2510.0;
+        type Make = fn() -> Box<dyn Oscillator>;
+
+        let oscillators: [(&str, Make); 4] = [
+            ("saw", || Box::new(SawWave::new(FREQ, SAMPLE_RATE))),
+            ("reversed saw", || Box::new(SawWave::reversed(FREQ, SAMPLE_RATE))),
+            ("square", || Box::new(Square::new(FREQ, SAMPLE_RATE))),
+            ("triangle", || Box::new(Triangle::new(FREQ, SAMPLE_RATE))),
+        ];
+
+        for (name, make) in oscillators {
+            let mut naive = make();
+            naive.set_quality(Quality::Naive);
+            let naive = aliasing_ratio(naive.as_mut(), FREQ);
+
+            let mut blep = make();
+            blep.set_quality(Quality::PolyBlep);
+            let blep = aliasing_ratio(blep.as_mut(), FREQ);
+
+            assert!(blep < naive / 10.0, "{name}: naive {naive:.2e}, polyblep {blep:.2e}");
+        }
+    }
+}
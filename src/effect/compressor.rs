@@ -0,0 +1,168 @@
+use crate::audio::Frame;
+use crate::dsp::{db_to_gain, gain_to_db};
+use crate::effect::{Effect, Param};
+
+pub const COMPRESSOR_PARAMS: [Param; 5] = [
+    Param::new("threshold", "dB", -60.0, 0.0, -18.0),
+    Param::new("ratio", ":1", 1.0, 20.0, 4.0),
+    Param::new("attack", "ms", 0.1, 100.0, 10.0).log(),
+    Param::new("release", "ms", 10.0, 1000.0, 120.0).log(),
+    Param::new("makeup", "dB", 0.0, 24.0, 0.0),
+];
+
+pub const LIMITER_PARAMS: [Param; 2] = [
+    Param::new("ceiling", "dB", -24.0, 0.0, -0.3),
+    Param::new("release", "ms", 1.0, 1000.0, 50.0).log(),
+];
+
+/// How much of the last value a one pole smoother keeps each frame, to
+/// cover most of the way in `ms`.
+fn coefficient(ms: f64, sample_rate: f64) -> f32 {
+    (-1.0 / (ms / 1000.0 * sample_rate).max(1.0)).exp() as f32
+}
+
+/// The louder side of a frame.
+fn peak(frame: &Frame) -> f32 {
+    frame[0].abs().max(frame[1].abs())
+}
+
+/// Turns whatever goes over the threshold down by the ratio, with both
+/// sides turned down together.
+pub struct Compressor {
+    sample_rate: f64,
+    threshold: f32,
+    ratio: f32,
+    attack_ms: f64,
+    release_ms: f64,
+    attack: f32,
+    release: f32,
+    makeup: f32,
+    /// Gain reduction in decibels.
+    reduction: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut compressor = Self {
+            sample_rate,
+            threshold: COMPRESSOR_PARAMS[0].default as f32,
+            ratio: COMPRESSOR_PARAMS[1].default as f32,
+            attack_ms: COMPRESSOR_PARAMS[2].default,
+            release_ms: COMPRESSOR_PARAMS[3].default,
+            attack: 0.0,
+            release: 0.0,
+            makeup: COMPRESSOR_PARAMS[4].default as f32,
+            reduction: 0.0,
+        };
+
+        compressor.set_sample_rate(sample_rate);
+        compressor
+    }
+}
+
+impl Effect for Compressor {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.attack = coefficient(self.attack_ms, sample_rate);
+        self.release = coefficient(self.release_ms, sample_rate);
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.threshold = value as f32,
+            1 => self.ratio = value as f32,
+            2 => {
+                self.attack_ms = value;
+                self.attack = coefficient(value, self.sample_rate);
+            }
+            3 => {
+                self.release_ms = value;
+                self.release = coefficient(value, self.sample_rate);
+            }
+            4 => self.makeup = value as f32,
+            _ => (),
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        let slope = 1.0 - 1.0 / self.ratio;
+
+        for frame in out.iter_mut() {
+            let level = gain_to_db(peak(frame) as f64) as f32;
+            let target = (level - self.threshold).max(0.0) * slope;
+
+            let coefficient = if target > self.reduction { self.attack } else { self.release };
+            self.reduction = target + (self.reduction - target) * coefficient;
+
+            let gain = db_to_gain((self.makeup - self.reduction) as f64) as f32;
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reduction = 0.0;
+    }
+}
+
+/// Keeps peaks under the ceiling: turns down at once, and back up over the
+/// release time.
+pub struct Limiter {
+    sample_rate: f64,
+    ceiling: f32,
+    release_ms: f64,
+    release: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut limiter = Self {
+            sample_rate,
+            ceiling: db_to_gain(LIMITER_PARAMS[0].default) as f32,
+            release_ms: LIMITER_PARAMS[1].default,
+            release: 0.0,
+            gain: 1.0,
+        };
+
+        limiter.set_sample_rate(sample_rate);
+        limiter
+    }
+}
+
+impl Effect for Limiter {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.release = coefficient(self.release_ms, sample_rate);
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.ceiling = db_to_gain(value) as f32,
+            1 => {
+                self.release_ms = value;
+                self.release = coefficient(value, self.sample_rate);
+            }
+            _ => (),
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        for frame in out.iter_mut() {
+            let peak = peak(frame);
+            let needed = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+
+            self.gain = match needed < self.gain {
+                true => needed,
+                false => needed + (self.gain - needed) * self.release,
+            };
+
+            frame[0] *= self.gain;
+            frame[1] *= self.gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+}
@@ -0,0 +1,96 @@
+use crate::audio::Frame;
+use crate::effect::{Effect, Param};
+
+/// Longest echo, enough for four beats at 30 BPM.
+const MAX_SECONDS: f64 = 8.0;
+
+pub const PARAMS: [Param; 4] = [
+    Param::new("time", "beats", 0.0625, 4.0, 0.75).log(),
+    Param::new("feedback", "", 0.0, 0.95, 0.35),
+    Param::new("mix", "", 0.0, 1.0, 0.3),
+    // How much of each echo crosses to the other side.
+    Param::new("pingpong", "", 0.0, 1.0, 0.0),
+];
+
+/// Echoes a number of beats apart, following the tempo.
+pub struct Delay {
+    sample_rate: f64,
+    tempo: f64,
+    buffer: Vec<Frame>,
+    write: usize,
+    time: f64,
+    feedback: f32,
+    mix: f32,
+    pingpong: f32,
+}
+
+impl Delay {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut delay = Self {
+            sample_rate: 0.0,
+            tempo: 120.0,
+            buffer: Vec::new(),
+            write: 0,
+            time: PARAMS[0].default,
+            feedback: PARAMS[1].default as f32,
+            mix: PARAMS[2].default as f32,
+            pingpong: PARAMS[3].default as f32,
+        };
+
+        delay.set_sample_rate(sample_rate);
+        delay
+    }
+
+    /// Frames between echoes at the current tempo.
+    fn delay_frames(&self) -> usize {
+        let seconds = self.time * 60.0 / self.tempo.max(1.0);
+        ((seconds * self.sample_rate).round() as usize).clamp(1, self.buffer.len() - 1)
+    }
+}
+
+impl Effect for Delay {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.buffer = vec![[0.0; 2]; (MAX_SECONDS * sample_rate) as usize + 1];
+            self.write = 0;
+        }
+    }
+
+    fn set_tempo(&mut self, tempo: f64) {
+        self.tempo = tempo;
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.time = value,
+            1 => self.feedback = value as f32,
+            2 => self.mix = value as f32,
+            3 => self.pingpong = value as f32,
+            _ => (),
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        let len = self.buffer.len();
+        let delay = self.delay_frames();
+        let (straight, across) = (1.0 - self.pingpong, self.pingpong);
+
+        for frame in out.iter_mut() {
+            let [left, right] = self.buffer[(self.write + len - delay) % len];
+
+            self.buffer[self.write] = [
+                frame[0] + self.feedback * (left * straight + right * across),
+                frame[1] + self.feedback * (right * straight + left * across),
+            ];
+            self.write = (self.write + 1) % len;
+
+            frame[0] = frame[0] * (1.0 - self.mix) + left * self.mix;
+            frame[1] = frame[1] * (1.0 - self.mix) + right * self.mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill([0.0; 2]);
+    }
+}
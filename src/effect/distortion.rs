@@ -0,0 +1,79 @@
+use crate::audio::Frame;
+use crate::dsp::{
+    db_to_gain,
+    filter::{FilterMode, Svf},
+};
+use crate::effect::{Effect, Param};
+
+pub const PARAMS: [Param; 4] = [
+    Param::new("drive", "dB", 0.0, 48.0, 12.0),
+    Param::new("tone", "Hz", 500.0, 20000.0, 6000.0).log(),
+    Param::new("mix", "", 0.0, 1.0, 1.0),
+    Param::new("output", "dB", -24.0, 12.0, -6.0),
+];
+
+/// Soft clipping, with a lowpass after it to take the edge off.
+pub struct Distortion {
+    sample_rate: f64,
+    drive: f32,
+    tone: f64,
+    mix: f32,
+    output: f32,
+    filters: [Svf; 2],
+}
+
+impl Distortion {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut distortion = Self {
+            sample_rate,
+            drive: db_to_gain(PARAMS[0].default) as f32,
+            tone: PARAMS[1].default,
+            mix: PARAMS[2].default as f32,
+            output: db_to_gain(PARAMS[3].default) as f32,
+            filters: [
+                Svf::new(FilterMode::Lowpass, sample_rate),
+                Svf::new(FilterMode::Lowpass, sample_rate),
+            ],
+        };
+
+        distortion.set_sample_rate(sample_rate);
+        distortion
+    }
+}
+
+impl Effect for Distortion {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        for filter in self.filters.iter_mut() {
+            filter.set_sample_rate(sample_rate);
+            filter.set(self.tone, 0.0);
+        }
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.drive = db_to_gain(value) as f32,
+            1 => {
+                self.tone = value;
+                self.filters.iter_mut().for_each(|filter| filter.set(value, 0.0));
+            }
+            2 => self.mix = value as f32,
+            3 => self.output = db_to_gain(value) as f32,
+            _ => (),
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        for frame in out.iter_mut() {
+            for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                let clipped = filter.process((*sample * self.drive).tanh() as f64) as f32;
+                *sample = (*sample * (1.0 - self.mix) + clipped * self.mix) * self.output;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.filters.iter_mut().for_each(Svf::reset);
+    }
+}
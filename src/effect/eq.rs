@@ -0,0 +1,78 @@
+use crate::audio::Frame;
+use crate::dsp::filter::{Biquad, BiquadShape};
+use crate::effect::{Effect, Param};
+
+/// Q of the shelves, the gentlest slope without a bump.
+const SHELF_Q: f64 = 0.707;
+
+pub const PARAMS: [Param; 7] = [
+    Param::new("lowfreq", "Hz", 20.0, 1000.0, 120.0).log(),
+    Param::new("lowgain", "dB", -18.0, 18.0, 0.0),
+    Param::new("midfreq", "Hz", 100.0, 10000.0, 1000.0).log(),
+    Param::new("midgain", "dB", -18.0, 18.0, 0.0),
+    Param::new("midq", "", 0.1, 10.0, 0.7).log(),
+    Param::new("highfreq", "Hz", 1000.0, 20000.0, 8000.0).log(),
+    Param::new("highgain", "dB", -18.0, 18.0, 0.0),
+];
+
+/// Three band parametric equaliser: a low shelf, a peak and a high shelf.
+pub struct Equalizer {
+    sample_rate: f64,
+    values: [f64; 7],
+    /// Low, mid and high for each side.
+    bands: [[Biquad; 3]; 2],
+}
+
+impl Equalizer {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut eq = Self {
+            sample_rate,
+            values: PARAMS.map(|param| param.default),
+            bands: [
+                [Biquad::new(), Biquad::new(), Biquad::new()],
+                [Biquad::new(), Biquad::new(), Biquad::new()],
+            ],
+        };
+
+        eq.update();
+        eq
+    }
+
+    fn update(&mut self) {
+        let [low_freq, low_gain, mid_freq, mid_gain, mid_q, high_freq, high_gain] = self.values;
+
+        for [low, mid, high] in self.bands.iter_mut() {
+            low.set(BiquadShape::LowShelf, low_freq, SHELF_Q, low_gain, self.sample_rate);
+            mid.set(BiquadShape::Peak, mid_freq, mid_q, mid_gain, self.sample_rate);
+            high.set(BiquadShape::HighShelf, high_freq, SHELF_Q, high_gain, self.sample_rate);
+        }
+    }
+}
+
+impl Effect for Equalizer {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.update();
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        if let Some(current) = self.values.get_mut(index) {
+            *current = value;
+            self.update();
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        for frame in out.iter_mut() {
+            for (sample, bands) in frame.iter_mut().zip(self.bands.iter_mut()) {
+                let filtered = bands.iter_mut()
+                    .fold(*sample as f64, |signal, band| band.process(signal));
+                *sample = filtered as f32;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bands.iter_mut().flatten().for_each(Biquad::reset);
+    }
+}
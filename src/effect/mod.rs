@@ -0,0 +1,271 @@
+pub mod compressor;
+pub mod delay;
+pub mod distortion;
+pub mod eq;
+pub mod reverb;
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::Frame;
+
+use compressor::{Compressor, Limiter};
+use delay::Delay;
+use distortion::Distortion;
+use eq::Equalizer;
+use reverb::Reverb;
+
+/// Something that changes sound in place, inserted on a track or bus.
+/// Effects run on the audio thread, so none of these may block or allocate.
+pub trait Effect: Send {
+    fn set_sample_rate(&mut self, sample_rate: f64);
+    /// Beats per minute, for effects that follow the tempo.
+    fn set_tempo(&mut self, _tempo: f64) {}
+    /// Set a parameter by its place in `EffectKind::params`, within range.
+    fn set_param(&mut self, index: usize, value: f64);
+    fn process(&mut self, out: &mut [Frame]);
+    /// Forget anything still ringing out.
+    fn reset(&mut self);
+}
+
+/// How a parameter moves from one end of its range to the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    Linear,
+    /// Equal steps multiply, for frequencies and times.
+    Log,
+}
+
+/// A named, ranged value an effect can be set with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Param {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub scale: Scale,
+}
+
+impl Param {
+    pub const fn new(name: &'static str, unit: &'static str, min: f64, max: f64, default: f64) -> Self {
+        Self { name, unit, min, max, default, scale: Scale::Linear }
+    }
+
+    pub const fn log(self) -> Self {
+        Self { scale: Scale::Log, ..self }
+    }
+
+    /// Where a value sits in the range, from 0 to 1.
+    pub fn normalize(&self, value: f64) -> f64 {
+        let value = value.clamp(self.min, self.max);
+
+        match self.scale {
+            Scale::Linear => (value - self.min) / (self.max - self.min),
+            Scale::Log => (value / self.min).ln() / (self.max / self.min).ln(),
+        }
+    }
+
+    /// The value at a place in the range, from 0 to 1.
+    pub fn denormalize(&self, position: f64) -> f64 {
+        let position = position.clamp(0.0, 1.0);
+
+        match self.scale {
+            Scale::Linear => self.min + position * (self.max - self.min),
+            Scale::Log => self.min * (self.max / self.min).powf(position),
+        }
+    }
+
+    /// A value moved by `steps` hundredths of the range.
+    pub fn step(&self, value: f64, steps: i32) -> f64 {
+        self.denormalize(self.normalize(value) + steps as f64 / 100.0)
+    }
+
+    /// A value with its unit, for showing.
+    pub fn format(&self, value: f64) -> String {
+        let digits = if value.abs() >= 100.0 { 0 } else { 2 };
+        format!("{value:.digits$} {}", self.unit)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    Delay,
+    Reverb,
+    Equalizer,
+    Compressor,
+    Limiter,
+    Distortion,
+}
+
+impl EffectKind {
+    pub const ALL: [EffectKind; 6] = [
+        EffectKind::Delay,
+        EffectKind::Reverb,
+        EffectKind::Equalizer,
+        EffectKind::Compressor,
+        EffectKind::Limiter,
+        EffectKind::Distortion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EffectKind::Delay => "delay",
+            EffectKind::Reverb => "reverb",
+            EffectKind::Equalizer => "eq",
+            EffectKind::Compressor => "compressor",
+            EffectKind::Limiter => "limiter",
+            EffectKind::Distortion => "distortion",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn params(self) -> &'static [Param] {
+        match self {
+            EffectKind::Delay => &delay::PARAMS,
+            EffectKind::Reverb => &reverb::PARAMS,
+            EffectKind::Equalizer => &eq::PARAMS,
+            EffectKind::Compressor => &compressor::COMPRESSOR_PARAMS,
+            EffectKind::Limiter => &compressor::LIMITER_PARAMS,
+            EffectKind::Distortion => &distortion::PARAMS,
+        }
+    }
+}
+
+/// An effect as kept in the project, the live one is built from it for the
+/// audio thread.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EffectSpec {
+    pub kind: EffectKind,
+    /// By name, so that saved projects survive parameters being added.
+    /// Anything missing is at its default.
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub bypassed: bool,
+}
+
+impl EffectSpec {
+    pub fn new(kind: EffectKind) -> Self {
+        Self {
+            kind,
+            params: BTreeMap::new(),
+            bypassed: false,
+        }
+    }
+
+    pub fn value(&self, index: usize) -> f64 {
+        let param = &self.kind.params()[index];
+
+        self.params.get(param.name)
+            .map_or(param.default, |value| value.clamp(param.min, param.max))
+    }
+
+    /// Set a parameter by name, kept in range. Returns where it ended up,
+    /// or nothing for a parameter the effect does not have.
+    pub fn set(&mut self, name: &str, value: f64) -> Option<f64> {
+        let param = self.kind.params().iter()
+            .find(|param| param.name.eq_ignore_ascii_case(name))?;
+        let value = value.clamp(param.min, param.max);
+
+        self.params.insert(param.name.to_string(), value);
+        Some(value)
+    }
+
+    pub fn build(&self, sample_rate: f64) -> Box<dyn Effect> {
+        let mut effect: Box<dyn Effect> = match self.kind {
+            EffectKind::Delay => Box::new(Delay::new(sample_rate)),
+            EffectKind::Reverb => Box::new(Reverb::new(sample_rate)),
+            EffectKind::Equalizer => Box::new(Equalizer::new(sample_rate)),
+            EffectKind::Compressor => Box::new(Compressor::new(sample_rate)),
+            EffectKind::Limiter => Box::new(Limiter::new(sample_rate)),
+            EffectKind::Distortion => Box::new(Distortion::new(sample_rate)),
+        };
+
+        for index in 0..self.kind.params().len() {
+            effect.set_param(index, self.value(index));
+        }
+
+        effect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 48000.0;
+
+    fn impulse(frames: usize) -> Vec<Frame> {
+        let mut out = vec![[0.0; 2]; frames];
+        out[0] = [1.0, 1.0];
+        out
+    }
+
+    #[test]
+    fn params_keep_to_their_range() {
+        let mut spec = EffectSpec::new(EffectKind::Delay);
+        assert_eq!(spec.set("feedback", 3.0), Some(delay::PARAMS[1].max));
+        assert_eq!(spec.set("nonsense", 1.0), None);
+
+        let freq = eq::PARAMS[0];
+        assert!((freq.denormalize(freq.normalize(440.0)) - 440.0).abs() < 1e-9);
+        assert!(freq.step(freq.max, 5) == freq.max);
+        assert!(freq.step(freq.default, 1) > freq.default);
+    }
+
+    #[test]
+    fn every_effect_is_silent_on_silence_and_stays_finite() {
+        for kind in EffectKind::ALL {
+            let mut effect = EffectSpec::new(kind).build(RATE);
+            effect.set_tempo(120.0);
+
+            let mut silence = vec![[0.0; 2]; 512];
+            effect.process(&mut silence);
+            assert!(silence.iter().all(|frame| *frame == [0.0; 2]), "{kind:?}");
+
+            let mut out = impulse(4096);
+            effect.process(&mut out);
+            assert!(out.iter().flatten().all(|sample| sample.is_finite()), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn delay_echoes_in_time_with_the_tempo() {
+        let mut spec = EffectSpec::new(EffectKind::Delay);
+        spec.set("time", 0.25);
+        spec.set("mix", 1.0);
+        spec.set("feedback", 0.0);
+
+        let mut delay = spec.build(RATE);
+        delay.set_tempo(120.0);
+
+        // A sixteenth at 120 BPM is an eighth of a second.
+        let mut out = impulse(8000);
+        delay.process(&mut out);
+
+        let echo = out.iter().position(|frame| frame[0] > 0.5);
+        assert_eq!(echo, Some(6000));
+    }
+
+    #[test]
+    fn compressor_turns_loud_signals_down_by_the_ratio() {
+        let mut spec = EffectSpec::new(EffectKind::Compressor);
+        spec.set("threshold", -20.0);
+        spec.set("ratio", 4.0);
+        spec.set("makeup", 0.0);
+
+        let mut compressor = spec.build(RATE);
+
+        // A steady 0 dB signal is 20 dB over, so comes out 15 dB down.
+        let mut out = vec![[1.0, -1.0]; 48000];
+        compressor.process(&mut out);
+
+        let level = crate::dsp::gain_to_db(out[47999][0] as f64);
+        assert!((level + 15.0).abs() < 0.1, "{level}");
+    }
+}
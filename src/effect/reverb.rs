@@ -0,0 +1,150 @@
+use crate::audio::Frame;
+use crate::effect::{Effect, Param};
+
+pub const PARAMS: [Param; 4] = [
+    Param::new("size", "", 0.0, 1.0, 0.5),
+    Param::new("damping", "", 0.0, 1.0, 0.5),
+    Param::new("width", "", 0.0, 1.0, 1.0),
+    Param::new("mix", "", 0.0, 1.0, 0.25),
+];
+
+/// Comb and allpass lengths at 44.1kHz, from Freeverb.
+const COMBS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASSES: [usize; 4] = [556, 441, 341, 225];
+/// The right side is a little longer, so that the sides differ.
+const SPREAD: usize = 23;
+
+/// Keeps the sum of eight combs in range.
+const INPUT_GAIN: f32 = 0.015;
+const WET_GAIN: f32 = 3.0;
+
+/// Feedback comb filter with a lowpass in the loop.
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    store: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self { buffer: vec![0.0; len.max(1)], index: 0, store: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.store = output * (1.0 - damping) + self.store * damping;
+        self.buffer[self.index] = input + self.store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.store = 0.0;
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Self {
+        Self { buffer: vec![0.0; len.max(1)], index: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+/// Schroeder-Moorer reverb in the manner of Freeverb: parallel combs into
+/// allpasses in series, one set for each side.
+pub struct Reverb {
+    sample_rate: f64,
+    combs: [Vec<Comb>; 2],
+    allpasses: [Vec<Allpass>; 2],
+    size: f32,
+    damping: f32,
+    width: f32,
+    mix: f32,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut reverb = Self {
+            sample_rate: 0.0,
+            combs: [Vec::new(), Vec::new()],
+            allpasses: [Vec::new(), Vec::new()],
+            size: PARAMS[0].default as f32,
+            damping: PARAMS[1].default as f32,
+            width: PARAMS[2].default as f32,
+            mix: PARAMS[3].default as f32,
+        };
+
+        reverb.set_sample_rate(sample_rate);
+        reverb
+    }
+}
+
+impl Effect for Reverb {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+
+        self.sample_rate = sample_rate;
+        let scale = |len: usize| (len as f64 * sample_rate / 44100.0) as usize;
+
+        for side in 0..2 {
+            let spread = side * SPREAD;
+            self.combs[side] = COMBS.iter().map(|len| Comb::new(scale(len + spread))).collect();
+            self.allpasses[side] = ALLPASSES.iter().map(|len| Allpass::new(scale(len + spread))).collect();
+        }
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.size = value as f32,
+            1 => self.damping = value as f32,
+            2 => self.width = value as f32,
+            3 => self.mix = value as f32,
+            _ => (),
+        }
+    }
+
+    fn process(&mut self, out: &mut [Frame]) {
+        let feedback = self.size * 0.28 + 0.7;
+        let damping = self.damping * 0.4;
+        let wet = self.mix * WET_GAIN;
+        let (same, other) = (wet * (self.width / 2.0 + 0.5), wet * (1.0 - self.width) / 2.0);
+
+        for frame in out.iter_mut() {
+            let input = (frame[0] + frame[1]) * INPUT_GAIN;
+            let wet = [0, 1].map(|side| {
+                let combs = self.combs[side].iter_mut()
+                    .map(|comb| comb.process(input, feedback, damping))
+                    .sum::<f32>();
+
+                self.allpasses[side].iter_mut()
+                    .fold(combs, |signal, allpass| allpass.process(signal))
+            });
+
+            frame[0] = frame[0] * (1.0 - self.mix) + wet[0] * same + wet[1] * other;
+            frame[1] = frame[1] * (1.0 - self.mix) + wet[1] * same + wet[0] * other;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.combs.iter_mut().flatten().for_each(Comb::reset);
+        self.allpasses.iter_mut().flatten().for_each(Allpass::reset);
+    }
+}
@@ -1,14 +1,27 @@
-use std::collections::HashMap;
 use std::fmt;
-use ratatui::crossterm::event::KeyCode;
+use std::path::PathBuf;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::layout::Direction;
 
 use crate::AppState;
+use crate::audio::export::WavFormat;
+use crate::effect::EffectKind;
+use crate::project::{
+    automation::Target,
+    quantize::{Humanize, Quantize},
+    tempo::TimeUnit,
+};
+use crate::registers::Registers;
+use crate::window::WindowKind;
 
 pub enum Mode {
     Normal,
     Insert,
     Command,
+    /// Selecting a stretch of time, over every pitch.
+    Visual,
+    /// Selecting a rectangle of pitch over time.
+    VisualBlock,
 }
 
 impl fmt::Display for Mode {
@@ -17,10 +30,13 @@ impl fmt::Display for Mode {
             Mode::Normal => write!(f, "Normal"),
             Mode::Insert => write!(f, "Insert"),
             Mode::Command => write!(f, "Command"),
+            Mode::Visual => write!(f, "Visual"),
+            Mode::VisualBlock => write!(f, "Visual Block"),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Motion {
     None,
 
@@ -29,13 +45,53 @@ pub enum Motion {
     Up,
     Down,
 
+    Beat { forward: bool },
+    Bar { forward: bool },
+
+    /// Start and end of the pattern.
+    Start,
+    End,
+    /// Highest and lowest row.
+    Top,
+    Bottom,
+
+    /// A text object, only used after an operator.
+    Inner(TextObject),
+
+    /// Whatever was selected in visual mode.
+    Selection,
+}
+
+/// Musical spans around the cursor, the way `iw` and `ip` are around a
+/// word or a paragraph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
     Beat,
     Bar,
+    Pattern,
+}
 
+impl TextObject {
+    fn from_key(key: char) -> Option<Self> {
+        match key {
+            'w' => Some(TextObject::Beat),
+            'b' | 'B' => Some(TextObject::Bar),
+            'p' => Some(TextObject::Pattern),
+            _ => None,
+        }
+    }
+}
+
+/// Where a jump to the edge of a window goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
     Start,
     End,
+    Top,
+    Bottom,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Operator {
     Delete,
     Yank,
@@ -44,6 +100,37 @@ pub enum Operator {
     Redo,
     Mute,
     Solo,
+    Transpose { semitones: i32 },
+    /// Scale velocities by a tenth for every step.
+    Velocity { steps: i32 },
+    /// Move notes along by columns of the window.
+    Nudge { steps: i32 },
+    Quantize(Quantize),
+    Humanize(Humanize),
+}
+
+impl Operator {
+    /// Operators that change notes by an amount take the count typed
+    /// before them as a multiple of it, rather than of their motion.
+    fn times(self, count: i32) -> Option<Self> {
+        match self {
+            Operator::Transpose { semitones } => Some(Operator::Transpose { semitones: semitones * count }),
+            Operator::Velocity { steps } => Some(Operator::Velocity { steps: steps * count }),
+            Operator::Nudge { steps } => Some(Operator::Nudge { steps: steps * count }),
+            _ => None,
+        }
+    }
+
+    /// The key typed after `g` for operators that start with it, which
+    /// doubles them on its own, like `g]]` for `g]g]`.
+    fn g_key(self) -> Option<char> {
+        match self {
+            Operator::Transpose { semitones: 12 } => Some(']'),
+            Operator::Transpose { semitones: -12 } => Some('['),
+            Operator::Quantize(_) => Some('q'),
+            _ => None,
+        }
+    }
 }
 
 pub enum InputAction {
@@ -56,26 +143,108 @@ pub enum InputAction {
         count: usize,
         operator: Operator,
         motion: Motion,
+        register: Option<char>,
     },
 
+    Local(LocalCommand),
+
     Command(String),
 }
 
 pub enum EditorCommand {
-    Delete { count: usize, motion: Motion },
-    Yank { count: usize, motion: Motion },
-    Paste { count: usize, motion: Motion },
-    Undo { count: usize, motion: Motion },
-    Redo { count: usize, motion: Motion },
+    Delete { count: usize, motion: Motion, register: Option<char> },
+    Yank { count: usize, motion: Motion, register: Option<char> },
+    Paste { count: usize, motion: Motion, register: Option<char> },
+    Undo { count: usize },
+    Redo { count: usize },
+    /// Mute or solo the notes a motion covers.
     Mute { count: usize, motion: Motion },
     Solo { count: usize, motion: Motion },
-    Bpm { bpm: u32 },
+    /// Mute, solo or make solo safe a track, by number counting from one
+    /// or by name, or the track of the focused window without one. Buses
+    /// can be muted too.
+    MuteTrack { track: Option<String> },
+    SoloTrack { track: Option<String>, exclusive: bool },
+    SoloSafe { track: Option<String> },
+    /// Arm or disarm a track for recording.
+    Arm { track: Option<String> },
+    AddBus { name: String },
+    RemoveBus { name: String },
+    /// Route the focused window's track or bus into a bus, by name.
+    Output { bus: String },
+    /// Send from the focused window's track or bus to a bus, taking the
+    /// send away without a level.
+    Send { bus: String, level: Option<f64>, pre_fader: bool },
+    /// Add an effect to the end of the focused window's insert chain.
+    Insert { effect: EffectKind },
+    /// Effects by number counting from one, in the focused window's chain.
+    RemoveInsert { insert: usize },
+    Bypass { insert: usize },
+    Param { insert: usize, name: String, value: f64 },
+    /// Give a parameter of the focused window's track or bus a lane, or
+    /// take it away.
+    Automate { target: Target },
+    Unautomate { target: Target },
+    Transpose { semitones: i32, count: usize, motion: Motion },
+    Velocity { steps: i32, count: usize, motion: Motion },
+    Nudge { steps: i32, count: usize, motion: Motion },
+    /// Quantize over a motion, or the selection or whole pattern without
+    /// one.
+    Quantize { settings: Quantize, count: usize, motion: Option<Motion> },
+    /// Humanize the selection or the whole pattern, with a fresh seed
+    /// unless one is given.
+    Humanize { timing: u32, velocity: u8, seed: Option<u64> },
+    /// Set the tempo from a bar on, counting from one, or for the whole
+    /// song up to the first tempo change.
+    Bpm { bpm: u32, bar: Option<u32> },
+    Meter { numerator: u8, denominator: u8, bar: Option<u32> },
+    Play,
+    Pause,
+    TogglePlayback,
+    Stop,
+    /// Loop between two bars, both included and counting from one, or
+    /// stop looping.
+    Loop { bars: Option<(u32, u32)> },
     Split { direction: Direction },
-    Quit,
+    Open { kind: WindowKind, direction: Direction },
+    Export { path: PathBuf, format: WavFormat },
+    /// Add the tracks of a Standard MIDI File to the project.
+    Import { path: PathBuf },
+    ExportMidi { path: PathBuf },
+    /// Save the project, to the file it came from without a path.
+    Write { path: Option<PathBuf>, quit: bool },
+    /// Open a project, or reload the current one without a path.
+    Edit { path: Option<PathBuf>, force: bool },
+    /// Leave, unless there are unsaved changes and it is not forced.
+    Quit { force: bool },
 }
 
 pub enum LocalCommand {
     MoveLocalCursor { dx: i32, dy: i32 },
+    /// Move along the song by beats or bars of the time signature there.
+    MoveTime { unit: TimeUnit, count: i32 },
+    MoveToEdge { edge: Edge },
+    /// Start selecting from the cursor, or change the shape of the
+    /// selection already being made.
+    Visual { block: bool },
+    EndVisual,
+    Select,
+    InsertNote,
+    DeleteNote,
+    /// Lengthen the note under the cursor by `delta` note sizes.
+    ResizeNote { delta: i32 },
+    AdjustNoteSize { delta: i32 },
+    /// Go to the next of whatever the window shows one at a time.
+    Cycle { delta: i32 },
+}
+
+/// An operator over a motion, for the focused window to carry out.
+#[derive(Clone, Copy, Debug)]
+pub struct Operation {
+    pub operator: Operator,
+    pub count: usize,
+    pub motion: Motion,
+    pub register: Option<char>,
 }
 
 pub enum ResolvedCommand {
@@ -87,8 +256,10 @@ pub struct VimInput;
 impl VimInput {
     pub fn handle_keypress(
         state: &mut AppState,
-        key: KeyCode
+        event: KeyEvent
     ) -> Option<ResolvedCommand> {
+        let key = event.code;
+
         if key == KeyCode::Esc {
             state.input_state.clear();
             state.mode = Mode::Normal;
@@ -96,7 +267,13 @@ impl VimInput {
         }
 
         let action = match state.mode {
+            Mode::Normal | Mode::Visual | Mode::VisualBlock
+                if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                handle_normal_control(state, key)
+            }
+
             Mode::Normal => handle_normal_mode(state, key),
+            Mode::Visual | Mode::VisualBlock => handle_visual_mode(state, key),
             Mode::Insert => handle_insert_mode(state, key),
             Mode::Command => handle_command_mode(state, key),
         };
@@ -105,9 +282,21 @@ impl VimInput {
     }
 }
 
+/// Counts stop growing here, which is more than any motion needs.
+const MAX_COUNT: usize = 9999;
+
 pub struct InputState {
     pub count: usize,
     pub operator: Option<Operator>,
+    /// Count typed before the operator, the one after goes to the motion.
+    pub operator_count: usize,
+    /// Register picked with `"` for the next operator.
+    pub register: Option<char>,
+    /// `"` was typed and the register name comes next.
+    pub naming_register: bool,
+    /// First key of a two key command, like the `g` of `gg` or the `i` of
+    /// a text object.
+    pub prefix: Option<char>,
 }
 
 impl InputState {
@@ -115,19 +304,34 @@ impl InputState {
         Self {
             count: 0,
             operator: None,
+            operator_count: 0,
+            register: None,
+            naming_register: false,
+            prefix: None,
         }
     }
 
-    pub fn clear(self: &mut Self) {
+    pub fn clear(&mut self) {
         self.count = 0;
         self.operator = None;
+        self.operator_count = 0;
+        self.register = None;
+        self.naming_register = false;
+        self.prefix = None;
     }
 
     pub fn display(&self) -> String {
         let mut s = String::new();
 
-        if self.count > 0 {
-            s.push_str(&self.count.to_string());
+        if let Some(register) = self.register {
+            s.push('"');
+            s.push(register);
+        } else if self.naming_register {
+            s.push('"');
+        }
+
+        if self.operator_count > 0 {
+            s.push_str(&self.operator_count.to_string());
         }
 
         if let Some(op) = &self.operator {
@@ -138,11 +342,28 @@ impl InputState {
                 Operator::Mute => "m",
                 Operator::Solo => "s",
                 Operator::Undo => "u",
-
-                _ => "?",
+                Operator::Redo => "^R",
+                Operator::Transpose { semitones: 12 } => "g]",
+                Operator::Transpose { semitones: -12 } => "g[",
+                Operator::Transpose { semitones } if *semitones > 0 => "]",
+                Operator::Transpose { .. } => "[",
+                Operator::Velocity { steps } if *steps > 0 => "*",
+                Operator::Velocity { .. } => "/",
+                Operator::Nudge { steps } if *steps > 0 => ")",
+                Operator::Nudge { .. } => "(",
+                Operator::Quantize(_) => "gq",
+                Operator::Humanize(_) => "",
             });
         }
 
+        if self.count > 0 {
+            s.push_str(&self.count.to_string());
+        }
+
+        if let Some(prefix) = self.prefix {
+            s.push(prefix);
+        }
+
         s
     }
 }
@@ -164,13 +385,77 @@ fn handle_normal_mode(
     state: &mut AppState,
     key: KeyCode
 ) -> Option<InputAction> {
+    if state.input_state.naming_register {
+        state.input_state.naming_register = false;
+
+        match key {
+            KeyCode::Char(name) if Registers::is_valid(name) => {
+                state.input_state.register = Some(name);
+            }
+
+            _ => state.input_state.clear(),
+        }
+
+        return None;
+    }
+
+    if let Some(prefix) = state.input_state.prefix.take() {
+        let motion = match (prefix, key) {
+            ('g', KeyCode::Char(']')) => {
+                return pend_operator(state, Operator::Transpose { semitones: 12 });
+            }
+
+            ('g', KeyCode::Char('[')) => {
+                return pend_operator(state, Operator::Transpose { semitones: -12 });
+            }
+
+            ('g', KeyCode::Char('q')) => {
+                return pend_operator(state, Operator::Quantize(Quantize::default()));
+            }
+
+            ('g', KeyCode::Char('S')) => {
+                state.input_state.clear();
+                return Some(InputAction::Command("solo!".to_string()));
+            }
+
+            ('g', KeyCode::Char('g')) => Some(Motion::Top),
+            ('i', KeyCode::Char(c)) => TextObject::from_key(c).map(Motion::Inner),
+            _ => None,
+        };
+
+        return match motion {
+            Some(motion) => emit_action(&mut state.input_state, motion),
+            None => {
+                state.input_state.clear();
+                None
+            }
+        };
+    }
+
     match key {
+        KeyCode::Char(c) if state.input_state.operator.and_then(Operator::g_key) == Some(c) => {
+            emit_action(&mut state.input_state, Motion::None)
+        }
+
+        // Text objects only follow an operator, on its own it inserts.
+        KeyCode::Char('i') if state.input_state.operator.is_some() => {
+            state.input_state.prefix = Some('i');
+            None
+        }
+
         KeyCode::Char('i') => {
             state.input_state.clear();
             state.mode = Mode::Insert;
             None
         },
 
+        KeyCode::Char('g') => {
+            state.input_state.prefix = Some('g');
+            None
+        }
+
+        KeyCode::Char('v') => visual(state, false),
+
         KeyCode::Char(':') | KeyCode::Char(';') => {
             state.input_state.clear();
             state.command_state.clear();
@@ -178,48 +463,236 @@ fn handle_normal_mode(
             None
         },
 
+        // A count can't start with zero, which goes to the start instead.
+        KeyCode::Char('0') if state.input_state.count == 0 => {
+            emit_action(&mut state.input_state, Motion::Start)
+        }
+
         KeyCode::Char(c) if c.is_ascii_digit() => {
             let d = c.to_digit(10).unwrap() as usize;
-            state.input_state.count = state.input_state.count * 10 + d;
+            state.input_state.count = (state.input_state.count * 10 + d).min(MAX_COUNT);
             None
         }
 
-        KeyCode::Char('d') => {
-            state.input_state.operator = Some(Operator::Delete);
-            None
+        KeyCode::Char('d') => pend_operator(state, Operator::Delete),
+        KeyCode::Char('y') => pend_operator(state, Operator::Yank),
+
+        KeyCode::Char(']') => pend_operator(state, Operator::Transpose { semitones: 1 }),
+        KeyCode::Char('[') => pend_operator(state, Operator::Transpose { semitones: -1 }),
+        KeyCode::Char('*') => pend_operator(state, Operator::Velocity { steps: 1 }),
+        KeyCode::Char('/') => pend_operator(state, Operator::Velocity { steps: -1 }),
+        KeyCode::Char(')') => pend_operator(state, Operator::Nudge { steps: 1 }),
+        KeyCode::Char('(') => pend_operator(state, Operator::Nudge { steps: -1 }),
+
+        KeyCode::Char('u') => {
+            state.input_state.operator = Some(Operator::Undo);
+            emit_action(&mut state.input_state, Motion::None)
         }
 
-        KeyCode::Char('y') => {
-            state.input_state.operator = Some(Operator::Yank);
+        KeyCode::Char('"') => {
+            state.input_state.naming_register = true;
             None
         }
 
-        KeyCode::Char('u') => {
-            state.input_state.operator = Some(Operator::Undo);
+        KeyCode::Char('p') => {
+            state.input_state.operator = Some(Operator::Paste);
             emit_action(&mut state.input_state, Motion::None)
         }
 
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            emit_local(&mut state.input_state, |_| LocalCommand::Select)
+        }
+
+        KeyCode::Char('x') => {
+            emit_local(&mut state.input_state, |_| LocalCommand::DeleteNote)
+        }
+
+        KeyCode::Char('>') => {
+            emit_local(&mut state.input_state, |count| LocalCommand::ResizeNote { delta: count })
+        }
+
+        KeyCode::Char('<') => {
+            emit_local(&mut state.input_state, |count| LocalCommand::ResizeNote { delta: -count })
+        }
+
+        KeyCode::Char('+') | KeyCode::Char('=') => {
+            emit_local(&mut state.input_state, |count| LocalCommand::AdjustNoteSize { delta: count })
+        }
+
+        KeyCode::Char('-') => {
+            emit_local(&mut state.input_state, |count| LocalCommand::AdjustNoteSize { delta: -count })
+        }
+
+        KeyCode::Tab => {
+            emit_local(&mut state.input_state, |count| LocalCommand::Cycle { delta: count })
+        }
+
+        KeyCode::BackTab => {
+            emit_local(&mut state.input_state, |count| LocalCommand::Cycle { delta: -count })
+        }
+
         KeyCode::Char('h') => emit_action(&mut state.input_state, Motion::Left),
         KeyCode::Char('j') => emit_action(&mut state.input_state, Motion::Down),
         KeyCode::Char('k') => emit_action(&mut state.input_state, Motion::Up),
         KeyCode::Char('l') => emit_action(&mut state.input_state, Motion::Right),
 
+        KeyCode::Char('w') => emit_action(&mut state.input_state, Motion::Beat { forward: true }),
+        KeyCode::Char('b') => emit_action(&mut state.input_state, Motion::Beat { forward: false }),
+        KeyCode::Char('W') => emit_action(&mut state.input_state, Motion::Bar { forward: true }),
+        KeyCode::Char('B') => emit_action(&mut state.input_state, Motion::Bar { forward: false }),
+        KeyCode::Char('}') => emit_action(&mut state.input_state, Motion::Bar { forward: true }),
+        KeyCode::Char('{') => emit_action(&mut state.input_state, Motion::Bar { forward: false }),
+
+        KeyCode::Char('$') => emit_action(&mut state.input_state, Motion::End),
+        KeyCode::Char('G') => emit_action(&mut state.input_state, Motion::Bottom),
+
+        KeyCode::Char('m') => pend_operator(state, Operator::Mute),
+        KeyCode::Char('s') => pend_operator(state, Operator::Solo),
+
+        KeyCode::Char('M') => {
+            state.input_state.clear();
+            Some(InputAction::Command("mute".to_string()))
+        }
+
+        KeyCode::Char('S') => {
+            state.input_state.clear();
+            Some(InputAction::Command("solo".to_string()))
+        }
+
+        KeyCode::Char('t') => {
+            state.input_state.clear();
+            Some(InputAction::Command("toggle-playback".to_string()))
+        }
+
+        KeyCode::Char('T') => {
+            state.input_state.clear();
+            Some(InputAction::Command("stop".to_string()))
+        }
+
+        _ => None,
+    }
+}
+
+/// Keys held together with Ctrl.
+fn handle_normal_control(
+    state: &mut AppState,
+    key: KeyCode
+) -> Option<InputAction> {
+    match key {
+        KeyCode::Char('r') => {
+            state.input_state.operator = Some(Operator::Redo);
+            emit_action(&mut state.input_state, Motion::None)
+        }
+
+        KeyCode::Char('v') => visual(state, true),
+
+        _ => None,
+    }
+}
+
+fn handle_visual_mode(
+    state: &mut AppState,
+    key: KeyCode
+) -> Option<InputAction> {
+    let input = &state.input_state;
+    if input.naming_register || input.prefix.is_some() {
+        return handle_normal_mode(state, key);
+    }
+
+    match key {
+        KeyCode::Char('v') => visual(state, false),
+
+        KeyCode::Char('x') => pend_operator(state, Operator::Delete),
+
+        // Commands work on the selection, which stays until they are done.
+        KeyCode::Char(':') => {
+            state.input_state.clear();
+            state.command_state.clear();
+            state.mode = Mode::Command;
+            None
+        }
+
+        // Operators go straight to the selection, everything else works
+        // as it does in normal mode.
+        KeyCode::Char(
+            '"' | 'g' | '0'..='9' | 'h' | 'j' | 'k' | 'l' | 'w' | 'b' | 'W' | 'B' | '{' | '}' | '$' | 'G'
+                | 'd' | 'y' | ']' | '[' | '*' | '/' | ')' | '(' | 'm' | 's'
+        ) => handle_normal_mode(state, key),
+
         _ => None,
     }
 }
 
+/// Start selecting, switch between the two kinds of selection, or stop
+/// when the key of the current kind is pressed again.
+fn visual(state: &mut AppState, block: bool) -> Option<InputAction> {
+    state.input_state.clear();
+
+    let current = match state.mode {
+        Mode::Visual => Some(false),
+        Mode::VisualBlock => Some(true),
+        _ => None,
+    };
+
+    if current == Some(block) {
+        state.mode = Mode::Normal;
+        return None;
+    }
+
+    state.mode = if block { Mode::VisualBlock } else { Mode::Visual };
+    Some(InputAction::Local(LocalCommand::Visual { block }))
+}
+
+/// Wait for the motion of an operator, or work on the note under the
+/// cursor when it is doubled, like `dd`. In visual mode the selection is
+/// what it works on.
+fn pend_operator(
+    state: &mut AppState,
+    operator: Operator,
+) -> Option<InputAction> {
+    let input = &mut state.input_state;
+
+    if matches!(state.mode, Mode::Visual | Mode::VisualBlock) {
+        state.mode = Mode::Normal;
+        input.operator = Some(operator);
+        input.operator_count = input.count;
+        input.count = 0;
+
+        return emit_action(input, Motion::Selection);
+    }
+
+    if input.operator == Some(operator) {
+        return emit_action(input, Motion::None);
+    }
+
+    input.operator = Some(operator);
+    input.operator_count = input.count;
+    input.count = 0;
+    None
+}
+
 fn emit_action(
     state: &mut InputState,
     motion: Motion
 ) -> Option<InputAction> {
     let count = if state.count == 0 { 1 } else { state.count };
+    let operator_count = state.operator_count.max(1);
 
     let action = match state.operator.take() {
-        Some(op) => InputAction::Operation {
-            count,
-            operator: op,
-            motion,
-        },
+        Some(op) => {
+            // Otherwise both counts multiply, so 2d3l deletes six columns.
+            let (operator, count) = match op.times(operator_count as i32) {
+                Some(operator) => (operator, count),
+                None => (op, (operator_count * count).min(MAX_COUNT)),
+            };
+
+            InputAction::Operation {
+                count,
+                operator,
+                motion,
+                register: state.register,
+            }
+        }
 
         None => InputAction::Move {count, motion},
     };
@@ -228,11 +701,37 @@ fn emit_action(
     Some(action)
 }
 
+fn emit_local<F>(
+    state: &mut InputState,
+    command: F,
+) -> Option<InputAction>
+where F: FnOnce(i32) -> LocalCommand {
+    let count = if state.count == 0 { 1 } else { state.count };
+
+    state.clear();
+    Some(InputAction::Local(command(count as i32)))
+}
+
 fn handle_insert_mode(
     state: &mut AppState,
     key: KeyCode
 ) -> Option<InputAction> {
-    return None;
+    match key {
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            emit_local(&mut state.input_state, |_| LocalCommand::InsertNote)
+        }
+
+        KeyCode::Backspace | KeyCode::Delete => {
+            emit_local(&mut state.input_state, |_| LocalCommand::DeleteNote)
+        }
+
+        KeyCode::Char('h') | KeyCode::Left => emit_action(&mut state.input_state, Motion::Left),
+        KeyCode::Char('j') | KeyCode::Down => emit_action(&mut state.input_state, Motion::Down),
+        KeyCode::Char('k') | KeyCode::Up => emit_action(&mut state.input_state, Motion::Up),
+        KeyCode::Char('l') | KeyCode::Right => emit_action(&mut state.input_state, Motion::Right),
+
+        _ => None,
+    }
 }
 
 fn resolve_action(
@@ -247,7 +746,10 @@ fn resolve_action(
             count,
             operator,
             motion,
-        }) => resolve_operation(count, operator, motion),
+            register,
+        }) => resolve_operation(count, operator, motion, register),
+
+        Some(InputAction::Local(cmd)) => Some(ResolvedCommand::Local(cmd)),
 
         Some(InputAction::Command(cmd)) => resolve_command(cmd),
 
@@ -267,6 +769,28 @@ fn resolve_move(
         Motion::Down  => dy = -(count as i32),
         Motion::Left  => dx = -(count as i32),
         Motion::Right => dx = count as i32,
+
+        Motion::Beat { forward } | Motion::Bar { forward } => {
+            let unit = match motion {
+                Motion::Bar { .. } => TimeUnit::Bar,
+                _ => TimeUnit::Beat,
+            };
+            let count = if forward { count as i32 } else { -(count as i32) };
+
+            return Some(ResolvedCommand::Local(LocalCommand::MoveTime { unit, count }));
+        }
+
+        Motion::Start | Motion::End | Motion::Top | Motion::Bottom => {
+            let edge = match motion {
+                Motion::Start => Edge::Start,
+                Motion::End => Edge::End,
+                Motion::Top => Edge::Top,
+                _ => Edge::Bottom,
+            };
+
+            return Some(ResolvedCommand::Local(LocalCommand::MoveToEdge { edge }));
+        }
+
         _ => return None,
     }
 
@@ -277,20 +801,53 @@ fn resolve_operation(
     count: usize,
     operator: Operator,
     motion: Motion,
+    register: Option<char>,
 ) -> Option<ResolvedCommand> {
     match operator {
         Operator::Delete => Some(ResolvedCommand::Editor(
-            EditorCommand::Delete { count, motion }
+            EditorCommand::Delete { count, motion, register }
         )),
 
         Operator::Yank => Some(ResolvedCommand::Editor(
-            EditorCommand::Yank { count, motion }
+            EditorCommand::Yank { count, motion, register }
+        )),
+
+        Operator::Paste => Some(ResolvedCommand::Editor(
+            EditorCommand::Paste { count, motion, register }
         )),
 
         Operator::Mute => Some(ResolvedCommand::Editor(
             EditorCommand::Mute { count, motion }
         )),
 
+        Operator::Solo => Some(ResolvedCommand::Editor(
+            EditorCommand::Solo { count, motion }
+        )),
+
+        Operator::Transpose { semitones } => Some(ResolvedCommand::Editor(
+            EditorCommand::Transpose { semitones, count, motion }
+        )),
+
+        Operator::Velocity { steps } => Some(ResolvedCommand::Editor(
+            EditorCommand::Velocity { steps, count, motion }
+        )),
+
+        Operator::Nudge { steps } => Some(ResolvedCommand::Editor(
+            EditorCommand::Nudge { steps, count, motion }
+        )),
+
+        Operator::Quantize(settings) => Some(ResolvedCommand::Editor(
+            EditorCommand::Quantize { settings, count, motion: Some(motion) }
+        )),
+
+        Operator::Undo => Some(ResolvedCommand::Editor(
+            EditorCommand::Undo { count }
+        )),
+
+        Operator::Redo => Some(ResolvedCommand::Editor(
+            EditorCommand::Redo { count }
+        )),
+
         _ => None,
     }
 }
@@ -298,8 +855,23 @@ fn resolve_operation(
 fn resolve_command(
     command: String,
 ) -> Option<ResolvedCommand> {
-    match command.as_str() {
-        "q" | "quit" => Some(ResolvedCommand::Editor(EditorCommand::Quit)),
+    let mut args = command.split_whitespace();
+    let name = args.next()?;
+
+    match name {
+        "q" | "quit" | "q!" | "quit!" => Some(ResolvedCommand::Editor(EditorCommand::Quit {
+            force: name.ends_with('!'),
+        })),
+
+        "w" | "write" | "wq" | "x" => Some(ResolvedCommand::Editor(EditorCommand::Write {
+            path: args.next().map(PathBuf::from),
+            quit: matches!(name, "wq" | "x"),
+        })),
+
+        "e" | "edit" | "e!" | "edit!" => Some(ResolvedCommand::Editor(EditorCommand::Edit {
+            path: args.next().map(PathBuf::from),
+            force: name.ends_with('!'),
+        })),
 
         // We want to split accross the opposite direction since
         // splitting adds another window on the 'direction' axis.
@@ -311,10 +883,191 @@ fn resolve_command(
             EditorCommand::Split { direction: Direction::Vertical }
         )),
 
+        "play" => Some(ResolvedCommand::Editor(EditorCommand::Play)),
+        "pause" => Some(ResolvedCommand::Editor(EditorCommand::Pause)),
+        "stop" => Some(ResolvedCommand::Editor(EditorCommand::Stop)),
+        "toggle-playback" => Some(ResolvedCommand::Editor(EditorCommand::TogglePlayback)),
+
+        // :loop <first bar> [last bar], or :loop on its own to stop looping.
+        "loop" => {
+            let bars = match args.next() {
+                Some(first) => {
+                    let first: u32 = first.parse().ok().filter(|bar| *bar > 0)?;
+                    let last = match args.next() {
+                        Some(last) => last.parse().ok()?,
+                        None => first,
+                    };
+
+                    Some((first, last))
+                }
+
+                None => None,
+            };
+
+            Some(ResolvedCommand::Editor(EditorCommand::Loop { bars }))
+        }
+
+        // :bpm <tempo> [bar]
+        "bpm" => {
+            let bpm = args.next()?.parse().ok()?;
+            let bar = parse_bar(args.next())?;
+
+            Some(ResolvedCommand::Editor(EditorCommand::Bpm { bpm, bar }))
+        }
+
+        // :meter <numerator>/<denominator> [bar]
+        "meter" | "timesig" => {
+            let (numerator, denominator) = args.next()?.split_once('/')?;
+            let numerator = numerator.parse().ok().filter(|n| (1..=32).contains(n))?;
+            let denominator = denominator.parse().ok()
+                .filter(|d: &u8| d.is_power_of_two() && *d <= 64)?;
+            let bar = parse_bar(args.next())?;
+
+            Some(ResolvedCommand::Editor(EditorCommand::Meter { numerator, denominator, bar }))
+        }
+
+        "mute" => Some(ResolvedCommand::Editor(EditorCommand::MuteTrack {
+            track: track_arg(args),
+        })),
+
+        // The bang solos the track alone.
+        "solo" | "solo!" => Some(ResolvedCommand::Editor(EditorCommand::SoloTrack {
+            track: track_arg(args),
+            exclusive: name.ends_with('!'),
+        })),
+
+        "solosafe" => Some(ResolvedCommand::Editor(EditorCommand::SoloSafe {
+            track: track_arg(args),
+        })),
+
+        "arm" => Some(ResolvedCommand::Editor(EditorCommand::Arm {
+            track: track_arg(args),
+        })),
+
+        "bus" => Some(ResolvedCommand::Editor(EditorCommand::AddBus {
+            name: track_arg(args)?,
+        })),
+
+        "delbus" => Some(ResolvedCommand::Editor(EditorCommand::RemoveBus {
+            name: track_arg(args)?,
+        })),
+
+        "output" => Some(ResolvedCommand::Editor(EditorCommand::Output {
+            bus: track_arg(args)?,
+        })),
+
+        "insert" => Some(ResolvedCommand::Editor(EditorCommand::Insert {
+            effect: EffectKind::from_name(args.next()?)?,
+        })),
+
+        "delinsert" => Some(ResolvedCommand::Editor(EditorCommand::RemoveInsert {
+            insert: args.next()?.parse().ok()?,
+        })),
+
+        "bypass" => Some(ResolvedCommand::Editor(EditorCommand::Bypass {
+            insert: args.next()?.parse().ok()?,
+        })),
+
+        // :param <insert> <name> <value>
+        "param" => Some(ResolvedCommand::Editor(EditorCommand::Param {
+            insert: args.next()?.parse().ok()?,
+            name: args.next()?.to_string(),
+            value: args.next()?.parse().ok()?,
+        })),
+
+        // :automate gain|pan|<instrument param>|<insert> <param>
+        "automate" => Some(ResolvedCommand::Editor(EditorCommand::Automate {
+            target: Target::parse(&args.collect::<Vec<_>>())?,
+        })),
+
+        "unautomate" => Some(ResolvedCommand::Editor(EditorCommand::Unautomate {
+            target: Target::parse(&args.collect::<Vec<_>>())?,
+        })),
+
+        // :send[!] <bus> [level|off], where the bang sends before the fader.
+        "send" | "send!" => {
+            let bus = args.next()?.to_string();
+            let level = match args.next() {
+                Some("off") => None,
+                Some(level) => Some(level.parse().ok()?),
+                None => Some(0.0),
+            };
+
+            Some(ResolvedCommand::Editor(EditorCommand::Send {
+                bus,
+                level,
+                pre_fader: name.ends_with('!'),
+            }))
+        }
+
+        // :quantize[!] [grid] [strength] [swing], where the bang snaps note
+        // ends too.
+        "quantize" | "quantize!" => {
+            let mut settings = Quantize { ends: name.ends_with('!'), ..Quantize::default() };
+
+            if let Some(grid) = args.next() {
+                settings.grid = Some(Quantize::parse_grid(grid)?);
+            }
+            if let Some(strength) = args.next() {
+                settings.strength = strength.parse().ok().filter(|strength| *strength <= 100)?;
+            }
+            if let Some(swing) = args.next() {
+                settings.swing = swing.parse().ok().filter(|swing| *swing <= 100)?;
+            }
+
+            Some(ResolvedCommand::Editor(EditorCommand::Quantize { settings, count: 1, motion: None }))
+        }
+
+        // :humanize [timing ticks] [velocity] [seed]
+        "humanize" => {
+            let timing = args.next().map_or(Some(4), |timing| timing.parse().ok())?;
+            let velocity = args.next().map_or(Some(8), |velocity| velocity.parse().ok())?;
+            let seed = match args.next() {
+                Some(seed) => Some(seed.parse().ok()?),
+                None => None,
+            };
+
+            Some(ResolvedCommand::Editor(EditorCommand::Humanize { timing, velocity, seed }))
+        }
+
+        // :export <path> [16|24|32f]
+        "export" => {
+            let path = PathBuf::from(args.next()?);
+            let format = match args.next() {
+                Some(format) => format.parse().ok()?,
+                None => WavFormat::Int16,
+            };
+
+            Some(ResolvedCommand::Editor(EditorCommand::Export { path, format }))
+        }
+
+        "import" => Some(ResolvedCommand::Editor(EditorCommand::Import {
+            path: PathBuf::from(args.next()?),
+        })),
+
+        "export-midi" => Some(ResolvedCommand::Editor(EditorCommand::ExportMidi {
+            path: PathBuf::from(args.next()?),
+        })),
+
         _ => None,
     }
 }
 
+/// Track names can have spaces, so the rest of the command is the name.
+fn track_arg<'a>(args: impl Iterator<Item = &'a str>) -> Option<String> {
+    let name = args.collect::<Vec<_>>().join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
+/// An optional bar number counting from one, `None` inside when absent
+/// and `None` outside when it does not parse.
+fn parse_bar(arg: Option<&str>) -> Option<Option<u32>> {
+    match arg {
+        Some(bar) => Some(Some(bar.parse().ok().filter(|bar| *bar > 0)?)),
+        None => Some(None),
+    }
+}
+
 fn handle_command_mode(
     state: &mut AppState,
     key: KeyCode
@@ -345,7 +1098,7 @@ fn handle_command_mode(
         }
 
         KeyCode::Backspace => {
-            if command.buffer.len() > 0 {
+            if !command.buffer.is_empty() {
                 if command.cursor > 0 {
                     command.cursor -= 1;
                     command.buffer.remove(command.cursor);
@@ -372,3 +1125,116 @@ fn handle_command_mode(
         _ => None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::args::Args;
+    use crate::audio::backend::BackendKind;
+
+    use super::*;
+
+    fn state() -> AppState {
+        AppState::new(&Args { audio: BackendKind::Null }).unwrap()
+    }
+
+    /// Type the keys one by one, returning what the last one resolved to.
+    fn keys(state: &mut AppState, keys: &str) -> Option<ResolvedCommand> {
+        let mut resolved = None;
+
+        for key in keys.chars() {
+            resolved = VimInput::handle_keypress(state, KeyEvent::from(KeyCode::Char(key)));
+        }
+
+        resolved
+    }
+
+    fn editor(state: &mut AppState, typed: &str) -> EditorCommand {
+        match keys(state, typed) {
+            Some(ResolvedCommand::Editor(command)) => command,
+            _ => panic!("'{typed}' is not an editor command"),
+        }
+    }
+
+    #[test]
+    fn doubled_operators_work_on_the_note_under_the_cursor() {
+        let mut state = state();
+
+        assert!(matches!(editor(&mut state, "dd"), EditorCommand::Delete {
+            count: 1, motion: Motion::None, register: None,
+        }));
+        assert!(matches!(editor(&mut state, "3yy"), EditorCommand::Yank {
+            count: 3, motion: Motion::None, register: None,
+        }));
+    }
+
+    #[test]
+    fn counts_registers_and_motions() {
+        let mut state = state();
+
+        assert!(matches!(editor(&mut state, "y2B"), EditorCommand::Yank {
+            count: 2, motion: Motion::Bar { forward: false }, register: None,
+        }));
+        assert!(matches!(editor(&mut state, "\"a2d3l"), EditorCommand::Delete {
+            count: 6, motion: Motion::Right, register: Some('a'),
+        }));
+        assert!(matches!(editor(&mut state, "\"B3p"), EditorCommand::Paste {
+            count: 3, register: Some('B'), ..
+        }));
+
+        // Amounts take the count before the operator, motions the one after.
+        assert!(matches!(editor(&mut state, "3]2w"), EditorCommand::Transpose {
+            semitones: 3, count: 2, motion: Motion::Beat { forward: true },
+        }));
+        assert!(matches!(editor(&mut state, "2g[["), EditorCommand::Transpose {
+            semitones: -24, count: 1, motion: Motion::None,
+        }));
+        assert!(matches!(editor(&mut state, "g]g]"), EditorCommand::Transpose {
+            semitones: 12, count: 1, motion: Motion::None,
+        }));
+        assert!(matches!(editor(&mut state, "gqq"), EditorCommand::Quantize {
+            motion: Some(Motion::None), ..
+        }));
+    }
+
+    #[test]
+    fn edges_move_or_take_a_range() {
+        let mut state = state();
+
+        assert!(matches!(keys(&mut state, "gg"), Some(ResolvedCommand::Local(
+            LocalCommand::MoveToEdge { edge: Edge::Top }
+        ))));
+        assert!(matches!(keys(&mut state, "G"), Some(ResolvedCommand::Local(
+            LocalCommand::MoveToEdge { edge: Edge::Bottom }
+        ))));
+        assert!(matches!(keys(&mut state, "0"), Some(ResolvedCommand::Local(
+            LocalCommand::MoveToEdge { edge: Edge::Start }
+        ))));
+
+        assert!(matches!(editor(&mut state, "dgg"), EditorCommand::Delete { motion: Motion::Top, .. }));
+        assert!(matches!(editor(&mut state, "yG"), EditorCommand::Yank { motion: Motion::Bottom, .. }));
+        assert!(matches!(editor(&mut state, "d$"), EditorCommand::Delete { motion: Motion::End, .. }));
+    }
+
+    #[test]
+    fn text_objects_follow_operators() {
+        let mut state = state();
+
+        assert!(matches!(editor(&mut state, "dib"), EditorCommand::Delete {
+            motion: Motion::Inner(TextObject::Bar), ..
+        }));
+        assert!(matches!(editor(&mut state, "y2iw"), EditorCommand::Yank {
+            count: 2, motion: Motion::Inner(TextObject::Beat), ..
+        }));
+        assert!(matches!(editor(&mut state, "mip"), EditorCommand::Mute {
+            motion: Motion::Inner(TextObject::Pattern), ..
+        }));
+
+        // An unknown object drops the whole command.
+        assert!(keys(&mut state, "diz").is_none());
+        assert_eq!(state.input_state.display(), "");
+
+        // Without an operator `i` inserts.
+        assert!(keys(&mut state, "i").is_none());
+        assert!(matches!(state.mode, Mode::Insert));
+    }
+}
@@ -0,0 +1,251 @@
+use std::f64::consts::TAU;
+
+use crate::audio::Frame;
+use crate::effect::Param;
+use crate::instrument::Instrument;
+use crate::dsp::{
+    filter::{FilterMode, Svf},
+    random::Rng,
+};
+
+pub const PARAMS: [Param; 1] = [Param::new("gain", "", 0.0, 1.0, 0.5)];
+
+/// The pieces of the built-in kit, each answering to its General MIDI
+/// drum pitch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrumSound {
+    Kick,
+    Snare,
+    Clap,
+    ClosedHat,
+    OpenHat,
+}
+
+impl DrumSound {
+    pub const ALL: [DrumSound; 5] = [
+        DrumSound::Kick,
+        DrumSound::Snare,
+        DrumSound::Clap,
+        DrumSound::ClosedHat,
+        DrumSound::OpenHat,
+    ];
+
+    pub fn pitch(self) -> u8 {
+        match self {
+            DrumSound::Kick => 36,
+            DrumSound::Snare => 38,
+            DrumSound::Clap => 39,
+            DrumSound::ClosedHat => 42,
+            DrumSound::OpenHat => 46,
+        }
+    }
+
+    pub fn from_pitch(pitch: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|sound| sound.pitch() == pitch)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DrumSound::Kick => "Kick",
+            DrumSound::Snare => "Snare",
+            DrumSound::Clap => "Clap",
+            DrumSound::ClosedHat => "Closed Hat",
+            DrumSound::OpenHat => "Open Hat",
+        }
+    }
+
+    /// Seconds until the hit has died away.
+    fn duration(self) -> f64 {
+        match self {
+            DrumSound::Kick => 1.5,
+            DrumSound::Snare => 0.6,
+            DrumSound::Clap => 0.7,
+            DrumSound::ClosedHat => 0.2,
+            DrumSound::OpenHat => 1.2,
+        }
+    }
+}
+
+/// Time between the hand claps that make up a clap.
+const CLAP_SPACING: f64 = 0.01;
+const CLAP_COUNT: f64 = 3.0;
+
+/// How quickly a choked hit fades out, in seconds.
+const CHOKE_TIME: f64 = 0.005;
+
+struct DrumVoice {
+    sound: DrumSound,
+    velocity: f64,
+    /// Frames since the hit, `None` once it has finished.
+    age: Option<u64>,
+    phase: f64,
+    fade: f64,
+    choked: bool,
+    filter: Svf,
+}
+
+impl DrumVoice {
+    fn new(sound: DrumSound, sample_rate: f64) -> Self {
+        let mode = match sound {
+            DrumSound::Kick => FilterMode::Lowpass,
+            DrumSound::Clap => FilterMode::Bandpass,
+            DrumSound::Snare | DrumSound::ClosedHat | DrumSound::OpenHat => FilterMode::Highpass,
+        };
+
+        let mut voice = Self {
+            sound,
+            velocity: 0.0,
+            age: None,
+            phase: 0.0,
+            fade: 1.0,
+            choked: false,
+            filter: Svf::new(mode, sample_rate),
+        };
+
+        voice.set_sample_rate(sample_rate);
+        voice
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        let (cutoff, resonance) = match self.sound {
+            DrumSound::Kick => (8000.0, 0.0),
+            DrumSound::Snare => (1500.0, 0.1),
+            DrumSound::Clap => (1200.0, 0.4),
+            DrumSound::ClosedHat | DrumSound::OpenHat => (7000.0, 0.2),
+        };
+
+        self.filter.set_sample_rate(sample_rate);
+        self.filter.set(cutoff, resonance);
+    }
+
+    fn trigger(&mut self, velocity: u8) {
+        self.velocity = velocity as f64 / 127.0;
+        self.age = Some(0);
+        self.phase = 0.0;
+        self.fade = 1.0;
+        self.choked = false;
+        self.filter.reset();
+    }
+
+    fn choke(&mut self) {
+        self.choked = self.age.is_some();
+    }
+
+    fn next(&mut self, noise: f64, sample_rate: f64) -> f64 {
+        let Some(age) = self.age else { return 0.0 };
+        let t = age as f64 / sample_rate;
+
+        let out = match self.sound {
+            // A sine sweeping down from a click into the body.
+            DrumSound::Kick => {
+                let freq = 45.0 + 120.0 * (-t * 35.0).exp();
+                self.phase = (self.phase + freq / sample_rate).fract();
+
+                let body = (self.phase * TAU).sin();
+                self.filter.process(body) * (-t / 0.35).exp()
+            }
+
+            DrumSound::Snare => {
+                self.phase = (self.phase + 185.0 / sample_rate).fract();
+
+                let tone = (self.phase * TAU).sin() * (-t / 0.08).exp();
+                let rattle = self.filter.process(noise) * (-t / 0.15).exp();
+                0.5 * tone + 0.7 * rattle
+            }
+
+            DrumSound::Clap => {
+                let env = if t < CLAP_SPACING * CLAP_COUNT {
+                    (-(t % CLAP_SPACING) / 0.003).exp()
+                } else {
+                    (-(t - CLAP_SPACING * CLAP_COUNT) / 0.15).exp()
+                };
+
+                2.0 * self.filter.process(noise) * env
+            }
+
+            DrumSound::ClosedHat => self.filter.process(noise) * (-t / 0.04).exp(),
+            DrumSound::OpenHat => self.filter.process(noise) * (-t / 0.3).exp(),
+        };
+
+        if self.choked {
+            self.fade *= (-1.0 / (CHOKE_TIME * sample_rate)).exp();
+        }
+
+        let finished = t >= self.sound.duration() || self.fade < 1.0e-4;
+        self.age = if finished { None } else { Some(age + 1) };
+
+        out * self.velocity * self.fade
+    }
+}
+
+/// A synthesized drum kit with one voice per piece. Hits are one-shots, so
+/// note-offs are ignored, and a closed hat chokes the open one.
+pub struct DrumKit {
+    voices: Vec<DrumVoice>,
+    noise: Rng,
+    sample_rate: f64,
+    gain: f64,
+}
+
+impl DrumKit {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            voices: DrumSound::ALL.into_iter()
+                .map(|sound| DrumVoice::new(sound, sample_rate))
+                .collect(),
+            noise: Rng::new(0),
+            sample_rate,
+            gain: PARAMS[0].default,
+        }
+    }
+
+    pub fn set_gain(&mut self, gain: f64) {
+        self.gain = gain;
+    }
+}
+
+impl Instrument for DrumKit {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        for voice in self.voices.iter_mut() {
+            voice.set_sample_rate(sample_rate);
+        }
+    }
+
+    fn note_on(&mut self, pitch: u8, velocity: u8) {
+        let Some(sound) = DrumSound::from_pitch(pitch) else { return };
+
+        if sound == DrumSound::ClosedHat {
+            for voice in self.voices.iter_mut().filter(|voice| voice.sound == DrumSound::OpenHat) {
+                voice.choke();
+            }
+        }
+
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.sound == sound) {
+            voice.trigger(velocity);
+        }
+    }
+
+    fn note_off(&mut self, _pitch: u8) {}
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        if index == 0 {
+            self.set_gain(value);
+        }
+    }
+
+    fn render(&mut self, out: &mut [Frame]) {
+        for frame in out.iter_mut() {
+            let noise = self.noise.next_bipolar();
+
+            let sample: f64 = self.voices.iter_mut()
+                .map(|voice| voice.next(noise, self.sample_rate))
+                .sum();
+
+            let sample = (sample * self.gain) as f32;
+            frame[0] += sample;
+            frame[1] += sample;
+        }
+    }
+}
@@ -0,0 +1,18 @@
+pub mod drums;
+pub mod sampler;
+pub mod synth;
+
+use crate::audio::Frame;
+
+/// Something that turns notes into sound. Instruments are driven from the
+/// audio thread, so none of these may block or allocate.
+pub trait Instrument: Send {
+    fn set_sample_rate(&mut self, sample_rate: f64);
+    fn note_on(&mut self, pitch: u8, velocity: u8);
+    fn note_off(&mut self, pitch: u8);
+    /// Set a parameter by its place in `InstrumentSpec::params`, within
+    /// range.
+    fn set_param(&mut self, _index: usize, _value: f64) {}
+    /// Mix the next `out.len()` frames into `out`.
+    fn render(&mut self, out: &mut [Frame]);
+}
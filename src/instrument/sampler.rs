@@ -0,0 +1,326 @@
+use std::{path::Path, sync::Arc};
+
+use color_eyre::eyre::{eyre, Result};
+use hound::{SampleFormat, WavReader};
+
+use crate::audio::Frame;
+use crate::effect::Param;
+use crate::instrument::Instrument;
+use crate::dsp::envelope::{Adsr, AdsrParams};
+
+pub const PARAMS: [Param; 1] = [Param::new("gain", "", 0.0, 2.0, 1.0)];
+
+/// Decoded audio, always stored as stereo frames.
+pub struct SampleData {
+    frames: Vec<Frame>,
+    sample_rate: u32,
+}
+
+impl SampleData {
+    pub fn new(frames: Vec<Frame>, sample_rate: u32) -> Self {
+        Self { frames, sample_rate }
+    }
+
+    /// Load 8, 16, 24 or 32-bit integer or 32-bit float WAV files. Mono
+    /// files are spread to both sides and extra channels are dropped.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut reader = WavReader::open(path)
+            .map_err(|err| eyre!("could not open '{}': {err}", path.display()))?;
+
+        let spec = reader.spec();
+        let channels = spec.channels as usize;
+
+        let samples: Vec<f32> = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Float, 32) => reader.samples::<f32>()
+                .collect::<Result<_, _>>()?,
+
+            (SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+                let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+                reader.samples::<i32>()
+                    .map(|sample| sample.map(|value| value as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+
+            (format, bits) => return Err(eyre!(
+                "unsupported wav format in '{}': {bits}-bit {format:?}", path.display()
+            )),
+        };
+
+        let frames = samples.chunks_exact(channels)
+            .map(|frame| match frame {
+                [mono] => [*mono, *mono],
+                [left, right, ..] => [*left, *right],
+                [] => [0.0, 0.0],
+            })
+            .collect();
+
+        Ok(Self::new(frames, spec.sample_rate))
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Cubic Hermite interpolation between frames, silent outside the data.
+    fn at(&self, position: f64) -> Frame {
+        let index = position.floor() as isize;
+        let t = (position - index as f64) as f32;
+
+        let get = |i: isize| -> Frame {
+            usize::try_from(i).ok()
+                .and_then(|i| self.frames.get(i))
+                .copied()
+                .unwrap_or([0.0; 2])
+        };
+
+        let (p0, p1, p2, p3) = (get(index - 1), get(index), get(index + 1), get(index + 2));
+        let mut out = [0.0; 2];
+
+        for ch in 0..2 {
+            let c1 = 0.5 * (p2[ch] - p0[ch]);
+            let c2 = p0[ch] - 2.5 * p1[ch] + 2.0 * p2[ch] - 0.5 * p3[ch];
+            let c3 = 0.5 * (p3[ch] - p0[ch]) + 1.5 * (p1[ch] - p2[ch]);
+            out[ch] = ((c3 * t + c2) * t + c1) * t + p1[ch];
+        }
+
+        out
+    }
+}
+
+/// A sample mapped over a range of keys. Playing `root` plays it at its
+/// recorded pitch, every semitone away shifts it accordingly.
+#[derive(Clone)]
+pub struct SampleZone {
+    pub sample: Arc<SampleData>,
+    pub root: u8,
+    pub low: u8,
+    pub high: u8,
+    /// Play to the end regardless of note-offs, as drum hits usually want.
+    pub one_shot: bool,
+}
+
+impl SampleZone {
+    pub fn new(sample: Arc<SampleData>, root: u8) -> Self {
+        Self {
+            sample,
+            root,
+            low: 0,
+            high: 127,
+            one_shot: false,
+        }
+    }
+
+    pub fn contains(&self, pitch: u8) -> bool {
+        (self.low..=self.high).contains(&pitch)
+    }
+}
+
+/// Short fades so that starting and stopping mid-waveform never clicks.
+const DECLICK: AdsrParams = AdsrParams {
+    attack: 0.001,
+    decay: 0.0,
+    sustain: 1.0,
+    release: 0.05,
+};
+
+struct SamplerVoice {
+    zone: usize,
+    pitch: u8,
+    velocity: f32,
+    started: u64,
+    position: f64,
+    /// Frames of sample data to move for each output frame.
+    step: f64,
+    env: Adsr,
+}
+
+impl SamplerVoice {
+    fn is_active(&self) -> bool {
+        self.env.is_active()
+    }
+}
+
+pub struct Sampler {
+    zones: Vec<SampleZone>,
+    voices: Vec<SamplerVoice>,
+    sample_rate: f64,
+    triggers: u64,
+    gain: f32,
+}
+
+impl Sampler {
+    pub fn new(zones: Vec<SampleZone>, polyphony: usize, sample_rate: f64) -> Self {
+        let voices = (0..polyphony.max(1))
+            .map(|_| SamplerVoice {
+                zone: 0,
+                pitch: 0,
+                velocity: 0.0,
+                started: 0,
+                position: 0.0,
+                step: 1.0,
+                env: Adsr::new(DECLICK, sample_rate),
+            })
+            .collect();
+
+        Self {
+            zones,
+            voices,
+            sample_rate,
+            triggers: 0,
+            gain: PARAMS[0].default as f32,
+        }
+    }
+
+    /// A single sample across the whole keyboard.
+    pub fn from_file(path: &Path, root: u8, sample_rate: f64) -> Result<Self> {
+        let sample = Arc::new(SampleData::load(path)?);
+        Ok(Self::new(vec![SampleZone::new(sample, root)], 16, sample_rate))
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    /// A free voice, or else the one that has been playing longest.
+    fn allocate_voice(&self) -> usize {
+        self.voices.iter()
+            .position(|voice| !voice.is_active())
+            .or_else(|| self.voices.iter()
+                .enumerate()
+                .min_by_key(|(_, voice)| voice.started)
+                .map(|(i, _)| i))
+            .unwrap_or(0)
+    }
+}
+
+impl Instrument for Sampler {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        for voice in self.voices.iter_mut() {
+            voice.env.set_sample_rate(sample_rate);
+        }
+    }
+
+    fn note_on(&mut self, pitch: u8, velocity: u8) {
+        let Some(zone_index) = self.zones.iter().position(|zone| zone.contains(pitch)) else {
+            return;
+        };
+
+        let zone = &self.zones[zone_index];
+        let semitones = pitch as f64 - zone.root as f64;
+        let step = (semitones / 12.0).exp2() * zone.sample.sample_rate as f64 / self.sample_rate;
+
+        self.triggers += 1;
+        let index = self.allocate_voice();
+        let voice = &mut self.voices[index];
+
+        voice.zone = zone_index;
+        voice.pitch = pitch;
+        voice.velocity = velocity as f32 / 127.0;
+        voice.started = self.triggers;
+        voice.position = 0.0;
+        voice.step = step;
+        voice.env.reset();
+        voice.env.gate_on();
+    }
+
+    fn note_off(&mut self, pitch: u8) {
+        for voice in self.voices.iter_mut() {
+            if voice.is_active() && voice.pitch == pitch && !self.zones[voice.zone].one_shot {
+                voice.env.gate_off();
+            }
+        }
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        if index == 0 {
+            self.set_gain(value as f32);
+        }
+    }
+
+    fn render(&mut self, out: &mut [Frame]) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.is_active()) {
+            let sample = &self.zones[voice.zone].sample;
+
+            for frame in out.iter_mut() {
+                if voice.position >= sample.len() as f64 {
+                    voice.env.reset();
+                    break;
+                }
+
+                let [left, right] = sample.at(voice.position);
+                let gain = voice.env.next() as f32 * voice.velocity * self.gain;
+
+                frame[0] += left * gain;
+                frame[1] += right * gain;
+                voice.position += voice.step;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hound::{WavSpec, WavWriter};
+
+    use super::*;
+
+    const LEVELS: [f32; 4] = [0.5, -0.25, 0.75, -1.0];
+
+    /// Write `LEVELS` as interleaved samples and load them back.
+    fn load(channels: u16, bits: u16, format: SampleFormat, name: &str) -> SampleData {
+        let spec = WavSpec { channels, sample_rate: 44100, bits_per_sample: bits, sample_format: format };
+        let path = std::env::temp_dir().join(name);
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        let scale = (1i64 << (bits - 1)) as f32;
+
+        for level in LEVELS {
+            match (format, bits) {
+                (SampleFormat::Float, _) => writer.write_sample(level).unwrap(),
+                (SampleFormat::Int, 8) => writer.write_sample((level * scale) as i8).unwrap(),
+                (SampleFormat::Int, 16) => writer.write_sample((level * scale) as i16).unwrap(),
+                (SampleFormat::Int, _) => writer.write_sample((level * scale) as i32).unwrap(),
+            }
+        }
+
+        writer.finalize().unwrap();
+        let data = SampleData::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(data.sample_rate, 44100);
+        data
+    }
+
+    #[test]
+    fn loads_every_format() {
+        for (bits, format, name) in [
+            (8, SampleFormat::Int, "daw_sample_8"),
+            (16, SampleFormat::Int, "daw_sample_16"),
+            (24, SampleFormat::Int, "daw_sample_24"),
+            (32, SampleFormat::Float, "daw_sample_32f"),
+        ] {
+            let mono = load(1, bits, format, &format!("{name}_mono.wav"));
+            let expected: Vec<Frame> = LEVELS.iter().map(|level| [*level, *level]).collect();
+            assert_eq!(mono.frames, expected, "{bits}-bit {format:?} mono");
+
+            let stereo = load(2, bits, format, &format!("{name}_stereo.wav"));
+            let expected: Vec<Frame> = LEVELS.chunks(2).map(|pair| [pair[0], pair[1]]).collect();
+            assert_eq!(stereo.frames, expected, "{bits}-bit {format:?} stereo");
+        }
+    }
+
+    #[test]
+    fn pitch_moves_away_from_the_root() {
+        let sample = Arc::new(SampleData::new(vec![[0.0; 2]; 100], 24000));
+        let mut sampler = Sampler::new(vec![SampleZone::new(sample, 60)], 3, 48000.0);
+
+        // The sample rate difference alone halves the step.
+        for pitch in [60, 72, 48] {
+            sampler.note_on(pitch, 100);
+        }
+
+        let steps: Vec<f64> = sampler.voices.iter().map(|voice| voice.step).collect();
+        assert_eq!(steps, [0.5, 1.0, 0.25]);
+    }
+}
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::Frame;
+use crate::effect::Param;
+use crate::instrument::Instrument;
+use crate::dsp::{
+    midi_to_freq,
+    envelope::{Adsr, AdsrParams, Stage},
+    filter::{FilterMode, Svf},
+    oscillator::{Oscillator, Quality, Waveform},
+};
+
+/// What can be set while the synth plays.
+pub const PARAMS: [Param; 6] = [
+    Param::new("cutoff", "Hz", 20.0, 20000.0, 800.0).log(),
+    Param::new("resonance", "", 0.0, 0.99, 0.3),
+    Param::new("envelope", "oct", 0.0, 8.0, 3.0),
+    Param::new("mix", "", 0.0, 1.0, 0.5),
+    Param::new("detune", "ct", -100.0, 100.0, 7.0),
+    Param::new("gain", "", 0.0, 1.0, 0.3),
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SynthParams {
+    pub voices: usize,
+    pub osc1: Waveform,
+    pub osc2: Waveform,
+    /// Naive oscillators are cheaper, and alias at high pitches.
+    pub quality: Quality,
+    /// How much of each cycle square oscillators are high for.
+    pub pulse_width: f64,
+    /// Detune of the second oscillator, in cents.
+    pub detune: f64,
+    /// Balance between the oscillators, 0 is only `osc1` and 1 only `osc2`.
+    pub osc_mix: f64,
+    pub filter_mode: FilterMode,
+    pub cutoff: f64,
+    pub resonance: f64,
+    /// How far the filter envelope opens the cutoff, in octaves.
+    pub filter_env_amount: f64,
+    pub amp_env: AdsrParams,
+    pub filter_env: AdsrParams,
+    pub gain: f64,
+}
+
+impl SynthParams {
+    /// A parameter by its place in `PARAMS`.
+    pub fn value(&self, index: usize) -> f64 {
+        match index {
+            0 => self.cutoff,
+            1 => self.resonance,
+            2 => self.filter_env_amount,
+            3 => self.osc_mix,
+            4 => self.detune,
+            _ => self.gain,
+        }
+    }
+}
+
+impl Default for SynthParams {
+    fn default() -> Self {
+        Self {
+            voices: 8,
+            osc1: Waveform::Saw,
+            osc2: Waveform::Saw,
+            quality: Quality::PolyBlep,
+            pulse_width: 0.5,
+            detune: 7.0,
+            osc_mix: 0.5,
+            filter_mode: FilterMode::Lowpass,
+            cutoff: 800.0,
+            resonance: 0.3,
+            filter_env_amount: 3.0,
+            amp_env: AdsrParams::default(),
+            filter_env: AdsrParams {
+                attack: 0.01,
+                decay: 0.3,
+                sustain: 0.2,
+                release: 0.3,
+            },
+            gain: 0.3,
+        }
+    }
+}
+
+struct Voice {
+    pitch: u8,
+    velocity: f64,
+    /// When the voice was last triggered, for picking which one to steal.
+    started: u64,
+    osc1: Box<dyn Oscillator>,
+    osc2: Box<dyn Oscillator>,
+    filter: Svf,
+    amp_env: Adsr,
+    filter_env: Adsr,
+}
+
+impl Voice {
+    fn new(params: &SynthParams, sample_rate: f64, seed: u64) -> Self {
+        let mut filter = Svf::new(params.filter_mode, sample_rate);
+        filter.set(params.cutoff, params.resonance);
+
+        let mut osc1 = params.osc1.build(440.0, sample_rate, seed);
+        let mut osc2 = params.osc2.build(440.0, sample_rate, seed + 1);
+        for osc in [&mut osc1, &mut osc2] {
+            osc.set_quality(params.quality);
+            osc.set_pulse_width(params.pulse_width);
+        }
+
+        Self {
+            pitch: 0,
+            velocity: 0.0,
+            started: 0,
+            osc1,
+            osc2,
+            filter,
+            amp_env: Adsr::new(params.amp_env, sample_rate),
+            filter_env: Adsr::new(params.filter_env, sample_rate),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.amp_env.is_active()
+    }
+
+    fn is_released(&self) -> bool {
+        self.amp_env.stage() == Stage::Release
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.osc1.set_sample_rate(sample_rate);
+        self.osc2.set_sample_rate(sample_rate);
+        self.filter.set_sample_rate(sample_rate);
+        self.amp_env.set_sample_rate(sample_rate);
+        self.filter_env.set_sample_rate(sample_rate);
+    }
+
+    fn start(&mut self, params: &SynthParams, pitch: u8, velocity: u8, started: u64) {
+        let freq = midi_to_freq(pitch as f64);
+
+        // A fresh voice starts clean; a stolen one keeps its state so
+        // the transition does not click.
+        if !self.is_active() {
+            self.osc1.set_phase(0.0);
+            self.osc2.set_phase(0.0);
+            self.filter.reset();
+        }
+
+        self.osc1.set_frequency(freq);
+        self.osc2.set_frequency(freq * (params.detune / 1200.0).exp2());
+
+        self.pitch = pitch;
+        self.velocity = velocity as f64 / 127.0;
+        self.started = started;
+
+        self.amp_env.gate_on();
+        self.filter_env.gate_on();
+    }
+
+    fn release(&mut self) {
+        self.amp_env.gate_off();
+        self.filter_env.gate_off();
+    }
+
+    fn next(&mut self, params: &SynthParams) -> f64 {
+        let osc = self.osc1.next() * (1.0 - params.osc_mix)
+            + self.osc2.next() * params.osc_mix;
+
+        let octaves = params.filter_env_amount * self.filter_env.next();
+        self.filter.set(params.cutoff * octaves.exp2(), params.resonance);
+
+        self.filter.process(osc) * self.amp_env.next() * self.velocity
+    }
+}
+
+/// Polyphonic two-oscillator subtractive synth.
+pub struct Synth {
+    params: SynthParams,
+    voices: Vec<Voice>,
+    sample_rate: f64,
+    /// Counts note-ons, so voices can be ordered by age.
+    triggers: u64,
+}
+
+impl Synth {
+    pub fn new(params: SynthParams, sample_rate: f64) -> Self {
+        let voices = (0..params.voices.max(1))
+            .map(|i| Voice::new(&params, sample_rate, 2 * i as u64))
+            .collect();
+
+        Self {
+            params,
+            voices,
+            sample_rate,
+            triggers: 0,
+        }
+    }
+
+    /// Pick the voice for a new note: one already playing this pitch, then
+    /// a free one, then the oldest released one, then the oldest overall.
+    fn allocate_voice(&self, pitch: u8) -> usize {
+        self.voices.iter()
+            .position(|voice| voice.is_active() && voice.pitch == pitch)
+            .or_else(|| self.voices.iter().position(|voice| !voice.is_active()))
+            .or_else(|| self.voices.iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.is_released())
+                .min_by_key(|(_, voice)| voice.started)
+                .map(|(i, _)| i))
+            .or_else(|| self.voices.iter()
+                .enumerate()
+                .min_by_key(|(_, voice)| voice.started)
+                .map(|(i, _)| i))
+            .unwrap_or(0)
+    }
+}
+
+impl Instrument for Synth {
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        for voice in self.voices.iter_mut() {
+            voice.set_sample_rate(sample_rate);
+        }
+    }
+
+    fn note_on(&mut self, pitch: u8, velocity: u8) {
+        self.triggers += 1;
+
+        let index = self.allocate_voice(pitch);
+        self.voices[index].start(&self.params, pitch, velocity, self.triggers);
+    }
+
+    fn note_off(&mut self, pitch: u8) {
+        for voice in self.voices.iter_mut() {
+            if voice.is_active() && voice.pitch == pitch && !voice.is_released() {
+                voice.release();
+            }
+        }
+    }
+
+    fn set_param(&mut self, index: usize, value: f64) {
+        match index {
+            0 => self.params.cutoff = value,
+            1 => self.params.resonance = value,
+            2 => self.params.filter_env_amount = value,
+            3 => self.params.osc_mix = value,
+
+            // Retune the second oscillator of notes already playing.
+            4 => {
+                self.params.detune = value;
+                let ratio = (value / 1200.0).exp2();
+
+                for voice in self.voices.iter_mut().filter(|voice| voice.is_active()) {
+                    voice.osc2.set_frequency(midi_to_freq(voice.pitch as f64) * ratio);
+                }
+            }
+
+            5 => self.params.gain = value,
+            _ => (),
+        }
+    }
+
+    fn render(&mut self, out: &mut [Frame]) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.is_active()) {
+            for frame in out.iter_mut() {
+                let sample = (voice.next(&self.params) * self.params.gain) as f32;
+                frame[0] += sample;
+                frame[1] += sample;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synth(voices: usize) -> Synth {
+        Synth::new(SynthParams { voices, ..SynthParams::default() }, 48000.0)
+    }
+
+    fn pitches(synth: &Synth) -> Vec<u8> {
+        synth.voices.iter().filter(|voice| voice.is_active()).map(|voice| voice.pitch).collect()
+    }
+
+    #[test]
+    fn steals_the_oldest_voice() {
+        let mut synth = synth(2);
+        synth.note_on(60, 100);
+        synth.note_on(62, 100);
+        synth.note_on(64, 100);
+        assert_eq!(pitches(&synth), [64, 62]);
+
+        // A released voice goes before an older held one.
+        synth.note_off(64);
+        synth.note_on(65, 100);
+        assert_eq!(pitches(&synth), [65, 62]);
+
+        // The same pitch retriggers its own voice.
+        synth.note_on(62, 100);
+        assert_eq!(pitches(&synth), [65, 62]);
+    }
+}
@@ -1,35 +1,29 @@
-//mod audio;
-//use audio::Output;
-
 mod app;
+mod args;
+mod audio;
+mod dsp;
+mod effect;
+mod instrument;
 mod input;
+mod operator;
+mod project;
+mod registers;
 mod widgets;
 mod window;
 
 use app::{App, AppState};
+use args::Args;
 use color_eyre::eyre::Result;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let terminal = ratatui::init();
+    let args = Args::parse()?;
+    let mut state = AppState::new(&args)?;
 
-    let mut state = AppState::new();
+    let terminal = ratatui::init();
     let result = App::run_loop(terminal, &mut state);
 
     ratatui::restore();
-    return result;
+    result
 }
-
-
-// fn main() {
-//     let out: Output = Output::initialise().expect("failed to create output");
-//     // Produce a sinusoid of maximum amplitude.
-//     let sample_rate = 44100 as f32;
-//     let mut sample_clock: f32 = 0.0;
-//     let next_value = move || {
-//         sample_clock = (sample_clock + 1.0) % sample_rate;
-//         return (sample_clock * 220.0 * 2.0 * std::f32::consts::PI / sample_rate).sin() as f64;
-//     };
-//     out.play(Box::new(next_value));
-// }
@@ -0,0 +1,300 @@
+use crate::dsp::random::Rng;
+use crate::input::{Operation, Operator, TextObject};
+use crate::project::{
+    PatternId, Project,
+    pattern::{Note, NoteRange, NoteState},
+    tempo::TimeUnit,
+};
+use crate::registers::Clipboard;
+use crate::window::Context;
+
+/// Velocities change by this much for every step.
+const VELOCITY_STEP: f64 = 1.1;
+
+/// What an operator works on, as the window it was typed in sees it.
+pub struct Target {
+    pub pattern: PatternId,
+    /// The notes a motion covered, if it covered anything.
+    pub range: Option<NoteRange>,
+    /// Tick that pastes go to.
+    pub cursor: u32,
+    /// Ticks in a column of the window, the unit notes are nudged by.
+    pub grid: u32,
+}
+
+/// Carry out an operator on the notes of a pattern. Returns what to tell
+/// the user.
+pub fn apply(operation: Operation, target: Target, ctx: &mut Context) -> Option<String> {
+    let Target { pattern, range, cursor, grid } = target;
+
+    match operation.operator {
+        Operator::Yank => {
+            let range = range?;
+            let clipboard = copy(ctx, pattern, &range)?;
+            let count = clipboard.notes.len();
+
+            ctx.registers.yank(operation.register, clipboard);
+            Some(format!("{} yanked", notes(count)))
+        }
+
+        Operator::Delete => {
+            let range = range?;
+            let clipboard = copy(ctx, pattern, &range)?;
+            let count = clipboard.notes.len();
+
+            ctx.project.pattern_mut(pattern)?.remove_range(&range);
+            ctx.registers.delete(operation.register, clipboard);
+            Some(format!("{} deleted", notes(count)))
+        }
+
+        Operator::Paste => {
+            let clipboard = ctx.registers.get(operation.register)?.clone();
+            let pattern = ctx.project.pattern_mut(pattern)?;
+
+            // Notes that would start past the end of the pattern are left
+            // out rather than growing it.
+            let length = pattern.length();
+            let mut pasted = 0;
+            for repeat in 0..operation.count as u32 {
+                let offset = cursor.saturating_add(repeat.saturating_mul(clipboard.length));
+                for note in &clipboard.notes {
+                    let start = offset.saturating_add(note.start);
+                    if start >= length {
+                        continue;
+                    }
+
+                    pattern.insert(Note { start, ..*note });
+                    pasted += 1;
+                }
+            }
+
+            Some(format!("{} pasted", notes(pasted)))
+        }
+
+        // Notes move together and stop at the edge of the keyboard, so
+        // that none of them land on each other.
+        Operator::Transpose { semitones } => {
+            let range = range?;
+            let pattern = ctx.project.pattern_mut(pattern)?;
+            let (low, high) = pattern.notes_in(&range)
+                .fold((127, 0), |(low, high), note| (low.min(note.pitch), high.max(note.pitch)));
+
+            let semitones = semitones.clamp(-(low as i32), 127 - high as i32);
+            let changed = pattern.transform(&range, |note| {
+                note.pitch = (note.pitch as i32 + semitones) as u8;
+            });
+
+            Some(format!("{} transposed by {semitones:+}", notes(changed)))
+        }
+
+        Operator::Velocity { steps } => {
+            let scale = VELOCITY_STEP.powi(steps);
+            let changed = ctx.project.pattern_mut(pattern)?.transform(&range?, |note| {
+                note.velocity = (note.velocity as f64 * scale).round().clamp(1.0, 127.0) as u8;
+            });
+
+            Some(format!("{} {}", notes(changed), if steps > 0 { "louder" } else { "softer" }))
+        }
+
+        // Stops at the start of the pattern the way transposing stops at
+        // the edge of the keyboard.
+        Operator::Nudge { steps } => {
+            let range = range?;
+            let pattern = ctx.project.pattern_mut(pattern)?;
+            let first = pattern.notes_in(&range).map(|note| note.start).min().unwrap_or(0);
+
+            let first = i32::try_from(first).unwrap_or(i32::MAX);
+            let ticks = steps.saturating_mul(grid as i32).max(-first);
+            let changed = pattern.transform(&range, |note| {
+                note.start = note.start.saturating_add_signed(ticks);
+            });
+
+            Some(format!("{} nudged", notes(changed)))
+        }
+
+        Operator::Mute | Operator::Solo => {
+            let (state, verb) = match operation.operator {
+                Operator::Mute => (NoteState::Muted, "muted"),
+                _ => (NoteState::Solo, "soloed"),
+            };
+
+            // Toggles, unless only some of the notes were already set.
+            let range = range?;
+            let pattern = ctx.project.pattern_mut(pattern)?;
+            let set = pattern.notes_in(&range).all(|note| note.state == state);
+            let new = if set { NoteState::Normal } else { state };
+
+            let changed = pattern.transform(&range, |note| note.state = new);
+            Some(format!("{} {}{verb}", notes(changed), if set { "un" } else { "" }))
+        }
+
+        Operator::Quantize(settings) => {
+            let changed = ctx.project.pattern_mut(pattern)?
+                .transform(&range?, |note| settings.apply(note, grid));
+
+            Some(format!("{} quantized", notes(changed)))
+        }
+
+        Operator::Humanize(settings) => {
+            let mut rng = Rng::new(settings.seed);
+            let changed = ctx.project.pattern_mut(pattern)?
+                .transform(&range?, |note| settings.apply(note, &mut rng));
+
+            Some(format!("{} humanized with seed {}", notes(changed), settings.seed))
+        }
+
+        _ => None,
+    }
+}
+
+/// Every note in the beats, bars or pattern around `tick`, counting the
+/// beats and bars as the pattern is placed in the song.
+pub fn text_object(
+    project: &Project,
+    pattern: PatternId,
+    tick: u32,
+    object: TextObject,
+    count: usize,
+) -> Option<NoteRange> {
+    let length = project.pattern(pattern)?.length();
+    let unit = match object {
+        TextObject::Beat => TimeUnit::Beat,
+        TextObject::Bar => TimeUnit::Bar,
+        TextObject::Pattern => return Some(NoteRange { ticks: 0..length, pitches: 0..=127 }),
+    };
+
+    let start = project.pattern_start(pattern).unwrap_or(0);
+    let span = project.tempo_map().span(start + tick, unit, count as u32);
+
+    Some(NoteRange {
+        ticks: span.start.saturating_sub(start)..span.end.saturating_sub(start),
+        pitches: 0..=127,
+    })
+}
+
+fn copy(ctx: &Context, pattern: PatternId, range: &NoteRange) -> Option<Clipboard> {
+    let notes = ctx.project.pattern(pattern)?
+        .notes_in(range)
+        .map(|note| Note { start: note.start - range.ticks.start, ..*note })
+        .collect();
+
+    Some(Clipboard { notes, length: range.ticks.len() as u32 })
+}
+
+pub fn notes(count: usize) -> String {
+    match count {
+        1 => "1 note".to_string(),
+        _ => format!("{count} notes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::{mixer::Meters, transport::{PlayState, TransportStatus}};
+    use crate::input::Motion;
+    use crate::registers::Registers;
+
+    use super::*;
+
+    fn note(pitch: u8, start: u32) -> Note {
+        Note { pitch, velocity: 100, start, length: 24, state: NoteState::Normal }
+    }
+
+    /// Run an operator over pattern 0 with the cursor at `cursor`.
+    fn run(
+        project: &mut Project,
+        registers: &mut Registers,
+        operator: Operator,
+        count: usize,
+        range: Option<NoteRange>,
+        cursor: u32,
+    ) -> Option<String> {
+        let meters = Meters::new();
+        let mut ctx = Context {
+            project,
+            registers,
+            transport: TransportStatus { state: PlayState::Stopped, tick: 0.0 },
+            meters: &meters,
+        };
+
+        let operation = Operation { operator, count, motion: Motion::None, register: None };
+        apply(operation, Target { pattern: 0, range, cursor, grid: 24 }, &mut ctx)
+    }
+
+    fn notes(project: &Project) -> Vec<(u8, u32)> {
+        project.pattern(0).unwrap().notes().iter().map(|note| (note.pitch, note.start)).collect()
+    }
+
+    #[test]
+    fn pastes_repeat_one_after_another() {
+        let mut project = Project::with_default_tracks();
+        let mut registers = Registers::default();
+        let pattern = project.pattern_mut(0).unwrap();
+        pattern.insert(note(60, 0));
+        pattern.insert(note(64, 24));
+
+        let range = NoteRange { ticks: 0..48, pitches: 0..=127 };
+        run(&mut project, &mut registers, Operator::Yank, 1, Some(range), 0);
+
+        let message = run(&mut project, &mut registers, Operator::Paste, 3, None, 96);
+        assert_eq!(message.as_deref(), Some("6 notes pasted"));
+        assert_eq!(notes(&project), [
+            (60, 0), (64, 24),
+            (60, 96), (64, 120),
+            (60, 144), (64, 168),
+            (60, 192), (64, 216),
+        ]);
+    }
+
+    #[test]
+    fn pastes_stop_at_the_end_of_the_pattern() {
+        let mut project = Project::with_default_tracks();
+        let mut registers = Registers::default();
+        let pattern = project.pattern_mut(0).unwrap();
+        let length = pattern.length();
+        pattern.insert(note(60, 0));
+        pattern.insert(note(64, 24));
+
+        let range = NoteRange { ticks: 0..48, pitches: 0..=127 };
+        run(&mut project, &mut registers, Operator::Yank, 1, Some(range.clone()), 0);
+        run(&mut project, &mut registers, Operator::Delete, 1, Some(range), 0);
+
+        let message = run(&mut project, &mut registers, Operator::Paste, 3, None, length - 72);
+        assert_eq!(message.as_deref(), Some("3 notes pasted"));
+        assert_eq!(notes(&project), [(60, length - 72), (64, length - 48), (60, length - 24)]);
+        assert_eq!(project.pattern(0).unwrap().length(), length);
+
+        let message = run(&mut project, &mut registers, Operator::Paste, 1000, None, u32::MAX - 24);
+        assert_eq!(message.as_deref(), Some("0 notes pasted"));
+        assert_eq!(project.pattern(0).unwrap().length(), length);
+    }
+
+    #[test]
+    fn moved_notes_stay_apart_at_the_edges() {
+        let mut project = Project::with_default_tracks();
+        let mut registers = Registers::default();
+        let pattern = project.pattern_mut(0).unwrap();
+        for pitch in [120, 124, 127] {
+            pattern.insert(note(pitch, 48));
+        }
+        pattern.insert(note(60, 24));
+
+        let chord = || Some(NoteRange { ticks: 48..49, pitches: 0..=127 });
+
+        // The chord only goes as far as its top note can.
+        let message = run(&mut project, &mut registers, Operator::Transpose { semitones: 12 }, 1, chord(), 0);
+        assert_eq!(message.as_deref(), Some("3 notes transposed by +0"));
+        run(&mut project, &mut registers, Operator::Transpose { semitones: -125 }, 1, chord(), 0);
+        assert_eq!(notes(&project), [(60, 24), (0, 48), (4, 48), (7, 48)]);
+
+        // Nudging stops at the start of the pattern.
+        run(&mut project, &mut registers, Operator::Nudge { steps: -5 }, 1, chord(), 0);
+        assert_eq!(notes(&project), [(0, 0), (4, 0), (7, 0), (60, 24)]);
+
+        let all = Some(NoteRange { ticks: 0..96, pitches: 0..=127 });
+        run(&mut project, &mut registers, Operator::Velocity { steps: 100 }, 1, all.clone(), 0);
+        assert!(project.pattern(0).unwrap().notes().iter().all(|note| note.velocity == 127));
+        run(&mut project, &mut registers, Operator::Velocity { steps: -100 }, 1, all, 0);
+        assert!(project.pattern(0).unwrap().notes().iter().all(|note| note.velocity == 1));
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::automation::{Automation, AutomationLane, Destination};
+use crate::effect::Param;
+use crate::project::Project;
+
+/// The fader as automation sees it, in the mixer's range.
+pub const GAIN: Param = Param::new("gain", "dB", -60.0, 6.0, 0.0);
+pub const PAN: Param = Param::new("pan", "", -1.0, 1.0, 0.0);
+
+/// How the value moves from a point to the next one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Slow at first and quick at the end when rising, the other way round
+    /// when falling, the way fades are heard.
+    Exponential,
+    /// Holds the value until the next point.
+    Step,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 3] = [Curve::Linear, Curve::Exponential, Curve::Step];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Linear => "linear",
+            Curve::Exponential => "exp",
+            Curve::Step => "step",
+        }
+    }
+
+    /// The curve `steps` along in `ALL`, wrapping around.
+    pub fn cycle(self, steps: i32) -> Self {
+        let index = Self::ALL.iter().position(|curve| *curve == self).unwrap_or(0);
+        Self::ALL[(index as i32 + steps).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+
+    /// How far from `from` to `to` the value is, `t` of the way along.
+    fn interpolate(self, from: f64, to: f64, t: f64) -> f64 {
+        // Sharp enough to hear, gentle enough to still move at the start.
+        const SHARPNESS: f64 = 4.0;
+        let rise = |t: f64| (SHARPNESS * t).exp_m1() / SHARPNESS.exp_m1();
+
+        match self {
+            Curve::Linear => from + (to - from) * t,
+            Curve::Exponential if to >= from => from + (to - from) * rise(t),
+            Curve::Exponential => to + (from - to) * rise(1.0 - t),
+            Curve::Step => from,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    /// Song tick.
+    pub tick: u32,
+    /// Where in the parameter's range, from 0 to 1.
+    pub value: f64,
+    /// How the value goes on to the next point.
+    #[serde(default)]
+    pub curve: Curve,
+}
+
+/// Breakpoints a parameter follows over the song. Before the first point
+/// and after the last one it stays where they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Kept ordered by tick, at most one on any tick.
+    points: Vec<Point>,
+}
+
+impl Envelope {
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Add a point, replacing one on the same tick. Returns where it went.
+    pub fn insert(&mut self, point: Point) -> usize {
+        let index = self.points.partition_point(|other| other.tick < point.tick);
+
+        match self.points.get(index) {
+            Some(other) if other.tick == point.tick => self.points[index] = point,
+            _ => self.points.insert(index, point),
+        }
+
+        index
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Point> {
+        (index < self.points.len()).then(|| self.points.remove(index))
+    }
+
+    pub fn point_mut(&mut self, index: usize) -> Option<&mut Point> {
+        self.points.get_mut(index)
+    }
+
+    /// Move a point, no further than the points either side of it so that
+    /// it keeps its place. Returns the tick it ended up on.
+    pub fn move_point(&mut self, index: usize, tick: u32, value: f64) -> Option<u32> {
+        let after = index.checked_sub(1).map_or(0, |before| self.points[before].tick + 1);
+        let before = self.points.get(index + 1).map_or(u32::MAX, |next| next.tick - 1);
+
+        let point = self.points.get_mut(index)?;
+        point.tick = tick.clamp(after, before);
+        point.value = value.clamp(0.0, 1.0);
+        Some(point.tick)
+    }
+
+    /// Where the parameter is at a song tick, nothing without any points.
+    pub fn value_at(&self, tick: f64) -> Option<f64> {
+        let next = self.points.partition_point(|point| (point.tick as f64) <= tick);
+
+        let Some(from) = next.checked_sub(1).map(|index| self.points[index]) else {
+            return self.points.first().map(|point| point.value);
+        };
+        let Some(to) = self.points.get(next) else {
+            return Some(from.value);
+        };
+
+        let t = (tick - from.tick as f64) / (to.tick - from.tick) as f64;
+        Some(from.curve.interpolate(from.value, to.value, t))
+    }
+
+    /// The first point after a song tick.
+    pub fn next_point(&self, tick: f64) -> Option<u32> {
+        let next = self.points.partition_point(|point| (point.tick as f64) <= tick);
+        self.points.get(next).map(|point| point.tick)
+    }
+}
+
+/// What a lane moves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    Gain,
+    Pan,
+    /// A parameter of the track's instrument, by name.
+    Instrument(String),
+    /// A parameter of an effect, by its place in the chain and name.
+    Insert { insert: usize, param: String },
+}
+
+impl Target {
+    /// Reads `gain`, `pan`, an instrument parameter, or an insert number
+    /// counting from 1 followed by one of its parameters.
+    pub fn parse(args: &[&str]) -> Option<Self> {
+        match args {
+            ["gain"] => Some(Target::Gain),
+            ["pan"] => Some(Target::Pan),
+            [param] => Some(Target::Instrument(param.to_lowercase())),
+
+            [insert, param] => Some(Target::Insert {
+                insert: insert.parse::<usize>().ok()?.checked_sub(1)?,
+                param: param.to_lowercase(),
+            }),
+
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Target::Gain => "gain".to_string(),
+            Target::Pan => "pan".to_string(),
+            Target::Instrument(param) => param.clone(),
+            Target::Insert { insert, param } => format!("{} {param}", insert + 1),
+        }
+    }
+}
+
+/// A parameter of a track or bus and the envelope it follows.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lane {
+    pub target: Target,
+    pub envelope: Envelope,
+}
+
+impl Project {
+    /// The automation lanes of a track or bus.
+    pub fn automation(&self, id: usize) -> Option<&[Lane]> {
+        match self.track(id) {
+            Some(track) => Some(&track.automation),
+            None => Some(&self.bus(id)?.automation),
+        }
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn automation_mut(&mut self, id: usize) -> Option<&mut Vec<Lane>> {
+        if self.track(id).is_some() {
+            return Some(&mut self.track_mut(id)?.automation);
+        }
+
+        Some(&mut self.bus_mut(id)?.automation)
+    }
+
+    /// The parameter a target moves on a track or bus, and where it is by
+    /// its own index, for those that have one.
+    fn resolve(&self, id: usize, target: &Target) -> Option<(Param, Destination)> {
+        let find = |params: &'static [Param], name: &str| {
+            params.iter().position(|param| param.name == name).map(|index| (params[index], index))
+        };
+
+        match target {
+            Target::Gain => Some((GAIN, Destination::Gain)),
+            Target::Pan => Some((PAN, Destination::Pan)),
+
+            Target::Instrument(name) => {
+                let (param, index) = find(self.track(id)?.instrument.params(), name)?;
+                Some((param, Destination::Instrument(index)))
+            }
+
+            Target::Insert { insert, param: name } => {
+                let effect = self.inserts(id)?.get(*insert)?;
+                let (param, index) = find(effect.kind.params(), name)?;
+                Some((param, Destination::Insert { insert: *insert, param: index }))
+            }
+        }
+    }
+
+    pub fn param(&self, id: usize, target: &Target) -> Option<Param> {
+        Some(self.resolve(id, target)?.0)
+    }
+
+    /// Where a parameter is set to, as it is without automation.
+    pub fn param_value(&self, id: usize, target: &Target) -> Option<f64> {
+        match target {
+            Target::Gain | Target::Pan => {
+                let (gain, pan) = match self.track(id) {
+                    Some(track) => (track.gain, track.pan),
+                    None => self.bus(id).map(|bus| (bus.gain, bus.pan))?,
+                };
+
+                Some(if *target == Target::Gain { gain } else { pan })
+            }
+
+            Target::Instrument(_) => {
+                let Destination::Instrument(index) = self.resolve(id, target)?.1 else { return None };
+                Some(self.track(id)?.instrument.value(index))
+            }
+
+            Target::Insert { insert, .. } => {
+                let Destination::Insert { param, .. } = self.resolve(id, target)?.1 else { return None };
+                Some(self.inserts(id)?.get(*insert)?.value(param))
+            }
+        }
+    }
+
+    /// Start automating a parameter from where it is set to now. Returns
+    /// the lane, which may have been there already, or nothing for a
+    /// parameter the track or bus does not have.
+    pub fn automate(&mut self, id: usize, target: Target) -> Option<usize> {
+        let param = self.param(id, &target)?;
+
+        if let Some(lane) = self.automation(id)?.iter().position(|lane| lane.target == target) {
+            return Some(lane);
+        }
+
+        let value = param.normalize(self.param_value(id, &target)?);
+        let mut envelope = Envelope::default();
+        envelope.insert(Point { tick: 0, value, curve: Curve::Linear });
+
+        let lanes = self.automation_mut(id)?;
+        lanes.push(Lane { target, envelope });
+        Some(lanes.len() - 1)
+    }
+
+    /// Every lane of a track or bus, as the audio thread follows them.
+    /// Lanes for parameters that are gone are left out.
+    pub fn automation_for(&self, id: usize) -> Automation {
+        let lanes = self.automation(id).unwrap_or_default();
+
+        Automation {
+            lanes: lanes.iter()
+                .filter_map(|lane| {
+                    let (param, destination) = self.resolve(id, &lane.target)?;
+                    Some(AutomationLane { destination, param, envelope: lane.envelope.clone() })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(points: &[(u32, f64, Curve)]) -> Envelope {
+        let mut envelope = Envelope::default();
+        for &(tick, value, curve) in points {
+            envelope.insert(Point { tick, value, curve });
+        }
+        envelope
+    }
+
+    #[test]
+    fn follows_each_curve_between_points() {
+        let envelope = envelope(&[
+            (96, 0.0, Curve::Linear),
+            (192, 1.0, Curve::Exponential),
+            (288, 0.0, Curve::Step),
+            (384, 0.5, Curve::Linear),
+        ]);
+
+        // Held before the first point and after the last.
+        assert_eq!(envelope.value_at(0.0), Some(0.0));
+        assert_eq!(envelope.value_at(1000.0), Some(0.5));
+
+        assert_eq!(envelope.value_at(144.0), Some(0.5));
+
+        // Falling, it drops quickly at first.
+        let falling = envelope.value_at(216.0).unwrap();
+        assert!(falling < 0.5, "{falling}");
+
+        assert_eq!(envelope.value_at(383.0), Some(0.0));
+        assert_eq!(envelope.value_at(384.0), Some(0.5));
+        assert_eq!(Envelope::default().value_at(0.0), None);
+    }
+
+    #[test]
+    fn points_keep_their_order() {
+        let mut envelope = envelope(&[(0, 0.0, Curve::Linear), (96, 1.0, Curve::Linear)]);
+
+        // Same tick replaces.
+        assert_eq!(envelope.insert(Point { tick: 96, value: 0.5, curve: Curve::Step }), 1);
+        assert_eq!(envelope.points().len(), 2);
+
+        assert_eq!(envelope.move_point(0, 500, 2.0), Some(95));
+        assert_eq!(envelope.points()[0].value, 1.0);
+        assert_eq!(envelope.next_point(0.0), Some(95));
+    }
+}
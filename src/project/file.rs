@@ -0,0 +1,288 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{eyre, Result};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::project::Project;
+use crate::window::LayoutSpec;
+
+/// Bumped whenever the format changes in a way older builds cannot read.
+const FORMAT_VERSION: u32 = 1;
+
+/// What goes on disk. Everything in it is kept in vectors, so the same
+/// project always writes out the same text.
+#[derive(Serialize, Deserialize)]
+struct ProjectFile<P, L> {
+    version: u32,
+    project: P,
+    #[serde(default)]
+    layout: L,
+}
+
+pub fn to_string(project: &Project, layout: &LayoutSpec) -> Result<String> {
+    let file = ProjectFile {
+        version: FORMAT_VERSION,
+        project,
+        layout,
+    };
+
+    Ok(ron::ser::to_string_pretty(&file, PrettyConfig::default())?)
+}
+
+pub fn from_str(text: &str) -> Result<(Project, LayoutSpec)> {
+    let file: ProjectFile<Project, LayoutSpec> = ron::from_str(text)?;
+
+    if file.version > FORMAT_VERSION {
+        return Err(eyre!(
+            "written by a newer version (format {}, this build reads up to {FORMAT_VERSION})",
+            file.version,
+        ));
+    }
+
+    let mut project = file.project;
+    check(&mut project)?;
+
+    Ok((project, file.layout))
+}
+
+/// Put right what can be put right in a project read from a file, and
+/// turn down what would break playback or the editor.
+fn check(project: &mut Project) -> Result<()> {
+    if project.tempo_map.tempos().iter().any(|change| !change.bpm.is_finite()) {
+        return Err(eyre!("a tempo is not a number"));
+    }
+
+    project.tempo_map.normalize();
+
+    for pattern in &project.patterns {
+        let note = pattern.notes().iter().find(|note| note.pitch > 127 || note.velocity > 127);
+
+        if let Some(note) = note {
+            return Err(eyre!(
+                "pattern '{}' has a note with pitch {} and velocity {}, above 127",
+                pattern.name, note.pitch, note.velocity,
+            ));
+        }
+    }
+
+    for track in &project.tracks {
+        if let Some(clip) = track.clips.iter().find(|clip| clip.pattern >= project.patterns.len()) {
+            return Err(eyre!("track '{}' plays pattern {}, which does not exist", track.name, clip.pattern));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save(path: &Path, project: &Project, layout: &LayoutSpec) -> Result<()> {
+    let text = to_string(project, layout)?;
+
+    fs::write(path, text)
+        .map_err(|err| eyre!("could not write '{}': {err}", path.display()))
+}
+
+pub fn load(path: &Path) -> Result<(Project, LayoutSpec)> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| eyre!("could not read '{}': {err}", path.display()))?;
+
+    from_str(&text).map_err(|err| eyre!("could not load '{}': {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ratatui::layout::Direction;
+
+    use super::*;
+    use crate::dsp::{filter::FilterMode, oscillator::{Quality, Waveform}};
+    use crate::instrument::synth::SynthParams;
+    use crate::project::{
+        BAR_TICKS,
+        pattern::{Note, NoteState, Pattern},
+        tempo::{DEFAULT_TEMPO, MIN_TEMPO, TempoChange},
+        track::{Clip, InstrumentSpec},
+    };
+    use crate::window::WindowKind;
+
+    fn project() -> Project {
+        let mut project = Project::with_default_tracks();
+        project.tempo_map_mut().set_tempo(0, 137.5);
+
+        let melody = project.first_pattern(0).unwrap();
+        let pattern = project.pattern_mut(melody).unwrap();
+        pattern.name = "Lead \"hook\"".to_string();
+
+        for (i, pitch) in [60, 64, 67, 72].into_iter().enumerate() {
+            pattern.insert(Note {
+                pitch,
+                velocity: 90 + i as u8,
+                start: i as u32 * 96,
+                length: 90,
+                state: NoteState::Normal,
+            });
+        }
+
+        // Overhangs into a second bar.
+        pattern.insert(Note { pitch: 48, velocity: 127, start: 300, length: 200, state: NoteState::Normal });
+
+        let beat = project.first_pattern(1).unwrap();
+        for step in 0..4 {
+            project.pattern_mut(beat).unwrap()
+                .insert(Note {
+                    pitch: 36,
+                    velocity: 100,
+                    start: step * 96,
+                    length: 24,
+                    state: NoteState::Normal,
+                });
+        }
+
+        let params = SynthParams {
+            osc1: Waveform::Square,
+            osc2: Waveform::Noise,
+            quality: Quality::Naive,
+            pulse_width: 0.3,
+            filter_mode: FilterMode::Bandpass,
+            cutoff: 1234.5,
+            ..SynthParams::default()
+        };
+        let bass = project.add_track("Bass", InstrumentSpec::Synth(params));
+        let riff = project.add_pattern(Pattern::new("Riff", 2));
+        project.add_clip(bass, Clip { pattern: riff, start: 2 * BAR_TICKS });
+        project.add_clip(bass, Clip { pattern: riff, start: 0 });
+
+        project.add_track("Vox", InstrumentSpec::Sampler {
+            path: PathBuf::from("samples/vox.wav"),
+            root: 62,
+        });
+
+        project
+    }
+
+    fn layout() -> LayoutSpec {
+        LayoutSpec::Split {
+            direction: Direction::Horizontal,
+            ratio: 0.5,
+            first: Box::new(LayoutSpec::Window(WindowKind::PianoRoll)),
+            second: Box::new(LayoutSpec::Split {
+                direction: Direction::Vertical,
+                ratio: 0.25,
+                first: Box::new(LayoutSpec::Window(WindowKind::StepSequencer)),
+                second: Box::new(LayoutSpec::Window(WindowKind::Splash)),
+            }),
+        }
+    }
+
+    fn assert_same(a: &Project, b: &Project) {
+        assert_eq!(a.tempo_map(), b.tempo_map());
+        assert_eq!(a.tracks(), b.tracks());
+        assert_eq!(a.patterns, b.patterns);
+        assert_eq!(a.length(), b.length());
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let project = project();
+        let text = to_string(&project, &layout()).unwrap();
+        let (loaded, loaded_layout) = from_str(&text).unwrap();
+
+        assert_same(&project, &loaded);
+        assert_eq!(loaded_layout, layout());
+
+        // Saving what was loaded gives back the very same text.
+        assert_eq!(to_string(&loaded, &loaded_layout).unwrap(), text);
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = std::env::temp_dir().join("daw_round_trip.ron");
+        let project = project();
+
+        save(&path, &project, &layout()).unwrap();
+        let (loaded, loaded_layout) = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_same(&project, &loaded);
+        assert_eq!(loaded_layout, layout());
+    }
+
+    #[test]
+    fn loaded_projects_keep_allocating_fresh_track_ids() {
+        let (mut loaded, _) = from_str(&to_string(&project(), &layout()).unwrap()).unwrap();
+        let id = loaded.add_track("New", InstrumentSpec::DrumKit);
+
+        assert!(project().tracks().iter().all(|track| track.id != id));
+    }
+
+    #[test]
+    fn missing_layout_falls_back_to_the_splash_screen() {
+        let text = to_string(&project(), &layout()).unwrap();
+        let start = text.find("layout:").unwrap();
+        let (_, layout) = from_str(&format!("{})", &text[..start])).unwrap();
+
+        assert_eq!(layout, LayoutSpec::Window(WindowKind::Splash));
+    }
+
+    #[test]
+    fn rejects_newer_formats() {
+        let text = to_string(&project(), &layout()).unwrap()
+            .replacen(&format!("version: {FORMAT_VERSION}"), "version: 999", 1);
+
+        assert!(from_str(&text).is_err());
+    }
+
+    /// `text` with `from` replaced once, which it has to contain.
+    fn replace(text: &str, from: &str, to: &str) -> String {
+        assert!(text.contains(from), "no '{from}' to replace");
+        text.replacen(from, to, 1)
+    }
+
+    /// A saved project with its tempo list swapped for `tempos`.
+    fn with_tempos(tempos: &str) -> String {
+        let text = to_string(&project(), &layout()).unwrap();
+        let start = text.find("tempos: [").unwrap();
+        let end = start + text[start..].find("],").unwrap() + 2;
+
+        format!("{}tempos: [{tempos}],{}", &text[..start], &text[end..])
+    }
+
+    #[test]
+    fn puts_tempos_back_in_range_and_order() {
+        let (loaded, _) = from_str(&with_tempos("(tick: 1536, bpm: 0.0), (tick: 0, bpm: 90.0)")).unwrap();
+
+        assert_eq!(loaded.tempo_map().tempos(), [
+            TempoChange { tick: 0, bpm: 90.0 },
+            TempoChange { tick: 1536, bpm: MIN_TEMPO },
+        ]);
+
+        // Without a change at the start it gets the default one.
+        let (loaded, _) = from_str(&with_tempos("(tick: 96, bpm: 90.0)")).unwrap();
+        assert_eq!(loaded.tempo_map().tempo_at(0), DEFAULT_TEMPO);
+
+        assert!(from_str(&with_tempos("(tick: 0, bpm: NaN)")).is_err());
+    }
+
+    #[test]
+    fn rejects_notes_out_of_midi_range() {
+        let text = to_string(&project(), &layout()).unwrap();
+
+        assert!(from_str(&replace(&text, "pitch: 72,", "pitch: 200,")).is_err());
+        assert!(from_str(&replace(&text, "velocity: 127,", "velocity: 128,")).is_err());
+    }
+
+    #[test]
+    fn rejects_clips_of_missing_patterns() {
+        let mut project = project();
+        project.add_clip(0, Clip { pattern: 99, start: 0 });
+
+        assert!(from_str(&to_string(&project, &layout()).unwrap()).is_err());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(from_str("(version: 1, project: nonsense)").is_err());
+        assert!(load(Path::new("/nonexistent/daw/project.ron")).is_err());
+    }
+}
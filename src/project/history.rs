@@ -0,0 +1,342 @@
+use std::mem;
+
+use crate::audio::transport::LoopRegion;
+use crate::project::{
+    PatternId, Project, TrackId,
+    pattern::Pattern,
+    routing::Bus,
+    tempo::TempoMap,
+    track::Track,
+};
+
+/// Undo steps kept before the oldest are forgotten.
+const MAX_STEPS: usize = 1000;
+
+/// A part of the project that is saved as a whole before it is changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Part {
+    Pattern(PatternId),
+    /// The list of patterns itself, for when one is added.
+    Patterns,
+    Track(TrackId),
+    /// Every track, for when one is added or a change reaches all of them.
+    Tracks,
+    Buses,
+    TempoMap,
+    LoopRegion,
+}
+
+/// What a part looked like at some point.
+#[derive(Clone, Debug, PartialEq)]
+enum Snapshot {
+    Pattern(PatternId, Pattern),
+    Patterns(Vec<Pattern>),
+    Track(TrackId, Box<Track>),
+    Tracks(Vec<Track>),
+    Buses(Vec<Bus>),
+    TempoMap(TempoMap),
+    LoopRegion(Option<LoopRegion>),
+}
+
+impl Snapshot {
+    fn take(part: Part, project: &Project) -> Option<Self> {
+        Some(match part {
+            Part::Pattern(id) => Snapshot::Pattern(id, project.patterns.get(id)?.clone()),
+            Part::Patterns => Snapshot::Patterns(project.patterns.clone()),
+            Part::Track(id) => Snapshot::Track(id, Box::new(project.track(id)?.clone())),
+            Part::Tracks => Snapshot::Tracks(project.tracks.clone()),
+            Part::Buses => Snapshot::Buses(project.buses.clone()),
+            Part::TempoMap => Snapshot::TempoMap(project.tempo_map.clone()),
+            Part::LoopRegion => Snapshot::LoopRegion(project.loop_region),
+        })
+    }
+
+    /// Whether the part still looks like this.
+    fn is_current(&self, project: &Project) -> bool {
+        Snapshot::take(self.part(), project).as_ref() == Some(self)
+    }
+
+    fn part(&self) -> Part {
+        match self {
+            Snapshot::Pattern(id, _) => Part::Pattern(*id),
+            Snapshot::Patterns(_) => Part::Patterns,
+            Snapshot::Track(id, _) => Part::Track(*id),
+            Snapshot::Tracks(_) => Part::Tracks,
+            Snapshot::Buses(_) => Part::Buses,
+            Snapshot::TempoMap(_) => Part::TempoMap,
+            Snapshot::LoopRegion(_) => Part::LoopRegion,
+        }
+    }
+
+    /// Put the snapshot into the project and keep what was there instead,
+    /// so that swapping again takes it back.
+    fn swap(&mut self, project: &mut Project) {
+        match self {
+            Snapshot::Pattern(id, pattern) => {
+                if let Some(current) = project.patterns.get_mut(*id) {
+                    mem::swap(current, pattern);
+                }
+            }
+
+            Snapshot::Track(id, track) => {
+                if let Some(current) = project.tracks.iter_mut().find(|current| current.id == *id) {
+                    mem::swap(current, track.as_mut());
+                }
+            }
+
+            Snapshot::Patterns(patterns) => mem::swap(&mut project.patterns, patterns),
+            Snapshot::Tracks(tracks) => mem::swap(&mut project.tracks, tracks),
+            Snapshot::Buses(buses) => mem::swap(&mut project.buses, buses),
+            Snapshot::TempoMap(tempo_map) => mem::swap(&mut project.tempo_map, tempo_map),
+            Snapshot::LoopRegion(region) => mem::swap(&mut project.loop_region, region),
+        }
+    }
+}
+
+/// One undo step: every part that it changed.
+struct Edit {
+    snapshots: Vec<Snapshot>,
+}
+
+impl Edit {
+    /// Swapping in reverse order undoes the step, and swapping in order
+    /// afterwards redoes it.
+    fn undo(&mut self, project: &mut Project) {
+        for snapshot in self.snapshots.iter_mut().rev() {
+            snapshot.swap(project);
+        }
+    }
+
+    fn redo(&mut self, project: &mut Project) {
+        for snapshot in self.snapshots.iter_mut() {
+            snapshot.swap(project);
+        }
+    }
+}
+
+/// Every change made to a project, as steps that can be undone and redone.
+///
+/// Changes are collected until the next commit, so that however many
+/// notes a single command touches it comes back as one step.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /// Parts changed since the last commit, as they were before.
+    pending: Vec<Snapshot>,
+    /// Parts changed since the revision was last settled, as they were
+    /// then.
+    touched: Vec<Snapshot>,
+}
+
+fn is_recorded(snapshots: &[Snapshot], part: Part) -> bool {
+    snapshots.iter().any(|snapshot| snapshot.part() == part)
+}
+
+impl Project {
+    /// Called before a part of the project changes. Only the first change
+    /// to a part between commits needs to be kept.
+    pub(super) fn record(&mut self, part: Part) {
+        let pending = !is_recorded(&self.history.pending, part);
+        let touched = !is_recorded(&self.history.touched, part);
+
+        if !(pending || touched) {
+            return;
+        }
+
+        if let Some(snapshot) = Snapshot::take(part, self) {
+            if touched {
+                self.history.touched.push(snapshot.clone());
+            }
+            if pending {
+                self.history.pending.push(snapshot);
+            }
+        }
+    }
+
+    /// Bump the revision if anything changed since the last time, so that
+    /// looking at a part mutably without changing it goes unnoticed.
+    pub fn settle(&mut self) {
+        let touched = mem::take(&mut self.history.touched);
+
+        if touched.iter().any(|snapshot| !snapshot.is_current(self)) {
+            self.revision += 1;
+        }
+    }
+
+    /// Close the current undo step. Parts that were touched but ended up
+    /// as they were are left out, and a step without any is dropped.
+    pub fn commit(&mut self) {
+        self.settle();
+
+        let mut pending = mem::take(&mut self.history.pending);
+        pending.retain(|snapshot| !snapshot.is_current(self));
+
+        if pending.is_empty() {
+            return;
+        }
+
+        let history = &mut self.history;
+        history.undo.push(Edit { snapshots: pending });
+        history.redo.clear();
+
+        if history.undo.len() > MAX_STEPS {
+            history.undo.remove(0);
+        }
+    }
+
+    /// Undo up to `count` steps, returning how many there were.
+    pub fn undo(&mut self, count: usize) -> usize {
+        self.commit();
+
+        let mut done = 0;
+        while done < count && let Some(mut edit) = self.history.undo.pop() {
+            edit.undo(self);
+            self.history.redo.push(edit);
+            done += 1;
+        }
+
+        if done > 0 {
+            self.revision += 1;
+        }
+
+        done
+    }
+
+    /// Redo up to `count` undone steps, returning how many there were.
+    pub fn redo(&mut self, count: usize) -> usize {
+        self.commit();
+
+        let mut done = 0;
+        while done < count && let Some(mut edit) = self.history.redo.pop() {
+            edit.redo(self);
+            self.history.undo.push(edit);
+            done += 1;
+        }
+
+        if done > 0 {
+            self.revision += 1;
+        }
+
+        done
+    }
+
+    /// Forget every step, for a project that starts from here.
+    pub fn clear_history(&mut self) {
+        self.history = History::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::{
+        Project,
+        pattern::{Note, NoteState},
+        track::InstrumentSpec,
+    };
+
+    fn note(start: u32) -> Note {
+        Note { pitch: 60, velocity: 100, start, length: 24, state: NoteState::Normal }
+    }
+
+    fn notes(project: &Project) -> Vec<Note> {
+        project.patterns[0].notes().to_vec()
+    }
+
+    #[test]
+    fn undoes_and_redoes_steps_with_counts() {
+        let mut project = Project::with_default_tracks();
+
+        for start in [0, 96, 192] {
+            project.pattern_mut(0).unwrap().insert(note(start));
+            project.commit();
+        }
+
+        assert_eq!(project.undo(2), 2);
+        assert_eq!(notes(&project), [note(0)]);
+
+        assert_eq!(project.redo(1), 1);
+        assert_eq!(notes(&project), [note(0), note(96)]);
+
+        // Nothing older than the fresh project.
+        assert_eq!(project.undo(10), 2);
+        assert!(notes(&project).is_empty());
+        assert_eq!(project.tracks().len(), 2);
+
+        assert_eq!(project.redo(10), 3);
+        assert_eq!(notes(&project), [note(0), note(96), note(192)]);
+    }
+
+    #[test]
+    fn groups_everything_between_commits() {
+        let mut project = Project::with_default_tracks();
+
+        for start in [0, 96, 192] {
+            project.pattern_mut(0).unwrap().insert(note(start));
+        }
+        project.tempo_map_mut().set_tempo(0, 90.0);
+        let track = project.add_track("Bass", InstrumentSpec::DrumKit);
+        project.commit();
+
+        assert_eq!(project.undo(1), 1);
+        assert!(notes(&project).is_empty());
+        assert_eq!(project.tempo_map().tempo_at(0), 120.0);
+        assert!(project.track(track).is_none());
+
+        project.redo(1);
+        assert_eq!(notes(&project).len(), 3);
+        assert_eq!(project.tempo_map().tempo_at(0), 90.0);
+        assert!(project.track(track).is_some());
+    }
+
+    #[test]
+    fn skips_steps_that_changed_nothing() {
+        let mut project = Project::with_default_tracks();
+        project.pattern_mut(0).unwrap().insert(note(0));
+        project.commit();
+
+        let revision = project.revision();
+
+        // Looked at mutably, but left alone.
+        project.pattern_mut(1).unwrap();
+        project.track_mut(99);
+        project.commit();
+
+        assert_eq!(project.revision(), revision);
+        assert_eq!(project.undo(5), 1);
+    }
+
+    #[test]
+    fn a_new_change_forgets_what_was_undone() {
+        let mut project = Project::with_default_tracks();
+        project.pattern_mut(0).unwrap().insert(note(0));
+        project.commit();
+        project.undo(1);
+
+        project.pattern_mut(0).unwrap().insert(note(96));
+        project.commit();
+
+        assert_eq!(project.redo(1), 0);
+        assert_eq!(notes(&project), [note(96)]);
+    }
+
+    #[test]
+    fn track_changes_undo_alongside_new_tracks() {
+        let mut project = Project::with_default_tracks();
+        project.track_mut(0).unwrap().muted = true;
+        let added = project.add_track("Bass", InstrumentSpec::DrumKit);
+        project.track_mut(1).unwrap().gain = -6.0;
+        project.track_mut(added).unwrap().solo = true;
+        project.commit();
+
+        assert_eq!(project.undo(1), 1);
+        assert!(!project.track(0).unwrap().muted);
+        assert_eq!(project.track(1).unwrap().gain, 0.0);
+        assert!(project.track(added).is_none());
+
+        assert_eq!(project.redo(1), 1);
+        assert!(project.track(0).unwrap().muted);
+        assert_eq!(project.track(1).unwrap().gain, -6.0);
+        assert!(project.track(added).unwrap().solo);
+    }
+}
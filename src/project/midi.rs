@@ -0,0 +1,393 @@
+use std::{collections::{HashMap, VecDeque}, fs, path::Path};
+
+use color_eyre::eyre::{eyre, Result};
+use midly::{
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+    num::{u4, u7, u15, u24, u28},
+};
+
+use crate::instrument::synth::SynthParams;
+use crate::project::{
+    PPQ, Project, TrackId,
+    pattern::{Note, NoteState, Pattern},
+    tempo::{TempoMap, TimeSignature},
+    track::{Clip, InstrumentSpec},
+};
+
+/// Channel 10 in General MIDI, counting from zero.
+const DRUM_CHANNEL: u8 = 9;
+const MICROS_PER_MINUTE: f64 = 60_000_000.0;
+
+/// Add every part of a MIDI file to the project as a new track with a
+/// single pattern, and take over the file's tempo map.
+pub fn import(project: &mut Project, path: &Path) -> Result<Vec<TrackId>> {
+    let bytes = fs::read(path)
+        .map_err(|err| eyre!("could not read '{}': {err}", path.display()))?;
+
+    from_bytes(project, &bytes)
+        .map_err(|err| eyre!("could not import '{}': {err}", path.display()))
+}
+
+/// Write the song out as a format 1 file: a conductor track with the tempo
+/// map, then one track per project track.
+pub fn export(project: &Project, path: &Path) -> Result<()> {
+    to_smf(project)?.save(path)
+        .map_err(|err| eyre!("could not write '{}': {err}", path.display()))
+}
+
+/// Notes of one channel of one file track.
+struct Part {
+    name: String,
+    channel: u8,
+    notes: Vec<Note>,
+}
+
+fn from_bytes(project: &mut Project, bytes: &[u8]) -> Result<Vec<TrackId>> {
+    let smf = Smf::parse(bytes)?;
+
+    let Timing::Metrical(ppq) = smf.header.timing else {
+        return Err(eyre!("timecode based files are not supported"));
+    };
+    let ppq = ppq.as_int().max(1) as u64;
+    let rescale = |tick: u64| (tick * PPQ as u64 + ppq / 2) / ppq;
+
+    let mut tempo_map = TempoMap::default();
+    let mut parts = Vec::new();
+
+    for (index, events) in smf.tracks.iter().enumerate() {
+        let mut name = None;
+        let mut track_parts: Vec<Part> = Vec::new();
+        // Notes still held, oldest first, by channel and key.
+        let mut held: HashMap<(u8, u8), VecDeque<(u32, u8)>> = HashMap::new();
+        let mut tick = 0;
+
+        for event in events {
+            tick += event.delta.as_int() as u64;
+            let now = rescale(tick) as u32;
+
+            match event.kind {
+                TrackEventKind::Meta(MetaMessage::TrackName(bytes)) if name.is_none() => {
+                    name = Some(String::from_utf8_lossy(bytes).trim().to_string());
+                }
+
+                TrackEventKind::Meta(MetaMessage::Tempo(micros)) => {
+                    // Microseconds per beat can't hold every tempo exactly,
+                    // so round off what the conversion adds.
+                    let bpm = MICROS_PER_MINUTE / micros.as_int().max(1) as f64;
+                    tempo_map.set_tempo(now, (bpm * 1000.0).round() / 1000.0);
+                }
+
+                TrackEventKind::Meta(MetaMessage::TimeSignature(numerator, power, _, _)) => {
+                    let signature = TimeSignature {
+                        numerator,
+                        denominator: 1 << power.min(6),
+                    };
+                    tempo_map.set_meter(now, signature);
+                }
+
+                TrackEventKind::Midi { channel, message } => {
+                    let channel = channel.as_int();
+
+                    match message {
+                        MidiMessage::NoteOn { key, vel } if vel > 0 => {
+                            held.entry((channel, key.as_int()))
+                                .or_default()
+                                .push_back((now, vel.as_int()));
+                        }
+
+                        MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                            let pitch = key.as_int();
+                            let Some((start, velocity)) = held.get_mut(&(channel, pitch))
+                                .and_then(VecDeque::pop_front) else { continue };
+
+                            part(&mut track_parts, channel).notes.push(Note {
+                                pitch,
+                                velocity,
+                                start,
+                                length: (now - start).max(1),
+                                state: NoteState::Normal,
+                            });
+                        }
+
+                        _ => (),
+                    }
+                }
+
+                _ => (),
+            }
+        }
+
+        // Anything never released runs to the end of the track.
+        let end = rescale(tick) as u32;
+        for ((channel, pitch), notes) in held {
+            for (start, velocity) in notes {
+                part(&mut track_parts, channel).notes.push(Note {
+                    pitch,
+                    velocity,
+                    start,
+                    length: (end - start).max(1),
+                    state: NoteState::Normal,
+                });
+            }
+        }
+
+        let name = name.filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("MIDI {}", index + 1));
+        let split = track_parts.len() > 1;
+
+        for mut part in track_parts {
+            part.name = match split {
+                true => format!("{name} ch{}", part.channel + 1),
+                false => name.clone(),
+            };
+            parts.push(part);
+        }
+    }
+
+    *project.tempo_map_mut() = tempo_map;
+
+    let mut tracks = Vec::new();
+
+    for part in parts {
+        let instrument = match part.channel {
+            DRUM_CHANNEL => InstrumentSpec::DrumKit,
+            _ => InstrumentSpec::Synth(SynthParams::default()),
+        };
+
+        let mut pattern = Pattern::new(&part.name, 1);
+        for note in part.notes {
+            pattern.insert(note);
+        }
+
+        let track = project.add_track(&part.name, instrument);
+        let pattern = project.add_pattern(pattern);
+        project.add_clip(track, Clip { pattern, start: 0 });
+        tracks.push(track);
+    }
+
+    Ok(tracks)
+}
+
+/// The part of a file track playing on a channel.
+fn part(parts: &mut Vec<Part>, channel: u8) -> &mut Part {
+    let index = match parts.iter().position(|part| part.channel == channel) {
+        Some(index) => index,
+        None => {
+            parts.push(Part { name: String::new(), channel, notes: Vec::new() });
+            parts.len() - 1
+        }
+    };
+
+    &mut parts[index]
+}
+
+fn to_smf(project: &Project) -> Result<Smf<'_>> {
+    let mut smf = Smf::new(Header::new(Format::Parallel, Timing::Metrical(u15::new(PPQ as u16))));
+
+    let tempo_map = project.tempo_map();
+    let mut conductor = Vec::new();
+
+    for change in tempo_map.tempos() {
+        let micros = (MICROS_PER_MINUTE / change.bpm).round() as u32;
+        let micros = u24::try_from(micros)
+            .ok_or_else(|| eyre!("tempo of {} BPM is too slow for a MIDI file", change.bpm))?;
+        conductor.push((change.tick, TrackEventKind::Meta(MetaMessage::Tempo(micros))));
+    }
+
+    for change in tempo_map.meters() {
+        let TimeSignature { numerator, denominator } = change.signature;
+        let power = denominator.max(1).trailing_zeros() as u8;
+        // 24 clocks per metronome click and 8 32nds per quarter are the
+        // usual values.
+        let meta = MetaMessage::TimeSignature(numerator, power, 24, 8);
+        conductor.push((change.tick, TrackEventKind::Meta(meta)));
+    }
+
+    conductor.sort_by_key(|(tick, _)| *tick);
+    smf.tracks.push(encode(conductor)?);
+
+    let mut melodic = 0;
+
+    for track in project.tracks() {
+        let channel = match track.instrument {
+            InstrumentSpec::DrumKit => DRUM_CHANNEL,
+            _ => {
+                let channel = melodic % 15;
+                melodic += 1;
+                if channel >= DRUM_CHANNEL { channel + 1 } else { channel }
+            }
+        };
+        let channel = u4::new(channel);
+
+        let mut events = vec![(0, TrackEventKind::Meta(MetaMessage::TrackName(track.name.as_bytes())))];
+
+        for note in project.song_notes(track) {
+            let key = u7::new(note.pitch.min(127));
+            let on = MidiMessage::NoteOn { key, vel: u7::new(note.velocity.clamp(1, 127)) };
+            let off = MidiMessage::NoteOff { key, vel: u7::new(0) };
+
+            events.push((note.start, TrackEventKind::Midi { channel, message: on }));
+            events.push((note.start + note.length.max(1), TrackEventKind::Midi { channel, message: off }));
+        }
+
+        // Note-offs go first on the same tick so repeated notes stay apart.
+        events.sort_by_key(|(tick, kind)| (*tick, matches!(kind,
+            TrackEventKind::Midi { message: MidiMessage::NoteOn { .. }, .. })));
+
+        smf.tracks.push(encode(events)?);
+    }
+
+    Ok(smf)
+}
+
+/// Turn time-ordered events at absolute ticks into a track.
+fn encode(events: Vec<(u32, TrackEventKind<'_>)>) -> Result<Vec<TrackEvent<'_>>> {
+    let mut previous = 0;
+    let mut track = events.into_iter()
+        .map(|(tick, kind)| {
+            let delta = u28::try_from(tick - previous)
+                .ok_or_else(|| eyre!("events {} ticks apart are too far for a MIDI file", tick - previous))?;
+            previous = tick;
+            Ok(TrackEvent { delta, kind })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    track.push(TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::EndOfTrack) });
+    Ok(track)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{BAR_TICKS, tempo::MIN_TEMPO};
+
+    fn to_bytes(project: &Project) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        to_smf(project).unwrap().write_std(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn notes(project: &Project, track: TrackId) -> Vec<Note> {
+        let pattern = project.first_pattern(track).unwrap();
+        project.pattern(pattern).unwrap().notes().to_vec()
+    }
+
+    #[test]
+    fn round_trips_notes_and_tempo_map() {
+        let mut project = Project::with_default_tracks();
+        project.tempo_map_mut().set_tempo(0, 93.0);
+        project.tempo_map_mut().set_tempo(4 * BAR_TICKS, 140.0);
+        project.tempo_map_mut().set_meter(2 * BAR_TICKS, TimeSignature { numerator: 7, denominator: 8 });
+
+        let melody = project.first_pattern(0).unwrap();
+        for (i, pitch) in [60, 62, 60, 67].into_iter().enumerate() {
+            project.pattern_mut(melody).unwrap().insert(Note {
+                pitch,
+                velocity: 40 + i as u8 * 20,
+                start: i as u32 * 72,
+                length: 13 + i as u32 * 50,
+                state: NoteState::Normal,
+            });
+        }
+
+        let beat = project.first_pattern(1).unwrap();
+        for step in 0..16 {
+            project.pattern_mut(beat).unwrap()
+                .insert(Note {
+                    pitch: 42,
+                    velocity: 100 - step as u8,
+                    start: step * 24,
+                    length: 24,
+                    state: NoteState::Normal,
+                });
+        }
+
+        let mut imported = Project::new();
+        let tracks = from_bytes(&mut imported, &to_bytes(&project)).unwrap();
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(imported.tempo_map(), project.tempo_map());
+        assert_eq!(imported.track(tracks[0]).unwrap().name, "Synth");
+        assert_eq!(imported.track(tracks[1]).unwrap().instrument, InstrumentSpec::DrumKit);
+        assert_eq!(notes(&imported, tracks[0]), notes(&project, 0));
+        assert_eq!(notes(&imported, tracks[1]), notes(&project, 1));
+    }
+
+    #[test]
+    fn tempos_stay_within_what_files_hold() {
+        let mut project = Project::with_default_tracks();
+        project.tempo_map_mut().set_tempo(0, 1.0);
+
+        let mut imported = Project::new();
+        from_bytes(&mut imported, &to_bytes(&project)).unwrap();
+        assert_eq!(imported.tempo_map().tempo_at(0), MIN_TEMPO);
+    }
+
+    #[test]
+    fn clips_are_flattened_onto_the_timeline() {
+        let mut project = Project::new();
+        let track = project.add_track("Bass", InstrumentSpec::Synth(SynthParams::default()));
+        let mut pattern = Pattern::new("Riff", 1);
+        pattern.insert(Note { pitch: 40, velocity: 90, start: 0, length: 48, state: NoteState::Normal });
+        let pattern = project.add_pattern(pattern);
+        project.add_clip(track, Clip { pattern, start: 0 });
+        project.add_clip(track, Clip { pattern, start: 3 * BAR_TICKS });
+
+        let mut imported = Project::new();
+        let tracks = from_bytes(&mut imported, &to_bytes(&project)).unwrap();
+        let starts: Vec<_> = notes(&imported, tracks[0]).iter().map(|note| note.start).collect();
+
+        assert_eq!(starts, [0, 3 * BAR_TICKS]);
+    }
+
+    #[test]
+    fn imports_format_0_split_by_channel() {
+        let event = |delta, channel, message| TrackEvent {
+            delta: u28::new(delta),
+            kind: TrackEventKind::Midi { channel: u4::new(channel), message },
+        };
+        let on = |key, vel| MidiMessage::NoteOn { key: u7::new(key), vel: u7::new(vel) };
+
+        // 480 ticks per quarter: an eighth note on channel 1, a quarter on
+        // the drum channel, the latter ended by a zero velocity note-on.
+        let mut smf = Smf::new(Header::new(Format::SingleTrack, Timing::Metrical(u15::new(480))));
+        smf.tracks.push(vec![
+            TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(400_000))) },
+            event(0, 0, on(64, 77)),
+            event(0, 9, on(36, 120)),
+            event(240, 0, MidiMessage::NoteOff { key: u7::new(64), vel: u7::new(0) }),
+            event(240, 9, on(36, 0)),
+            TrackEvent { delta: u28::new(0), kind: TrackEventKind::Meta(MetaMessage::EndOfTrack) },
+        ]);
+
+        let mut bytes = Vec::new();
+        smf.write_std(&mut bytes).unwrap();
+
+        let mut project = Project::new();
+        let tracks = from_bytes(&mut project, &bytes).unwrap();
+
+        assert_eq!(project.tempo_map().tempo_at(0), 150.0);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(notes(&project, tracks[0]), [Note {
+            pitch: 64,
+            velocity: 77,
+            start: 0,
+            length: PPQ / 2,
+            state: NoteState::Normal,
+        }]);
+        assert_eq!(notes(&project, tracks[1]), [Note {
+            pitch: 36,
+            velocity: 120,
+            start: 0,
+            length: PPQ,
+            state: NoteState::Normal,
+        }]);
+        assert_eq!(project.track(tracks[1]).unwrap().instrument, InstrumentSpec::DrumKit);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(from_bytes(&mut Project::new(), b"MThd nonsense").is_err());
+        assert!(import(&mut Project::new(), Path::new("/nonexistent/daw/song.mid")).is_err());
+    }
+}
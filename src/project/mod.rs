@@ -0,0 +1,211 @@
+pub mod automation;
+pub mod file;
+pub mod history;
+pub mod midi;
+pub mod pattern;
+pub mod playback;
+pub mod quantize;
+pub mod routing;
+pub mod tempo;
+pub mod track;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::transport::LoopRegion;
+
+use crate::instrument::synth::SynthParams;
+
+use history::{History, Part};
+use pattern::Pattern;
+use routing::Bus;
+use tempo::TempoMap;
+use track::{Clip, InstrumentSpec, Track};
+
+/// Ticks per quarter note.
+pub const PPQ: u32 = 96;
+/// A bar of 4/4.
+pub const BAR_TICKS: u32 = 4 * PPQ;
+
+pub type TrackId = usize;
+pub type PatternId = usize;
+
+/// The song: everything that gets saved, drawn by the windows and played
+/// by the audio engine.
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    #[serde(default)]
+    tempo_map: TempoMap,
+    #[serde(default)]
+    loop_region: Option<LoopRegion>,
+    tracks: Vec<Track>,
+    #[serde(default = "routing::default_buses")]
+    buses: Vec<Bus>,
+    patterns: Vec<Pattern>,
+    /// Buses take their ids from here too.
+    next_track_id: TrackId,
+    /// Bumped whenever changes settle so that views of the project know to
+    /// catch up.
+    #[serde(skip)]
+    revision: u64,
+    #[serde(skip)]
+    history: History,
+}
+
+impl Project {
+    pub fn new() -> Self {
+        Self {
+            tempo_map: TempoMap::default(),
+            loop_region: None,
+            tracks: Vec::new(),
+            buses: routing::default_buses(),
+            patterns: Vec::new(),
+            next_track_id: 0,
+            revision: 0,
+            history: History::default(),
+        }
+    }
+
+    /// What a new session starts with: a synth and a drum kit, each with a
+    /// bar long pattern.
+    pub fn with_default_tracks() -> Self {
+        let mut project = Self::new();
+
+        for (name, instrument, pattern) in [
+            ("Synth", InstrumentSpec::Synth(SynthParams::default()), "Melody"),
+            ("Drums", InstrumentSpec::DrumKit, "Beat"),
+        ] {
+            let track = project.add_track(name, instrument);
+            let pattern = project.add_pattern(Pattern::new(pattern, 1));
+            project.add_clip(track, Clip { pattern, start: 0 });
+        }
+
+        project.clear_history();
+        project
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn tempo_map(&self) -> &TempoMap {
+        &self.tempo_map
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn tempo_map_mut(&mut self) -> &mut TempoMap {
+        self.record(Part::TempoMap);
+        &mut self.tempo_map
+    }
+
+    pub fn loop_region(&self) -> Option<LoopRegion> {
+        self.loop_region
+    }
+
+    pub fn set_loop_region(&mut self, region: Option<LoopRegion>) {
+        self.record(Part::LoopRegion);
+        self.loop_region = region;
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn track(&self, id: TrackId) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.id == id)
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn track_mut(&mut self, id: TrackId) -> Option<&mut Track> {
+        self.record(Part::Track(id));
+        self.tracks.iter_mut().find(|track| track.id == id)
+    }
+
+    pub fn add_track(&mut self, name: &str, instrument: InstrumentSpec) -> TrackId {
+        self.record(Part::Tracks);
+        let id = self.next_track_id;
+        self.next_track_id += 1;
+
+        self.tracks.push(Track::new(id, name, instrument));
+        id
+    }
+
+    pub fn pattern(&self, id: PatternId) -> Option<&Pattern> {
+        self.patterns.get(id)
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn pattern_mut(&mut self, id: PatternId) -> Option<&mut Pattern> {
+        self.record(Part::Pattern(id));
+        self.patterns.get_mut(id)
+    }
+
+    pub fn add_pattern(&mut self, pattern: Pattern) -> PatternId {
+        self.record(Part::Patterns);
+        self.patterns.push(pattern);
+        self.patterns.len() - 1
+    }
+
+    pub fn add_clip(&mut self, track: TrackId, clip: Clip) {
+        if let Some(track) = self.track_mut(track) {
+            track.clips.push(clip);
+            track.clips.sort_by_key(|clip| clip.start);
+        }
+    }
+
+    /// The pattern in the first clip of a track, which is what the editing
+    /// windows open.
+    pub fn first_pattern(&self, track: TrackId) -> Option<PatternId> {
+        self.track(track)?.clips.first().map(|clip| clip.pattern)
+    }
+
+    /// The first track whose instrument matches.
+    pub fn find_track<F>(&self, matches: F) -> Option<TrackId>
+    where F: Fn(&InstrumentSpec) -> bool {
+        self.tracks.iter()
+            .find(|track| matches(&track.instrument))
+            .map(|track| track.id)
+    }
+
+    /// Song tick of the first clip of a pattern, which is where the
+    /// editing windows take its place in the song to be.
+    pub fn pattern_start(&self, pattern: PatternId) -> Option<u32> {
+        self.tracks.iter()
+            .flat_map(|track| track.clips.iter())
+            .filter(|clip| clip.pattern == pattern)
+            .map(|clip| clip.start)
+            .min()
+    }
+
+    /// The first track with a clip of a pattern.
+    pub fn pattern_track(&self, pattern: PatternId) -> Option<&Track> {
+        self.tracks.iter()
+            .find(|track| track.clips.iter().any(|clip| clip.pattern == pattern))
+    }
+
+    /// Where a song tick falls inside a pattern, going by the first clip
+    /// of it that is playing at that tick.
+    pub fn pattern_tick(&self, pattern: PatternId, tick: u32) -> Option<u32> {
+        let length = self.pattern(pattern)?.length();
+
+        self.tracks.iter()
+            .flat_map(|track| track.clips.iter())
+            .filter(|clip| clip.pattern == pattern)
+            .find(|clip| (clip.start..clip.start + length).contains(&tick))
+            .map(|clip| tick - clip.start)
+    }
+
+    /// Ticks until every clip has finished, rounded up to whole bars of
+    /// whatever time signature is in effect there.
+    pub fn length(&self) -> u32 {
+        let end = self.tracks.iter()
+            .flat_map(|track| track.clips.iter())
+            .filter_map(|clip| Some(clip.start + self.pattern(clip.pattern)?.length()))
+            .max()
+            .unwrap_or(0);
+
+        let bar = self.tempo_map.position(end).bar;
+        let bar = if self.tempo_map.bar_tick(bar) == Some(end) { bar } else { bar + 1 };
+
+        self.tempo_map.bar_tick(bar.max(1)).unwrap_or(u32::MAX)
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::{Range, RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::BAR_TICKS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    /// MIDI pitch, 60 is middle C.
+    pub pitch: u8,
+    pub velocity: u8,
+    /// Ticks from the start of the pattern.
+    pub start: u32,
+    pub length: u32,
+    #[serde(default)]
+    pub state: NoteState,
+}
+
+/// Notes can be muted, or soloed so that only the soloed notes of their
+/// pattern play.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoteState {
+    #[default]
+    Normal,
+    Muted,
+    Solo,
+}
+
+impl Note {
+    pub fn end(&self) -> u32 {
+        self.start + self.length
+    }
+
+    /// Whether the note sounds anywhere in `ticks` on a pitch.
+    pub fn covers(&self, pitch: u8, ticks: &Range<u32>) -> bool {
+        self.pitch == pitch && self.start < ticks.end && ticks.start < self.end()
+    }
+
+    /// Whether the note is heard, given whether any note of its pattern
+    /// is soloed.
+    pub fn is_audible(&self, soloing: bool) -> bool {
+        match self.state {
+            NoteState::Normal => !soloing,
+            NoteState::Muted => false,
+            NoteState::Solo => true,
+        }
+    }
+
+    fn overlaps(&self, other: &Note) -> bool {
+        self.pitch == other.pitch && self.start < other.end() && other.start < self.end()
+    }
+}
+
+/// Notes that start within `ticks` on a pitch within `pitches`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteRange {
+    pub ticks: Range<u32>,
+    pub pitches: RangeInclusive<u8>,
+}
+
+impl NoteRange {
+    pub fn contains(&self, note: &Note) -> bool {
+        self.ticks.contains(&note.start) && self.pitches.contains(&note.pitch)
+    }
+}
+
+/// A reusable run of notes. Clips place patterns on tracks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub name: String,
+    /// Length in ticks, always a whole number of bars.
+    length: u32,
+    /// Kept ordered by start, then pitch.
+    notes: Vec<Note>,
+}
+
+impl Pattern {
+    pub fn new(name: &str, bars: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            length: bars.max(1) * BAR_TICKS,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// Any note soloed, which leaves only the soloed ones to be heard.
+    pub fn is_soloing(&self) -> bool {
+        self.notes.iter().any(|note| note.state == NoteState::Solo)
+    }
+
+    /// The first note sounding anywhere in `ticks` on a pitch, so that
+    /// notes off the grid are found from any column they are in.
+    pub fn note_at(&self, pitch: u8, ticks: Range<u32>) -> Option<usize> {
+        self.notes.iter().position(|note| note.covers(pitch, &ticks))
+    }
+
+    /// Add a note, replacing whatever it overlaps on the same pitch. The
+    /// pattern grows by whole bars to fit it.
+    pub fn insert(&mut self, note: Note) {
+        self.notes.retain(|other| !other.overlaps(&note));
+
+        let index = self.notes
+            .partition_point(|other| (other.start, other.pitch) < (note.start, note.pitch));
+        self.notes.insert(index, note);
+
+        self.length = self.length.max(note.end().div_ceil(BAR_TICKS) * BAR_TICKS);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Note {
+        self.notes.remove(index)
+    }
+
+    pub fn notes_in<'a>(&'a self, range: &'a NoteRange) -> impl Iterator<Item = &'a Note> {
+        self.notes.iter().filter(|note| range.contains(note))
+    }
+
+    /// Take out every note in the range, in order.
+    pub fn remove_range(&mut self, range: &NoteRange) -> Vec<Note> {
+        let (removed, kept) = self.notes.iter().partition(|note| range.contains(note));
+        self.notes = kept;
+        removed
+    }
+
+    /// Change every note in the range, putting them back in order
+    /// afterwards. Returns how many there were.
+    pub fn transform(&mut self, range: &NoteRange, mut change: impl FnMut(&mut Note)) -> usize {
+        let notes = self.remove_range(range);
+
+        for mut note in notes.iter().copied() {
+            change(&mut note);
+            self.insert(note);
+        }
+
+        notes.len()
+    }
+
+    /// Change the length of a note, at least one tick.
+    pub fn resize(&mut self, index: usize, length: u32) {
+        let mut note = self.remove(index);
+        note.length = length.max(1);
+        self.insert(note);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_notes_anywhere_in_the_ticks() {
+        let mut pattern = Pattern::new("Off grid", 1);
+        pattern.insert(Note { pitch: 60, velocity: 100, start: 30, length: 12, state: NoteState::Normal });
+
+        // Starts and ends inside the second 16th.
+        assert_eq!(pattern.note_at(60, 24..48), Some(0));
+        assert_eq!(pattern.note_at(60, 0..24), None);
+        assert_eq!(pattern.note_at(60, 48..72), None);
+        assert_eq!(pattern.note_at(61, 24..48), None);
+    }
+}
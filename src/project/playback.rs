@@ -0,0 +1,451 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+
+use crate::audio::{
+    AudioCommand, AudioEngine,
+    automation::{Automation, Destination},
+    graph::{Graph, NodeMessage, Routing},
+    mixer::{BusNode, Channel, Insert, LevelMeter, Meters, Strip},
+    sequencer::{Sequence, SequencedNote},
+    track::InstrumentTrack,
+    transport::{LoopRegion, TransportCommand},
+};
+use crate::effect::EffectSpec;
+use crate::instrument::sampler::Sampler;
+use crate::project::{
+    Project, TrackId,
+    routing::Bus,
+    tempo::TempoMap,
+    track::{InstrumentSpec, Track},
+};
+
+impl Project {
+    /// Whether a track is heard: it is not muted, and either nothing is
+    /// soloed or it is soloed or solo safe.
+    pub fn is_audible(&self, track: &Track) -> bool {
+        let soloing = self.tracks.iter().any(|track| track.solo);
+        !track.muted && (!soloing || track.solo || track.solo_safe)
+    }
+
+    /// Solo or unsolo a track. An exclusive solo unsolos every other
+    /// track, or the track itself when it was the only one soloed.
+    /// Returns whether the track is soloed afterwards.
+    pub fn toggle_solo(&mut self, id: TrackId, exclusive: bool) -> Option<bool> {
+        let track = self.track(id)?;
+        let alone = self.tracks.iter().all(|other| other.solo == (other.id == id));
+        let solo = if exclusive { !(track.solo && alone) } else { !track.solo };
+
+        let others: Vec<TrackId> = self.tracks.iter()
+            .filter(|other| exclusive && other.id != id && other.solo)
+            .map(|other| other.id)
+            .collect();
+
+        for other in others {
+            self.track_mut(other)?.solo = false;
+        }
+
+        self.track_mut(id)?.solo = solo;
+        Some(solo)
+    }
+
+    /// Every audible note of a track's clips on the song timeline, cut off
+    /// where their pattern ends.
+    pub fn song_notes<'a>(&'a self, track: &'a Track) -> impl Iterator<Item = SequencedNote> + 'a {
+        track.clips.iter()
+            .filter_map(|clip| Some((clip, self.pattern(clip.pattern)?)))
+            .flat_map(|(clip, pattern)| {
+                let soloing = pattern.is_soloing();
+
+                pattern.notes().iter()
+                    .filter(move |note| note.start < pattern.length() && note.is_audible(soloing))
+                    .map(|note| SequencedNote {
+                        pitch: note.pitch,
+                        velocity: note.velocity,
+                        start: clip.start + note.start,
+                        length: note.length.min(pattern.length() - note.start),
+                    })
+            })
+    }
+
+    /// A track's notes for the audio thread, none when it is muted or
+    /// soloed out. The transport takes care of looping, so the sequence
+    /// plays through once.
+    pub fn sequence(&self, track: &Track) -> Sequence {
+        match self.is_audible(track) {
+            true => Sequence::new(self.song_notes(track), None),
+            false => Sequence::default(),
+        }
+    }
+
+    /// A fresh audio channel for a track, metered into `meter`.
+    /// Instruments that fail to load are replaced by a silent one so that
+    /// the track is still there.
+    fn build_channel(
+        &self,
+        track: &Track,
+        sample_rate: u32,
+        meter: Arc<LevelMeter>,
+    ) -> (Box<Channel>, Result<()>) {
+        let (instrument, result) = match track.instrument.build(sample_rate as f64) {
+            Ok(instrument) => (instrument, Ok(())),
+            Err(err) => (
+                Box::new(Sampler::new(Vec::new(), 1, sample_rate as f64)) as _,
+                Err(err.wrap_err(format!("track '{}'", track.name))),
+            ),
+        };
+
+        let node = Box::new(InstrumentTrack::new(instrument, self.sequence(track)));
+        let inserts = build_inserts(&track.inserts, sample_rate);
+        let automation = Box::new(self.automation_for(track.id));
+        (Box::new(Channel::new(track.id, node, inserts, automation, track.strip(), meter)), result)
+    }
+
+    /// The whole project as a graph, for rendering offline.
+    pub fn build_graph(&self, sample_rate: u32) -> Graph {
+        let mut graph = Graph::new();
+
+        for track in &self.tracks {
+            let meter = Arc::new(LevelMeter::new());
+            graph.add_channel(self.build_channel(track, sample_rate, meter).0);
+        }
+
+        for bus in &self.buses {
+            let meter = Arc::new(LevelMeter::new());
+            let automation = self.automation_for(bus.id);
+            graph.add_channel(build_bus(bus, sample_rate, automation, meter));
+        }
+
+        // Routes are checked as they are made, so only a hand edited file
+        // can loop. Its channels play straight out.
+        if let Ok(routing) = self.routing() {
+            graph.set_routing(Box::new(routing));
+        }
+
+        graph
+    }
+}
+
+fn build_inserts(effects: &[EffectSpec], sample_rate: u32) -> Vec<Insert> {
+    effects.iter()
+        .map(|effect| Insert {
+            effect: effect.build(sample_rate as f64),
+            bypassed: effect.bypassed,
+        })
+        .collect()
+}
+
+fn build_bus(bus: &Bus, sample_rate: u32, automation: Automation, meter: Arc<LevelMeter>) -> Box<Channel> {
+    let inserts = build_inserts(&bus.inserts, sample_rate);
+    Box::new(Channel::new(bus.id, Box::new(BusNode), inserts, Box::new(automation), bus.strip(), meter))
+}
+
+/// What the engine's channel for a track or bus was built from, and set to
+/// since.
+struct SyncedChannel {
+    id: usize,
+    /// Buses have no instrument, and no notes to play.
+    instrument: Option<InstrumentSpec>,
+    sequence: Option<Sequence>,
+    strip: Strip,
+    inserts: Vec<EffectSpec>,
+    automation: Automation,
+}
+
+/// Keeps the audio engine's graph in line with the project, sending only
+/// what changed since the last sync.
+pub struct ProjectSync {
+    revision: Option<u64>,
+    tempo_map: Option<TempoMap>,
+    length: Option<u32>,
+    loop_region: Option<LoopRegion>,
+    /// The tracks and buses the engine has channels for.
+    channels: Vec<SyncedChannel>,
+    routing: Option<Routing>,
+    meters: Meters,
+    /// A command was dropped, so the engine may be anything: the next sync
+    /// builds every channel anew.
+    lost: bool,
+}
+
+impl ProjectSync {
+    pub fn new() -> Self {
+        Self {
+            revision: None,
+            tempo_map: None,
+            length: None,
+            loop_region: None,
+            channels: Vec::new(),
+            routing: None,
+            meters: Meters::new(),
+            lost: false,
+        }
+    }
+
+    /// Level meters of the tracks and buses the engine has channels for.
+    pub fn meters(&self) -> &Meters {
+        &self.meters
+    }
+
+    /// Make the next sync go through even if the revision matches, for when
+    /// the project has been swapped out for another one.
+    pub fn invalidate(&mut self) {
+        self.revision = None;
+    }
+
+    /// Returns the first instrument that failed to build, or the routing
+    /// if it loops.
+    pub fn sync(&mut self, project: &Project, audio: &AudioEngine) -> Result<()> {
+        if self.revision == Some(project.revision()) {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        let mut sent = true;
+
+        if self.tempo_map.as_ref() != Some(project.tempo_map()) {
+            self.tempo_map = Some(project.tempo_map().clone());
+            sent &= audio.send(AudioCommand::SetTempoMap(Box::new(project.tempo_map().clone())));
+        }
+
+        if self.length != Some(project.length()) {
+            self.length = Some(project.length());
+            sent &= audio.send(AudioCommand::Transport(TransportCommand::SetEnd(self.length)));
+        }
+
+        if self.loop_region != project.loop_region() {
+            self.loop_region = project.loop_region();
+            sent &= audio.send(AudioCommand::Transport(TransportCommand::SetLoop(self.loop_region)));
+        }
+
+        let removed = self.channels.iter()
+            .map(|channel| channel.id)
+            .filter(|id| project.track(*id).is_none() && project.bus(*id).is_none());
+
+        for id in removed {
+            sent &= audio.send(AudioCommand::RemoveChannel { id });
+            self.meters.remove(&id);
+        }
+
+        if self.lost {
+            self.channels.clear();
+            self.lost = false;
+        }
+
+        let sample_rate = audio.sample_rate();
+        let mut channels = Vec::with_capacity(self.channels.len());
+
+        for track in project.tracks() {
+            let synced = SyncedChannel {
+                id: track.id,
+                instrument: Some(track.instrument.clone()),
+                sequence: Some(project.sequence(track)),
+                strip: track.strip(),
+                inserts: track.inserts.clone(),
+                automation: project.automation_for(track.id),
+            };
+
+            let previous = self.channels.iter()
+                .find(|channel| channel.id == track.id && channel.instrument == synced.instrument);
+
+            if let Some(previous) = previous {
+                sent &= Self::update(previous, &synced, audio);
+            } else {
+                let meter = Arc::new(LevelMeter::new());
+                self.meters.insert(track.id, meter.clone());
+
+                let (channel, built) = project.build_channel(track, sample_rate, meter);
+                sent &= audio.send(AudioCommand::AddChannel(channel));
+                result = result.and(built);
+            }
+
+            channels.push(synced);
+        }
+
+        for bus in project.buses() {
+            let synced = SyncedChannel {
+                id: bus.id,
+                instrument: None,
+                sequence: None,
+                strip: bus.strip(),
+                inserts: bus.inserts.clone(),
+                automation: project.automation_for(bus.id),
+            };
+
+            match self.channels.iter().find(|channel| channel.id == bus.id && channel.instrument.is_none()) {
+                Some(previous) => sent &= Self::update(previous, &synced, audio),
+
+                None => {
+                    let meter = Arc::new(LevelMeter::new());
+                    self.meters.insert(bus.id, meter.clone());
+
+                    let channel = build_bus(bus, sample_rate, synced.automation.clone(), meter);
+                    sent &= audio.send(AudioCommand::AddChannel(channel));
+                }
+            }
+
+            channels.push(synced);
+        }
+
+        // New channels are in place by now, ready to be routed.
+        match project.routing() {
+            Ok(routing) if self.routing.as_ref() != Some(&routing) => {
+                sent &= audio.send(AudioCommand::SetRouting(Box::new(routing.clone())));
+                self.routing = Some(routing);
+            }
+
+            Ok(_) => (),
+            Err(err) => result = result.and(Err(err)),
+        }
+
+        if !sent {
+            // Channels that were being removed have to be removed again.
+            let removed: Vec<_> = self.channels.drain(..)
+                .filter(|old| !channels.iter().any(|channel| channel.id == old.id))
+                .collect();
+            channels.extend(removed);
+        }
+
+        self.channels = channels;
+        self.revision = Some(project.revision());
+
+        if !sent {
+            self.lose();
+        }
+
+        result
+    }
+
+    /// Forget what the engine was sent, so that the next sync sends it all
+    /// again.
+    fn lose(&mut self) {
+        self.revision = None;
+        self.tempo_map = None;
+        self.length = None;
+        self.loop_region = None;
+        self.routing = None;
+        self.lost = true;
+    }
+
+    /// Send what changed about a channel that is already there. Parameters
+    /// are set on the running effects so that they keep ringing, and only
+    /// a different chain of effects is built anew. Returns whether every
+    /// command got through.
+    fn update(previous: &SyncedChannel, current: &SyncedChannel, audio: &AudioEngine) -> bool {
+        let (id, inserts) = (current.id, &current.inserts);
+        let mut sent = true;
+
+        if previous.sequence != current.sequence
+            && let Some(sequence) = &current.sequence
+        {
+            let message = NodeMessage::Sequence(Box::new(sequence.clone()));
+            sent &= audio.send(AudioCommand::Message { id, message });
+        }
+
+        if previous.strip != current.strip {
+            sent &= audio.send(AudioCommand::Message { id, message: NodeMessage::SetStrip(current.strip) });
+        }
+
+        let same_chain = previous.inserts.len() == inserts.len()
+            && previous.inserts.iter().zip(inserts).all(|(old, new)| old.kind == new.kind);
+
+        if previous.automation != current.automation {
+            let automation = Box::new(current.automation.clone());
+            sent &= audio.send(AudioCommand::Message { id, message: NodeMessage::SetAutomation(automation) });
+            sent &= Self::release(previous, current, same_chain, audio);
+        }
+
+        if !same_chain {
+            let inserts = build_inserts(inserts, audio.sample_rate());
+            sent &= audio.send(AudioCommand::Message { id, message: NodeMessage::SetInserts(inserts) });
+            return sent;
+        }
+
+        for (insert, (old, new)) in previous.inserts.iter().zip(inserts).enumerate() {
+            if old.bypassed != new.bypassed {
+                let message = NodeMessage::Bypass { insert, bypassed: new.bypassed };
+                sent &= audio.send(AudioCommand::Message { id, message });
+            }
+
+            for param in 0..new.kind.params().len() {
+                let value = new.value(param);
+                if old.value(param) != value {
+                    let message = NodeMessage::SetParam { insert, param, value };
+                    sent &= audio.send(AudioCommand::Message { id, message });
+                }
+            }
+        }
+
+        sent
+    }
+
+    /// Put parameters that are no longer automated back where the project
+    /// has them. A chain built anew starts out there anyway.
+    fn release(previous: &SyncedChannel, current: &SyncedChannel, same_chain: bool, audio: &AudioEngine) -> bool {
+        let id = current.id;
+        let mut sent = true;
+        let automated = |destination| current.automation.lanes.iter().any(|lane| lane.destination == destination);
+        let released = previous.automation.lanes.iter()
+            .map(|lane| lane.destination)
+            .filter(|destination| !automated(*destination));
+
+        for destination in released {
+            let message = match destination {
+                Destination::Gain | Destination::Pan => NodeMessage::SetStrip(current.strip),
+
+                Destination::Instrument(param) => {
+                    let Some(instrument) = &current.instrument else { continue };
+                    NodeMessage::SetInstrumentParam { param, value: instrument.value(param) }
+                }
+
+                Destination::Insert { insert, param } => {
+                    let Some(effect) = current.inserts.get(insert).filter(|_| same_chain) else { continue };
+                    NodeMessage::SetParam { insert, param, value: effect.value(param) }
+                }
+            };
+
+            sent &= audio.send(AudioCommand::Message { id, message });
+        }
+
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::{Project, track::InstrumentSpec};
+
+    fn audible(project: &Project) -> Vec<bool> {
+        project.tracks().iter().map(|track| project.is_audible(track)).collect()
+    }
+
+    #[test]
+    fn solos_silence_everything_but_soloed_and_safe_tracks() {
+        let mut project = Project::with_default_tracks();
+        let third = project.add_track("Pad", InstrumentSpec::DrumKit);
+        assert_eq!(audible(&project), [true, true, true]);
+
+        project.toggle_solo(0, false);
+        assert_eq!(audible(&project), [true, false, false]);
+
+        project.track_mut(third).unwrap().solo_safe = true;
+        project.toggle_solo(1, false);
+        assert_eq!(audible(&project), [true, true, true]);
+
+        project.track_mut(0).unwrap().muted = true;
+        assert_eq!(audible(&project), [false, true, true]);
+    }
+
+    #[test]
+    fn exclusive_solo_replaces_other_solos() {
+        let mut project = Project::with_default_tracks();
+        project.toggle_solo(0, false);
+
+        assert_eq!(project.toggle_solo(1, true), Some(true));
+        assert!(!project.track(0).unwrap().solo);
+
+        // Only soloed track, so it goes back to nothing soloed.
+        assert_eq!(project.toggle_solo(1, true), Some(false));
+        assert!(project.tracks().iter().all(|track| !track.solo));
+    }
+}
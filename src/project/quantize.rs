@@ -0,0 +1,166 @@
+use crate::dsp::random::Rng;
+use crate::project::{BAR_TICKS, pattern::Note};
+
+/// Snapping notes to a grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quantize {
+    /// Ticks between grid lines, the window's own grid without one.
+    pub grid: Option<u32>,
+    /// How far notes move towards the grid, in percent.
+    pub strength: u8,
+    /// How late every second grid line is, in percent where 100 is a
+    /// triplet feel.
+    pub swing: u8,
+    /// Snap note ends as well as starts.
+    pub ends: bool,
+}
+
+impl Default for Quantize {
+    fn default() -> Self {
+        Self {
+            grid: None,
+            strength: 100,
+            swing: 0,
+            ends: false,
+        }
+    }
+}
+
+impl Quantize {
+    /// Grid lines for a note value like `16`, or `8t` for triplets.
+    pub fn parse_grid(value: &str) -> Option<u32> {
+        let (value, triplet) = match value.strip_suffix('t') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+
+        let division: u32 = value.parse().ok()
+            .filter(|division: &u32| division.is_power_of_two() && *division <= 64)?;
+        let ticks = BAR_TICKS / division;
+
+        Some(if triplet { ticks * 2 / 3 } else { ticks })
+    }
+
+    pub fn apply(&self, note: &mut Note, grid: u32) {
+        let grid = self.grid.unwrap_or(grid).max(1);
+        let end = note.end();
+
+        note.start = self.snap(note.start, grid);
+
+        if self.ends {
+            let end = self.snap(end, grid);
+            if end > note.start {
+                note.length = end - note.start;
+            }
+        }
+    }
+
+    /// Move `tick` towards the nearest grid line, by the strength.
+    fn snap(&self, tick: u32, grid: u32) -> u32 {
+        let swing = grid * self.swing.min(100) as u32 / 300;
+        let line = |index: u32| index * grid + if index % 2 == 1 { swing } else { 0 };
+
+        let index = tick / grid;
+        let nearest = [index.saturating_sub(1), index, index + 1].into_iter()
+            .map(line)
+            .min_by_key(|line| line.abs_diff(tick))
+            .unwrap_or(tick);
+
+        let offset = (nearest as i64 - tick as i64) * self.strength.min(100) as i64;
+        (tick as i64 + (offset as f64 / 100.0).round() as i64) as u32
+    }
+}
+
+/// Random offsets to timing and velocity, the same for the same seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Humanize {
+    /// Largest timing offset, in ticks.
+    pub timing: u32,
+    pub velocity: u8,
+    pub seed: u64,
+}
+
+impl Humanize {
+    pub fn apply(&self, note: &mut Note, rng: &mut Rng) {
+        let timing = (rng.next_bipolar() * self.timing as f64).round() as i32;
+        let velocity = (rng.next_bipolar() * self.velocity as f64).round() as i32;
+
+        note.start = note.start.saturating_add_signed(timing);
+        note.velocity = (note.velocity as i32 + velocity).clamp(1, 127) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{PPQ, pattern::NoteState};
+
+    fn note(start: u32) -> Note {
+        Note { pitch: 60, velocity: 100, start, length: 20, state: NoteState::Normal }
+    }
+
+    fn quantized(quantize: Quantize, start: u32) -> Note {
+        let mut note = note(start);
+        quantize.apply(&mut note, PPQ / 4);
+        note
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_line_by_the_strength() {
+        let sixteenth = PPQ / 4;
+        let full = Quantize::default();
+        let half = Quantize { strength: 50, ..full };
+
+        assert_eq!(quantized(full, sixteenth + 5).start, sixteenth);
+        assert_eq!(quantized(full, 2 * sixteenth - 5).start, 2 * sixteenth);
+        assert_eq!(quantized(half, sixteenth + 6).start, sixteenth + 3);
+
+        let eighths = Quantize { grid: Quantize::parse_grid("8"), ..full };
+        assert_eq!(quantized(eighths, sixteenth + 1).start, 2 * sixteenth);
+    }
+
+    #[test]
+    fn swings_every_second_line() {
+        let sixteenth = PPQ / 4;
+        let swung = Quantize { swing: 100, ..Quantize::default() };
+
+        assert_eq!(quantized(swung, sixteenth).start, sixteenth + sixteenth / 3);
+        assert_eq!(quantized(swung, 2 * sixteenth + 2).start, 2 * sixteenth);
+    }
+
+    #[test]
+    fn snaps_ends_when_asked() {
+        let ends = Quantize { ends: true, ..Quantize::default() };
+        let note = quantized(ends, 2);
+
+        assert_eq!((note.start, note.length), (0, PPQ / 4));
+    }
+
+    #[test]
+    fn parses_note_values() {
+        assert_eq!(Quantize::parse_grid("16"), Some(PPQ / 4));
+        assert_eq!(Quantize::parse_grid("8t"), Some(PPQ / 3));
+        assert_eq!(Quantize::parse_grid("12"), None);
+    }
+
+    #[test]
+    fn humanizes_the_same_way_for_the_same_seed() {
+        let humanize = Humanize { timing: 4, velocity: 10, seed: 7 };
+        let run = || {
+            let mut rng = Rng::new(humanize.seed);
+            (0..8).map(|i| {
+                let mut note = note(i * PPQ);
+                humanize.apply(&mut note, &mut rng);
+                note
+            }).collect::<Vec<_>>()
+        };
+
+        let notes = run();
+        assert_eq!(notes, run());
+
+        for (i, note) in notes.iter().enumerate() {
+            assert!(note.start.abs_diff(i as u32 * PPQ) <= 4);
+            assert!(note.velocity.abs_diff(100) <= 10);
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use color_eyre::eyre::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{
+    graph::{Route, Routing, SendRoute},
+    mixer::Strip,
+};
+use crate::dsp::db_to_gain;
+use crate::effect::EffectSpec;
+use crate::project::{
+    Project,
+    automation::{Lane, Target},
+    history::Part,
+};
+
+/// Buses share ids with tracks, which are also their node ids.
+pub type BusId = usize;
+
+/// The bus everything ends up in, on its way to the speakers.
+pub const MASTER: BusId = BusId::MAX;
+
+/// Where a track or bus sends its output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Output {
+    #[default]
+    Master,
+    Bus(BusId),
+}
+
+impl Output {
+    fn id(self) -> BusId {
+        match self {
+            Output::Master => MASTER,
+            Output::Bus(id) => id,
+        }
+    }
+}
+
+/// A copy of a channel's audio going to a bus as well as its output,
+/// usually one with an effect on it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Send {
+    pub bus: BusId,
+    /// In decibels.
+    pub level: f64,
+    /// Taken before the channel's fader, so that it does not follow it.
+    pub pre_fader: bool,
+}
+
+/// A channel that mixes what is routed into it: a group of tracks, an
+/// effect return, or the master bus.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bus {
+    pub id: BusId,
+    pub name: String,
+    /// Fader level in decibels.
+    pub gain: f64,
+    pub pan: f64,
+    pub muted: bool,
+    /// Ignored on the master bus.
+    pub output: Output,
+    pub sends: Vec<Send>,
+    /// Effects the bus plays through, in order, before its fader.
+    #[serde(default)]
+    pub inserts: Vec<EffectSpec>,
+    #[serde(default)]
+    pub automation: Vec<Lane>,
+}
+
+impl Bus {
+    pub fn new(id: BusId, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            gain: 0.0,
+            pan: 0.0,
+            muted: false,
+            output: Output::Master,
+            sends: Vec::new(),
+            inserts: Vec::new(),
+            automation: Vec::new(),
+        }
+    }
+
+    pub fn master() -> Self {
+        Self::new(MASTER, "Master")
+    }
+
+    /// The fader settings for the audio thread.
+    pub fn strip(&self) -> Strip {
+        Strip {
+            gain: db_to_gain(self.gain) as f32,
+            pan: self.pan as f32,
+            muted: self.muted,
+        }
+    }
+}
+
+/// What a project starts out with, and what older projects load with.
+pub(super) fn default_buses() -> Vec<Bus> {
+    vec![Bus::master()]
+}
+
+impl Project {
+    /// Every bus, the master bus among them.
+    pub fn buses(&self) -> &[Bus] {
+        &self.buses
+    }
+
+    pub fn bus(&self, id: BusId) -> Option<&Bus> {
+        self.buses.iter().find(|bus| bus.id == id)
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn bus_mut(&mut self, id: BusId) -> Option<&mut Bus> {
+        self.record(Part::Buses);
+        self.buses.iter_mut().find(|bus| bus.id == id)
+    }
+
+    pub fn add_bus(&mut self, name: &str) -> BusId {
+        self.record(Part::Buses);
+        let id = self.next_track_id;
+        self.next_track_id += 1;
+
+        self.buses.push(Bus::new(id, name));
+        id
+    }
+
+    /// Remove a bus other than the master. Whatever was routed into it
+    /// goes to the master bus instead.
+    pub fn remove_bus(&mut self, id: BusId) -> Option<Bus> {
+        let index = self.buses.iter().position(|bus| bus.id == id && id != MASTER)?;
+        self.record(Part::Buses);
+        self.record(Part::Tracks);
+
+        let routes = self.tracks.iter_mut()
+            .map(|track| (&mut track.output, &mut track.sends))
+            .chain(self.buses.iter_mut().map(|bus| (&mut bus.output, &mut bus.sends)));
+
+        for (output, sends) in routes {
+            if *output == Output::Bus(id) {
+                *output = Output::Master;
+            }
+            sends.retain(|send| send.bus != id);
+        }
+
+        Some(self.buses.remove(index))
+    }
+
+    /// A track or bus by name, ignoring case.
+    pub fn find_channel(&self, name: &str) -> Option<usize> {
+        let tracks = self.tracks.iter().map(|track| (track.id, &track.name));
+        let buses = self.buses.iter().map(|bus| (bus.id, &bus.name));
+
+        tracks.chain(buses)
+            .find(|(_, channel)| channel.eq_ignore_ascii_case(name))
+            .map(|(id, _)| id)
+    }
+
+    pub fn channel_name(&self, id: usize) -> Option<&str> {
+        match self.track(id) {
+            Some(track) => Some(&track.name),
+            None => Some(&self.bus(id)?.name),
+        }
+    }
+
+    /// Every track and bus, in the order the mixer shows them: tracks,
+    /// then buses, then the master bus.
+    pub fn channels(&self) -> Vec<usize> {
+        let buses = self.buses.iter().filter(|bus| bus.id != MASTER);
+
+        self.tracks.iter().map(|track| track.id)
+            .chain(buses.map(|bus| bus.id))
+            .chain(self.bus(MASTER).map(|bus| bus.id))
+            .collect()
+    }
+
+    /// The insert chain of a track or bus.
+    pub fn inserts(&self, id: usize) -> Option<&[EffectSpec]> {
+        match self.track(id) {
+            Some(track) => Some(&track.inserts),
+            None => Some(&self.bus(id)?.inserts),
+        }
+    }
+
+    /// Recorded for undo, and counted as a change if anything changes.
+    pub fn inserts_mut(&mut self, id: usize) -> Option<&mut Vec<EffectSpec>> {
+        if self.track(id).is_some() {
+            return Some(&mut self.track_mut(id)?.inserts);
+        }
+
+        Some(&mut self.bus_mut(id)?.inserts)
+    }
+
+    /// Take an effect out of a chain along with its automation. Lanes of
+    /// the effects after it move up with them.
+    pub fn remove_insert(&mut self, id: usize, index: usize) -> Option<EffectSpec> {
+        let inserts = self.inserts_mut(id)?;
+        let effect = (index < inserts.len()).then(|| inserts.remove(index))?;
+        let lanes = self.automation_mut(id)?;
+
+        lanes.retain(|lane| !matches!(lane.target, Target::Insert { insert, .. } if insert == index));
+
+        for lane in lanes.iter_mut() {
+            if let Target::Insert { insert, .. } = &mut lane.target && *insert > index {
+                *insert -= 1;
+            }
+        }
+
+        Some(effect)
+    }
+
+    /// The fader level and pan of a track or bus.
+    pub fn fader_mut(&mut self, id: usize) -> Option<(&mut f64, &mut f64)> {
+        if self.track(id).is_some() {
+            let track = self.track_mut(id)?;
+            return Some((&mut track.gain, &mut track.pan));
+        }
+
+        let bus = self.bus_mut(id)?;
+        Some((&mut bus.gain, &mut bus.pan))
+    }
+
+    /// Where a track or bus sends its audio.
+    fn routes_mut(&mut self, id: usize) -> Option<(&mut Output, &mut Vec<Send>)> {
+        if self.track(id).is_some() {
+            let track = self.track_mut(id)?;
+            return Some((&mut track.output, &mut track.sends));
+        }
+
+        let bus = self.bus_mut(id).filter(|bus| bus.id != MASTER)?;
+        Some((&mut bus.output, &mut bus.sends))
+    }
+
+    /// Route a track or bus into another bus. Routes that would feed a bus
+    /// back into itself are refused.
+    pub fn set_output(&mut self, id: usize, output: Output) -> Result<()> {
+        if self.bus(output.id()).is_none() {
+            bail!("no such bus");
+        }
+
+        let Some((current, _)) = self.routes_mut(id) else { bail!("the master bus has no output") };
+        let previous = *current;
+        *current = output;
+
+        self.check_routing(|project| {
+            if let Some((current, _)) = project.routes_mut(id) {
+                *current = previous;
+            }
+        })
+    }
+
+    /// Add a send from a track or bus, or change the one it has to the
+    /// same bus.
+    pub fn set_send(&mut self, id: usize, send: Send) -> Result<()> {
+        if send.bus == MASTER || self.bus(send.bus).is_none() {
+            bail!("sends go to buses other than the master");
+        }
+
+        let Some((_, sends)) = self.routes_mut(id) else { bail!("the master bus has no sends") };
+        let previous = sends.clone();
+
+        match sends.iter_mut().find(|existing| existing.bus == send.bus) {
+            Some(existing) => *existing = send,
+            None => sends.push(send),
+        }
+
+        self.check_routing(|project| {
+            if let Some((_, sends)) = project.routes_mut(id) {
+                *sends = previous;
+            }
+        })
+    }
+
+    /// Returns whether there was a send to take away.
+    pub fn remove_send(&mut self, id: usize, bus: BusId) -> bool {
+        let Some((_, sends)) = self.routes_mut(id) else { return false };
+        let count = sends.len();
+        sends.retain(|send| send.bus != bus);
+
+        sends.len() != count
+    }
+
+    /// Undo a routing change that made a cycle.
+    fn check_routing(&mut self, revert: impl FnOnce(&mut Self)) -> Result<()> {
+        match self.routing() {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                revert(self);
+                Err(err)
+            }
+        }
+    }
+
+    /// Every channel's route, sorted so that each one comes before the
+    /// buses it feeds. Fails when buses feed back into themselves.
+    pub fn routing(&self) -> Result<Routing> {
+        let tracks = self.tracks.iter().map(|track| (track.id, track.output, &track.sends));
+        let buses = self.buses.iter().map(|bus| (bus.id, bus.output, &bus.sends));
+
+        let mut pending: Vec<Route> = tracks.chain(buses)
+            .map(|(id, output, sends)| Route {
+                id,
+                output: (id != MASTER).then_some(output.id()),
+                sends: sends.iter()
+                    .map(|send| SendRoute {
+                        to: send.bus,
+                        gain: db_to_gain(send.level) as f32,
+                        pre_fader: send.pre_fader,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // Take whatever nothing left is routed into, until nothing is left.
+        let mut routes = Vec::with_capacity(pending.len());
+
+        while !pending.is_empty() {
+            let fed = |id: usize, pending: &[Route]| pending.iter()
+                .any(|route| route.output == Some(id) || route.sends.iter().any(|send| send.to == id));
+
+            let Some(next) = pending.iter().position(|route| !fed(route.id, &pending)) else {
+                let names: Vec<&str> = pending.iter()
+                    .filter_map(|route| self.channel_name(route.id))
+                    .collect();
+                bail!("routing loops through {}", names.join(", "));
+            };
+
+            routes.push(pending.remove(next));
+        }
+
+        Ok(Routing { routes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+
+    fn order(project: &Project) -> Vec<usize> {
+        project.routing().unwrap().routes.iter().map(|route| route.id).collect()
+    }
+
+    #[test]
+    fn renders_channels_before_the_buses_they_feed() {
+        let mut project = Project::with_default_tracks();
+        let group = project.add_bus("Group");
+        let reverb = project.add_bus("Reverb");
+
+        project.set_output(0, Output::Bus(group)).unwrap();
+        project.set_send(group, Send { bus: reverb, level: -6.0, pre_fader: false }).unwrap();
+
+        let order = order(&project);
+        let position = |id| order.iter().position(|route| *route == id).unwrap();
+
+        assert!(position(0) < position(group));
+        assert!(position(group) < position(reverb));
+        assert!(position(reverb) < position(MASTER));
+        assert_eq!(order.len(), 5);
+    }
+
+    #[test]
+    fn refuses_routes_that_loop() {
+        let mut project = Project::with_default_tracks();
+        let first = project.add_bus("First");
+        let second = project.add_bus("Second");
+
+        project.set_output(first, Output::Bus(second)).unwrap();
+        let looped = Send { bus: first, level: 0.0, pre_fader: true };
+
+        assert!(project.set_send(second, looped).is_err());
+        assert!(project.set_output(second, Output::Bus(first)).is_err());
+        assert!(project.set_output(first, Output::Bus(first)).is_err());
+        assert_eq!(project.bus(second).unwrap().output, Output::Master);
+        assert!(project.bus(second).unwrap().sends.is_empty());
+        assert!(project.routing().is_ok());
+    }
+
+    #[test]
+    fn removing_a_bus_reroutes_to_the_master() {
+        let mut project = Project::with_default_tracks();
+        let group = project.add_bus("Group");
+
+        project.set_output(1, Output::Bus(group)).unwrap();
+        project.set_send(0, Send { bus: group, level: 0.0, pre_fader: false }).unwrap();
+        project.remove_bus(group);
+
+        assert_eq!(project.track(1).unwrap().output, Output::Master);
+        assert!(project.track(0).unwrap().sends.is_empty());
+        assert!(project.remove_bus(MASTER).is_none());
+    }
+}
@@ -0,0 +1,337 @@
+use std::{fmt, mem};
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::PPQ;
+
+pub const DEFAULT_TEMPO: f64 = 120.0;
+/// Tempos are kept within this range, which MIDI files can also hold.
+pub const MIN_TEMPO: f64 = 10.0;
+pub const MAX_TEMPO: f64 = 999.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeSignature {
+    pub numerator: u8,
+    /// Note value of one beat, a power of two.
+    pub denominator: u8,
+}
+
+impl TimeSignature {
+    pub const COMMON: Self = Self { numerator: 4, denominator: 4 };
+
+    pub fn beat_ticks(&self) -> u32 {
+        PPQ * 4 / self.denominator.max(1) as u32
+    }
+
+    pub fn bar_ticks(&self) -> u32 {
+        self.beat_ticks() * self.numerator.max(1) as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TempoChange {
+    pub tick: u32,
+    pub bpm: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MeterChange {
+    pub tick: u32,
+    pub signature: TimeSignature,
+}
+
+/// Musical distances the cursor can move by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Beat,
+    Bar,
+}
+
+/// A song position in musical terms, everything counting from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarPosition {
+    pub bar: u32,
+    pub beat: u32,
+    /// Ticks into the beat.
+    pub tick: u32,
+}
+
+/// Shown as bar.beat.tick, counting bars and beats from one.
+impl fmt::Display for BarPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{:03}", self.bar + 1, self.beat + 1, self.tick)
+    }
+}
+
+/// Tempo and time signature over the song. Both lists are sorted by tick
+/// and always start with an entry at tick zero.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TempoMap {
+    tempos: Vec<TempoChange>,
+    meters: Vec<MeterChange>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self {
+            tempos: vec![TempoChange { tick: 0, bpm: DEFAULT_TEMPO }],
+            meters: vec![MeterChange { tick: 0, signature: TimeSignature::COMMON }],
+        }
+    }
+}
+
+impl TempoMap {
+    pub fn tempos(&self) -> &[TempoChange] {
+        &self.tempos
+    }
+
+    pub fn meters(&self) -> &[MeterChange] {
+        &self.meters
+    }
+
+    /// Replaces any change already at the same tick. The tempo is clamped
+    /// to `MIN_TEMPO..=MAX_TEMPO`.
+    pub fn set_tempo(&mut self, tick: u32, bpm: f64) {
+        let bpm = bpm.clamp(MIN_TEMPO, MAX_TEMPO);
+
+        match self.tempos.binary_search_by_key(&tick, |change| change.tick) {
+            Ok(index) => self.tempos[index].bpm = bpm,
+            Err(index) => self.tempos.insert(index, TempoChange { tick, bpm }),
+        }
+    }
+
+    /// Replaces any change already at the same tick.
+    pub fn set_meter(&mut self, tick: u32, signature: TimeSignature) {
+        match self.meters.binary_search_by_key(&tick, |change| change.tick) {
+            Ok(index) => self.meters[index].signature = signature,
+            Err(index) => self.meters.insert(index, MeterChange { tick, signature }),
+        }
+    }
+
+    /// Put a map read from a file back in order, with its tempos in range
+    /// and both lists starting at tick zero.
+    pub fn normalize(&mut self) {
+        let Self { tempos, meters } = mem::take(self);
+
+        for change in tempos {
+            self.set_tempo(change.tick, change.bpm);
+        }
+        for change in meters {
+            self.set_meter(change.tick, change.signature);
+        }
+    }
+
+    pub fn tempo_at(&self, tick: u32) -> f64 {
+        self.tempos.iter()
+            .take_while(|change| change.tick <= tick)
+            .last()
+            .map_or(DEFAULT_TEMPO, |change| change.bpm)
+    }
+
+    pub fn meter_at(&self, tick: u32) -> TimeSignature {
+        self.meters.iter()
+            .take_while(|change| change.tick <= tick)
+            .last()
+            .map_or(TimeSignature::COMMON, |change| change.signature)
+    }
+
+    /// The first tempo change after `tick`.
+    pub fn next_tempo_change(&self, tick: f64) -> Option<u32> {
+        self.tempos.iter()
+            .map(|change| change.tick)
+            .find(|change| *change as f64 > tick)
+    }
+
+    /// Time from the start of the song until `tick`.
+    pub fn seconds_at(&self, tick: f64) -> f64 {
+        let mut seconds = 0.0;
+        let mut from = 0.0;
+        let mut bpm = DEFAULT_TEMPO;
+
+        for change in &self.tempos {
+            let at = (change.tick as f64).min(tick);
+            seconds += (at - from) * 60.0 / (bpm * PPQ as f64);
+            from = at;
+            bpm = change.bpm;
+        }
+
+        seconds + (tick - from).max(0.0) * 60.0 / (bpm * PPQ as f64)
+    }
+
+    /// Every stretch of the song with its own time signature, as the bar
+    /// it starts on, the tick it starts at and the signature. A change in
+    /// the middle of a bar starts a new one.
+    fn sections(&self) -> impl Iterator<Item = (u32, u32, TimeSignature)> + '_ {
+        let mut bar = 0;
+
+        self.meters.iter().enumerate().map(move |(i, change)| {
+            let section = (bar, change.tick, change.signature);
+
+            if let Some(next) = self.meters.get(i + 1) {
+                bar += (next.tick - change.tick).div_ceil(change.signature.bar_ticks());
+            }
+
+            section
+        })
+    }
+
+    pub fn position(&self, tick: u32) -> BarPosition {
+        let (bar, start, signature) = self.sections()
+            .take_while(|(_, start, _)| *start <= tick)
+            .last()
+            .unwrap_or((0, 0, TimeSignature::COMMON));
+
+        let offset = tick - start;
+        let (bar_ticks, beat_ticks) = (signature.bar_ticks(), signature.beat_ticks());
+
+        BarPosition {
+            bar: bar + offset / bar_ticks,
+            beat: offset % bar_ticks / beat_ticks,
+            tick: offset % beat_ticks,
+        }
+    }
+
+    /// The tick a bar starts at, counting from zero, unless it is further
+    /// out than a tick can count.
+    pub fn bar_tick(&self, bar: u32) -> Option<u32> {
+        let (first, start, signature) = self.sections()
+            .take_while(|(first, _, _)| *first <= bar)
+            .last()
+            .unwrap_or((0, 0, TimeSignature::COMMON));
+
+        (bar - first).checked_mul(signature.bar_ticks())?.checked_add(start)
+    }
+
+    /// `count` whole beats or bars, from the start of the one `tick` is in.
+    pub fn span(&self, tick: u32, unit: TimeUnit, count: u32) -> Range<u32> {
+        let position = self.position(tick);
+        let start = match unit {
+            TimeUnit::Beat => tick - position.tick,
+            TimeUnit::Bar => self.bar_tick(position.bar).unwrap_or(tick),
+        };
+
+        start..self.step(start, unit, count as i32)
+    }
+
+    /// Move `count` beats or bars on from `tick`, landing on the start of
+    /// one. Going back from the middle of a beat or bar first goes to its
+    /// start, the way `b` does with words.
+    pub fn step(&self, tick: u32, unit: TimeUnit, count: i32) -> u32 {
+        match unit {
+            TimeUnit::Bar => {
+                let position = self.position(tick);
+                let bar = match count {
+                    0.. => position.bar.saturating_add(count as u32),
+                    _ if self.bar_tick(position.bar).is_some_and(|start| tick > start) => {
+                        position.bar.saturating_sub(count.unsigned_abs() - 1)
+                    }
+                    _ => position.bar.saturating_sub(count.unsigned_abs()),
+                };
+
+                // Stops at the last tick rather than going past it.
+                self.bar_tick(bar).unwrap_or(u32::MAX)
+            }
+
+            TimeUnit::Beat => (0..count.unsigned_abs()).fold(tick, |tick, _| {
+                let beat_start = tick - self.position(tick).tick;
+
+                if count > 0 {
+                    // A meter change cuts the beat before it short.
+                    let next_meter = self.meters.iter()
+                        .map(|change| change.tick)
+                        .find(|change| *change > tick)
+                        .unwrap_or(u32::MAX);
+
+                    (beat_start + self.meter_at(tick).beat_ticks()).min(next_meter)
+                } else if tick > beat_start {
+                    beat_start
+                } else {
+                    tick.checked_sub(1).map_or(0, |tick| tick - self.position(tick).tick)
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_bars_across_meter_changes() {
+        let seven_eight = TimeSignature { numerator: 7, denominator: 8 };
+        let mut map = TempoMap::default();
+        map.set_meter(2 * 4 * PPQ, seven_eight);
+
+        // Two bars of 4/4, then bars of seven eighths.
+        let start = map.bar_tick(2).unwrap();
+        assert_eq!(start, 8 * PPQ);
+        assert_eq!(map.bar_tick(3), Some(start + 7 * PPQ / 2));
+        assert_eq!(map.bar_tick(20_000_000), None);
+
+        assert_eq!(map.position(start - 1), BarPosition { bar: 1, beat: 3, tick: PPQ - 1 });
+        assert_eq!(map.position(start + 7 * PPQ / 2 + PPQ / 2 + 5), BarPosition { bar: 3, beat: 1, tick: 5 });
+        assert_eq!(map.position(start).to_string(), "3.1.000");
+    }
+
+    #[test]
+    fn steps_by_beats_and_bars_of_the_active_meter() {
+        let mut map = TempoMap::default();
+        map.set_meter(4 * PPQ, TimeSignature { numerator: 7, denominator: 8 });
+        let eighth = PPQ / 2;
+
+        assert_eq!(map.step(0, TimeUnit::Beat, 3), 3 * PPQ);
+        assert_eq!(map.step(3 * PPQ, TimeUnit::Beat, 2), 4 * PPQ + eighth);
+        assert_eq!(map.step(4 * PPQ + eighth + 1, TimeUnit::Beat, -1), 4 * PPQ + eighth);
+        assert_eq!(map.step(4 * PPQ + eighth, TimeUnit::Beat, -2), 3 * PPQ);
+
+        assert_eq!(map.step(PPQ, TimeUnit::Bar, 2), 4 * PPQ + 7 * eighth);
+        assert_eq!(map.step(4 * PPQ + 1, TimeUnit::Bar, -1), 4 * PPQ);
+        assert_eq!(map.step(4 * PPQ, TimeUnit::Bar, -1), 0);
+        assert_eq!(map.step(4 * PPQ, TimeUnit::Bar, -5), 0);
+    }
+
+    #[test]
+    fn spans_whole_beats_and_bars_around_a_tick() {
+        let mut map = TempoMap::default();
+        map.set_meter(4 * PPQ, TimeSignature { numerator: 7, denominator: 8 });
+        let eighth = PPQ / 2;
+
+        assert_eq!(map.span(PPQ + 1, TimeUnit::Beat, 1), PPQ..2 * PPQ);
+        assert_eq!(map.span(3 * PPQ, TimeUnit::Bar, 2), 0..4 * PPQ + 7 * eighth);
+        assert_eq!(map.span(5 * PPQ, TimeUnit::Beat, 2), 5 * PPQ..6 * PPQ);
+    }
+
+    #[test]
+    fn integrates_time_over_tempo_changes() {
+        let mut map = TempoMap::default();
+        map.set_tempo(0, 60.0);
+        map.set_tempo(2 * PPQ, 120.0);
+
+        assert_eq!(map.seconds_at(PPQ as f64), 1.0);
+        assert_eq!(map.seconds_at((4 * PPQ) as f64), 3.0);
+        assert_eq!(map.next_tempo_change(0.0), Some(2 * PPQ));
+        assert_eq!(map.next_tempo_change(2.0 * PPQ as f64), None);
+    }
+
+    #[test]
+    fn normalizing_restores_order_and_range() {
+        let mut map = TempoMap {
+            tempos: vec![
+                TempoChange { tick: 960, bpm: 0.0 },
+                TempoChange { tick: 480, bpm: 5000.0 },
+            ],
+            meters: vec![MeterChange { tick: 480, signature: TimeSignature { numerator: 3, denominator: 4 } }],
+        };
+        map.normalize();
+
+        assert_eq!(map.tempos(), [
+            TempoChange { tick: 0, bpm: DEFAULT_TEMPO },
+            TempoChange { tick: 480, bpm: MAX_TEMPO },
+            TempoChange { tick: 960, bpm: MIN_TEMPO },
+        ]);
+        assert_eq!(map.meters()[0], MeterChange { tick: 0, signature: TimeSignature::COMMON });
+        assert_eq!(map.meter_at(500).numerator, 3);
+    }
+}
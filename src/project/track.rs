@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::mixer::Strip;
+use crate::dsp::db_to_gain;
+use crate::effect::{EffectSpec, Param};
+use crate::instrument::{
+    Instrument,
+    drums::{self, DrumKit},
+    sampler::{self, Sampler},
+    synth::{self, Synth, SynthParams},
+};
+use crate::project::{
+    PatternId, TrackId,
+    automation::Lane,
+    routing::{Output, Send},
+};
+
+/// What a track plays its notes through. This is the description kept in
+/// the project, the live instrument is built from it for the audio thread.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InstrumentSpec {
+    Synth(SynthParams),
+    DrumKit,
+    Sampler { path: PathBuf, root: u8 },
+}
+
+impl InstrumentSpec {
+    pub fn build(&self, sample_rate: f64) -> Result<Box<dyn Instrument>> {
+        Ok(match self {
+            InstrumentSpec::Synth(params) => Box::new(Synth::new(params.clone(), sample_rate)),
+            InstrumentSpec::DrumKit => Box::new(DrumKit::new(sample_rate)),
+            InstrumentSpec::Sampler { path, root } => {
+                Box::new(Sampler::from_file(path, *root, sample_rate)?)
+            }
+        })
+    }
+
+    /// What can be automated on the instrument.
+    pub fn params(&self) -> &'static [Param] {
+        match self {
+            InstrumentSpec::Synth(_) => &synth::PARAMS,
+            InstrumentSpec::DrumKit => &drums::PARAMS,
+            InstrumentSpec::Sampler { .. } => &sampler::PARAMS,
+        }
+    }
+
+    /// A parameter by its place in `params`. Only the synth keeps its
+    /// parameters, the others are at their defaults.
+    pub fn value(&self, index: usize) -> f64 {
+        match self {
+            InstrumentSpec::Synth(params) => params.value(index),
+            _ => self.params()[index].default,
+        }
+    }
+}
+
+/// A pattern placed on a track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clip {
+    pub pattern: PatternId,
+    /// Song tick the pattern starts playing at.
+    pub start: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    /// Stable for the lifetime of the track, doubles as its node id in the
+    /// audio graph.
+    pub id: TrackId,
+    pub name: String,
+    pub instrument: InstrumentSpec,
+    pub clips: Vec<Clip>,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub solo: bool,
+    /// Keeps playing while other tracks are soloed.
+    #[serde(default)]
+    pub solo_safe: bool,
+    /// Fader level in decibels.
+    #[serde(default)]
+    pub gain: f64,
+    /// From -1 for hard left to 1 for hard right.
+    #[serde(default)]
+    pub pan: f64,
+    /// Ready to record.
+    #[serde(default)]
+    pub armed: bool,
+    #[serde(default)]
+    pub output: Output,
+    #[serde(default)]
+    pub sends: Vec<Send>,
+    /// Effects the track plays through, in order, before its fader.
+    #[serde(default)]
+    pub inserts: Vec<EffectSpec>,
+    #[serde(default)]
+    pub automation: Vec<Lane>,
+}
+
+impl Track {
+    pub fn new(id: TrackId, name: &str, instrument: InstrumentSpec) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            instrument,
+            clips: Vec::new(),
+            muted: false,
+            solo: false,
+            solo_safe: false,
+            gain: 0.0,
+            pan: 0.0,
+            armed: false,
+            output: Output::Master,
+            sends: Vec::new(),
+            inserts: Vec::new(),
+            automation: Vec::new(),
+        }
+    }
+
+    /// Mute, solo and solo safe, as shown next to the track name.
+    pub fn flags(&self) -> String {
+        [(self.muted, "M"), (self.solo, "S"), (self.solo_safe, "safe")].into_iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| format!(" [{flag}]"))
+            .collect()
+    }
+
+    /// The fader settings for the audio thread.
+    pub fn strip(&self) -> Strip {
+        Strip {
+            gain: db_to_gain(self.gain) as f32,
+            pan: self.pan as f32,
+            muted: false,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use crate::project::pattern::Note;
+
+/// Notes taken out of a pattern, with their starts counted from the
+/// beginning of the range they were taken from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clipboard {
+    pub notes: Vec<Note>,
+    /// Ticks the range covered, which is how far apart repeated pastes are.
+    pub length: u32,
+}
+
+impl Clipboard {
+    fn append(&mut self, other: &Clipboard) {
+        self.notes.extend(other.notes.iter().map(|note| Note {
+            start: note.start + self.length,
+            ..*note
+        }));
+        self.length += other.length;
+    }
+}
+
+/// Vim style registers: the unnamed one that everything goes through, `0`
+/// for the last yank, `1` to `9` for the last deletes and `a` to `z`, which
+/// are appended to when written to in uppercase.
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, Clipboard>,
+}
+
+const UNNAMED: char = '"';
+
+impl Registers {
+    pub fn is_valid(name: char) -> bool {
+        name == UNNAMED || name.is_ascii_alphanumeric()
+    }
+
+    /// The register to paste from, unnamed without a name.
+    pub fn get(&self, name: Option<char>) -> Option<&Clipboard> {
+        let name = name.unwrap_or(UNNAMED).to_ascii_lowercase();
+        self.registers.get(&name)
+    }
+
+    pub fn yank(&mut self, name: Option<char>, clipboard: Clipboard) {
+        self.store(name, clipboard, '0');
+    }
+
+    pub fn delete(&mut self, name: Option<char>, clipboard: Clipboard) {
+        if matches!(name, Some(UNNAMED) | None) {
+            for n in (1..9).rev() {
+                let from = char::from_digit(n, 10).unwrap();
+                let to = char::from_digit(n + 1, 10).unwrap();
+
+                if let Some(older) = self.registers.remove(&from) {
+                    self.registers.insert(to, older);
+                }
+            }
+        }
+
+        self.store(name, clipboard, '1');
+    }
+
+    fn store(&mut self, name: Option<char>, clipboard: Clipboard, numbered: char) {
+        let unnamed = match name {
+            Some(name) if name.is_ascii_uppercase() => {
+                let register = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                register.append(&clipboard);
+                register.clone()
+            }
+
+            Some(UNNAMED) | None => {
+                self.registers.insert(numbered, clipboard.clone());
+                clipboard
+            }
+
+            Some(name) => {
+                self.registers.insert(name, clipboard.clone());
+                clipboard
+            }
+        };
+
+        self.registers.insert(UNNAMED, unnamed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::pattern::NoteState;
+
+    use super::*;
+
+    fn clip(pitch: u8) -> Clipboard {
+        let note = Note { pitch, velocity: 100, start: 0, length: 24, state: NoteState::Normal };
+        Clipboard { notes: vec![note], length: 96 }
+    }
+
+    fn pitch(registers: &Registers, name: Option<char>) -> Option<u8> {
+        Some(registers.get(name)?.notes[0].pitch)
+    }
+
+    #[test]
+    fn yanks_go_to_zero_and_unnamed() {
+        let mut registers = Registers::default();
+        registers.yank(None, clip(60));
+
+        assert_eq!(pitch(&registers, None), Some(60));
+        assert_eq!(pitch(&registers, Some('0')), Some(60));
+        assert_eq!(pitch(&registers, Some('1')), None);
+
+        // A named yank leaves `0` alone.
+        registers.yank(Some('a'), clip(62));
+        assert_eq!(pitch(&registers, Some('a')), Some(62));
+        assert_eq!(pitch(&registers, None), Some(62));
+        assert_eq!(pitch(&registers, Some('0')), Some(60));
+    }
+
+    #[test]
+    fn deletes_shift_through_the_numbered_registers() {
+        let mut registers = Registers::default();
+
+        for pitch in 0..10 {
+            registers.delete(None, clip(pitch));
+        }
+        // Naming the unnamed register is the same as naming none.
+        registers.delete(Some(UNNAMED), clip(10));
+
+        for n in 1..=9 {
+            let name = char::from_digit(n, 10);
+            assert_eq!(pitch(&registers, name), Some(11 - n as u8));
+        }
+        assert_eq!(pitch(&registers, None), Some(10));
+        assert_eq!(pitch(&registers, Some('0')), None);
+    }
+
+    #[test]
+    fn uppercase_appends() {
+        let mut registers = Registers::default();
+        registers.yank(Some('a'), clip(60));
+        registers.delete(Some('A'), clip(62));
+
+        let appended = registers.get(Some('a')).unwrap();
+        assert_eq!(appended.length, 192);
+        assert_eq!(appended.notes.iter().map(|note| (note.pitch, note.start)).collect::<Vec<_>>(), [
+            (60, 0),
+            (62, 96),
+        ]);
+
+        assert_eq!(registers.get(Some('A')), Some(appended));
+        assert_eq!(registers.get(None), Some(appended));
+        assert_eq!(pitch(&registers, Some('1')), None);
+    }
+}
@@ -0,0 +1,367 @@
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::StatefulWidget,
+};
+
+use crate::input::{Edge, EditorCommand, LocalCommand};
+use crate::project::{
+    PPQ, Project, TrackId,
+    automation::{Curve, Envelope, Lane, Point},
+    tempo::BarPosition,
+};
+use crate::widgets::theme::UIStyle;
+use crate::window::{Context, Window, WindowKind};
+
+/// A column is a 16th note.
+const COLUMN_TICKS: u32 = PPQ / 4;
+/// How far `j` and `k` move the cursor, out of the whole range.
+const VALUE_STEP: f64 = 0.02;
+
+/// Dots of a braille character by column, then row from the top.
+const BRAILLE: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+pub struct Automation<'a, 'b> {
+    ctx: &'a Context<'b>,
+}
+
+impl<'a, 'b> Automation<'a, 'b> {
+    pub fn new(ctx: &'a Context<'b>) -> Self {
+        Self { ctx }
+    }
+
+    /// Row of dots a value is drawn on, counting down from the top.
+    fn dot_row(value: f64, rows: u16) -> u32 {
+        let dots = rows as u32 * 4 - 1;
+        ((1.0 - value.clamp(0.0, 1.0)) * dots as f64).round() as u32
+    }
+}
+
+impl StatefulWidget for Automation<'_, '_> {
+    type State = AutomationState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if area.width == 0 || area.height == 0 { return; }
+
+        let Some(lane) = state.lane(self.ctx.project) else {
+            let hint = "Nothing automated, add a lane with :automate";
+            buf.set_string(area.x + 1, area.y, hint, Style::default().fg(Color::DarkGray));
+            return;
+        };
+
+        let columns = area.width as u32;
+        state.scroll_into_view(columns);
+
+        let tempo_map = self.ctx.project.tempo_map();
+        let envelope = &lane.envelope;
+        let playhead = (!self.ctx.transport.is_stopped())
+            .then_some(self.ctx.transport.tick as u32 / COLUMN_TICKS);
+
+        // Dots of the curve, two samples to a column, joined up so that
+        // steep moves leave no gaps.
+        let mut cells = vec![0u32; (columns * area.height as u32) as usize];
+        let mut last = None;
+
+        for dot in 0..columns * 2 {
+            let tick = (state.scroll * 2 + dot) as f64 * COLUMN_TICKS as f64 / 2.0;
+            let Some(value) = envelope.value_at(tick) else { break };
+            let row = Self::dot_row(value, area.height);
+            let (from, to) = last.map_or((row, row), |last: u32| (last.min(row), last.max(row)));
+
+            for row in from..=to {
+                let cell = (row / 4 * columns + dot / 2) as usize;
+                cells[cell] |= BRAILLE[(dot % 2) as usize][(row % 4) as usize];
+            }
+
+            last = Some(row);
+        }
+
+        let cursor_row = Self::dot_row(state.value, area.height) / 4;
+
+        for dy in 0..area.height {
+            for dx in 0..columns {
+                let column = state.scroll + dx;
+                let tick = column * COLUMN_TICKS;
+                let Some(cell) = buf.cell_mut((area.x + dx as u16, area.y + dy)) else { continue };
+
+                let point = AutomationState::point_in_column(envelope, column)
+                    .map(|index| envelope.points()[index])
+                    .filter(|point| Self::dot_row(point.value, area.height) / 4 == dy as u32);
+                let dots = cells[(dy as u32 * columns + dx) as usize];
+                let is_cursor = column == state.column && dy as u32 == cursor_row;
+                let is_playhead = playhead == Some(column);
+
+                let (symbol, color) = match () {
+                    _ if point.is_some() && state.grabbed.is_some() && column == state.column => ('●', Color::Yellow),
+                    _ if point.is_some() => ('●', Color::LightCyan),
+                    _ if dots != 0 => {
+                        let symbol = char::from_u32(0x2800 + dots).unwrap_or(' ');
+                        (symbol, if is_playhead { Color::Yellow } else { Color::Cyan })
+                    }
+                    _ if is_cursor => ('█', Color::White),
+                    _ if is_playhead => ('│', Color::Yellow),
+
+                    _ => match tempo_map.position(tick) {
+                        BarPosition { beat: 0, tick: 0, .. } => ('│', Color::DarkGray),
+                        BarPosition { tick: 0, .. } => ('·', Color::DarkGray),
+                        _ => (' ', Color::DarkGray),
+                    },
+                };
+
+                let mut style = Style::default().fg(color);
+                if is_cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+
+                cell.set_char(symbol).set_style(style);
+            }
+        }
+    }
+}
+
+/// One automation lane at a time, drawn over the song, with a cursor for
+/// adding and moving its points.
+pub struct AutomationState {
+    /// Which lane, counting through the lanes of every track and bus.
+    lane: usize,
+    column: u32,
+    /// Where the cursor is in the parameter's range, from 0 to 1.
+    value: f64,
+    /// First column on screen.
+    scroll: u32,
+    /// A point being moved along with the cursor.
+    grabbed: Option<usize>,
+}
+
+impl AutomationState {
+    pub fn new() -> Self {
+        Self {
+            lane: 0,
+            column: 0,
+            value: 0.5,
+            scroll: 0,
+            grabbed: None,
+        }
+    }
+
+    /// Every lane as its channel and its place among the channel's lanes.
+    fn lanes(project: &Project) -> Vec<(usize, usize)> {
+        project.channels().into_iter()
+            .flat_map(|id| {
+                let lanes = project.automation(id).map_or(0, <[Lane]>::len);
+                (0..lanes).map(move |lane| (id, lane))
+            })
+            .collect()
+    }
+
+    fn current(&self, project: &Project) -> Option<(usize, usize)> {
+        Self::lanes(project).get(self.lane).copied()
+    }
+
+    fn lane<'a>(&self, project: &'a Project) -> Option<&'a Lane> {
+        let (id, lane) = self.current(project)?;
+        project.automation(id)?.get(lane)
+    }
+
+    fn envelope_mut<'a>(&self, project: &'a mut Project) -> Option<&'a mut Envelope> {
+        let (id, lane) = self.current(project)?;
+        Some(&mut project.automation_mut(id)?.get_mut(lane)?.envelope)
+    }
+
+    fn cursor_tick(&self) -> u32 {
+        self.column * COLUMN_TICKS
+    }
+
+    /// Points need not be on the grid, so any point in a column counts.
+    fn point_in_column(envelope: &Envelope, column: u32) -> Option<usize> {
+        let start = column * COLUMN_TICKS;
+        let index = envelope.points().partition_point(|point| point.tick < start);
+
+        envelope.points().get(index)
+            .filter(|point| point.tick < start + COLUMN_TICKS)
+            .map(|_| index)
+    }
+
+    fn scroll_into_view(&mut self, columns: u32) {
+        if self.column < self.scroll {
+            self.scroll = self.column;
+        } else if self.column >= self.scroll + columns {
+            self.scroll = self.column + 1 - columns;
+        }
+    }
+
+    /// Take a point along to where the cursor went. It stops at the points
+    /// either side of it, and the cursor with it.
+    fn drag(&mut self, project: &mut Project) {
+        let Some(index) = self.grabbed else { return };
+        let (tick, value) = (self.cursor_tick(), self.value);
+        let Some(envelope) = self.envelope_mut(project) else { return };
+
+        if let Some(tick) = envelope.move_point(index, tick, value) {
+            self.column = tick / COLUMN_TICKS;
+        }
+    }
+
+    fn insert_point(&self, project: &mut Project) {
+        let point = Point { tick: self.cursor_tick(), value: self.value, curve: Curve::Linear };
+
+        if let Some(envelope) = self.envelope_mut(project) {
+            envelope.insert(point);
+        }
+    }
+
+    /// Put down the point being moved, pick up the one under the cursor,
+    /// or add one there.
+    fn select(&mut self, project: &mut Project) {
+        if self.grabbed.take().is_some() {
+            return;
+        }
+
+        let Some(lane) = self.lane(project) else { return };
+
+        match Self::point_in_column(&lane.envelope, self.column) {
+            Some(index) => {
+                self.value = lane.envelope.points()[index].value;
+                self.grabbed = Some(index);
+            }
+
+            None => self.insert_point(project),
+        }
+    }
+
+    fn delete_point(&mut self, project: &mut Project) {
+        let column = self.column;
+        let Some(envelope) = self.envelope_mut(project) else { return };
+
+        if let Some(index) = Self::point_in_column(envelope, column) {
+            envelope.remove(index);
+            self.grabbed = None;
+        }
+    }
+
+    fn cycle_curve(&self, project: &mut Project, steps: i32) {
+        let column = self.column;
+        let Some(envelope) = self.envelope_mut(project) else { return };
+        let Some(index) = Self::point_in_column(envelope, column) else { return };
+
+        if let Some(point) = envelope.point_mut(index) {
+            point.curve = point.curve.cycle(steps);
+        }
+    }
+
+    /// What the status line says about the cursor: the point under it, or
+    /// the value it would add.
+    fn status(&self, project: &Project) -> Option<String> {
+        let (id, _) = self.current(project)?;
+        let lane = self.lane(project)?;
+        let param = project.param(id, &lane.target)?;
+        let position = project.tempo_map().position(self.cursor_tick());
+
+        let text = match Self::point_in_column(&lane.envelope, self.column) {
+            Some(index) => {
+                let point = lane.envelope.points()[index];
+                let moving = if self.grabbed.is_some() { " moving" } else { "" };
+                format!("{} {}{moving}", param.format(param.denormalize(point.value)), point.curve.name())
+            }
+
+            None => param.format(param.denormalize(self.value)),
+        };
+
+        Some(format!(" {text} {position} "))
+    }
+}
+
+impl Window for AutomationState {
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::Automation)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context) {
+        let lanes = Self::lanes(ctx.project).len();
+        self.lane = self.lane.min(lanes.saturating_sub(1));
+
+        let title = match self.current(ctx.project).zip(self.lane(ctx.project)) {
+            Some(((id, _), lane)) => format!(
+                "Automation ─ {} {} ({}/{lanes})",
+                ctx.project.channel_name(id).unwrap_or("?"),
+                lane.target.name(),
+                self.lane + 1,
+            ),
+            None => "Automation".to_string(),
+        };
+
+        let mut block = UIStyle::window_border(&title, focused);
+        if let Some(status) = self.status(ctx.project) {
+            block = block.title_bottom(Line::from(status).right_aligned());
+        }
+
+        frame.render_widget(&block, area);
+
+        frame.render_stateful_widget(Automation::new(ctx), block.inner(area), self);
+    }
+
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand> {
+        match cmd {
+            LocalCommand::MoveLocalCursor { dx, dy } => {
+                self.column = self.column.saturating_add_signed(dx);
+                self.value = (self.value + dy as f64 * VALUE_STEP).clamp(0.0, 1.0);
+                self.drag(ctx.project);
+            }
+
+            LocalCommand::MoveTime { unit, count } => {
+                self.column = ctx.project.tempo_map().step(self.cursor_tick(), unit, count) / COLUMN_TICKS;
+                self.drag(ctx.project);
+            }
+
+            LocalCommand::MoveToEdge { edge } => {
+                match edge {
+                    Edge::Start => self.column = 0,
+                    Edge::End => self.column = (ctx.project.length() / COLUMN_TICKS).saturating_sub(1),
+                    Edge::Top => self.value = 1.0,
+                    Edge::Bottom => self.value = 0.0,
+                }
+
+                self.drag(ctx.project);
+            }
+
+            LocalCommand::Cycle { delta } => {
+                let lanes = Self::lanes(ctx.project).len().max(1);
+                self.lane = (self.lane as i64 + delta as i64).rem_euclid(lanes as i64) as usize;
+                self.grabbed = None;
+            }
+
+            LocalCommand::Select => self.select(ctx.project),
+
+            // Adding in insert mode moves on, to draw a run of points.
+            LocalCommand::InsertNote => {
+                self.insert_point(ctx.project);
+                self.column += 1;
+            }
+
+            LocalCommand::DeleteNote => self.delete_point(ctx.project),
+
+            // Lengthening and shortening go through the curves instead.
+            LocalCommand::ResizeNote { delta } => self.cycle_curve(ctx.project, delta),
+
+            _ => (),
+        }
+
+        None
+    }
+
+    fn track(&self, project: &Project) -> Option<TrackId> {
+        self.channel(project).filter(|id| project.track(*id).is_some())
+    }
+
+    /// The track or bus of the lane shown, or the first one while nothing
+    /// is automated, so that there is something to `:automate`.
+    fn channel(&self, project: &Project) -> Option<usize> {
+        match self.current(project) {
+            Some((id, _)) => Some(id),
+            None => project.channels().first().copied(),
+        }
+    }
+}
@@ -27,15 +27,6 @@ impl<'a> ButtonListState<'a> {
         }
     }
 
-    pub fn hovered(&mut self, hovered: Option<usize>) -> &mut Self {
-        self.hovered = hovered;
-        self
-    }
-
-    pub fn add_button(&mut self, button: Button<'a>) {
-        self.items.push(button);
-    }
-
     pub fn first_button(&mut self) {
         if self.items.is_empty() { self.hovered = None; return; }
 
@@ -48,21 +39,6 @@ impl<'a> ButtonListState<'a> {
         self.hovered = Some(self.items.len().saturating_sub(1));
     }
 
-    pub fn next_button(&mut self) {
-        if self.items.is_empty() { self.hovered = None; return; }
-
-        self.hovered = Some(self.hovered
-            .map_or(0, |i| (i + 1).min(self.items.len() - 1))
-        );
-    }
-
-    pub fn previous_button(&mut self) {
-        if self.items.is_empty() { self.hovered = None; return; }
-        
-        self.hovered = Some(self.hovered
-            .map_or(0, |i| i.saturating_sub(1)));
-    }
-
     pub fn jump_buttons(&mut self, count: i32) {
         if self.items.is_empty() { self.hovered = None; return; }
         
@@ -70,10 +46,6 @@ impl<'a> ButtonListState<'a> {
             .map_or(0, |i| (i.saturating_add_signed(count as isize))
             .min(self.items.len() - 1)));
     }
-
-    pub fn no_button(&mut self) {
-        self.hovered = None;
-    }
 }
 
 pub struct ButtonList<'a> {
@@ -100,11 +72,6 @@ impl<'a> ButtonList<'a> {
         self.style = style;
         self
     }
-
-    pub fn hovered_style(mut self, style: Style) -> ButtonList<'a> {
-        self.hovered_style = style;
-        self
-    }
 }
 
 impl<'a> StatefulWidget for ButtonList<'a> {
@@ -5,6 +5,7 @@ use ratatui::{
 };
 
 use crate::{AppState, input::Mode};
+use crate::audio::transport::PlayState;
 
 
 pub struct CommandLine;
@@ -17,7 +18,10 @@ impl CommandLine {
     ) {
         frame.render_widget(Paragraph::new(
             match state.mode {
-                Mode::Normal => Self::normal_line(state, area.width),
+                Mode::Normal | Mode::Visual | Mode::VisualBlock => {
+                    Self::normal_line(state, area.width)
+                }
+
                 Mode::Insert => Line::from(Self::get_mode(state)),
                 Mode::Command => Self::command_line(state),
             }), area
@@ -25,19 +29,49 @@ impl CommandLine {
     }
 
     fn normal_line(state: &AppState, width: u16) -> Line<'_> {
-        let left = Self::get_mode(state);
-        let right = state.input_state.display();
-        let spacing = left.len() + right.len();
-        
-        Line::from(vec![
-            Span::styled(left,
+        let (left, left_style) = match &state.message {
+            Some(message) => (message.clone(), Style::default()),
+            None => (
+                Self::get_mode(state),
                 Style::default().add_modifier(Modifier::BOLD),
             ),
+        };
+
+        let selection = match state.mode {
+            Mode::Visual | Mode::VisualBlock => state.windows.selection(&state.project),
+            _ => None,
+        };
+
+        let right = format!(
+            "{}{}  {}",
+            selection.map_or(String::new(), |selection| format!("{selection}  ")),
+            state.input_state.display(),
+            Self::transport(state),
+        );
+        let spacing = left.chars().count() + right.chars().count();
+
+        Line::from(vec![
+            Span::styled(left, left_style),
             Span::raw(" ".repeat((width as usize).saturating_sub(spacing))),
             Span::raw(right),
         ])
     }
 
+    /// Play state and song position, as bar.beat.tick.
+    fn transport(state: &AppState) -> String {
+        let transport = state.audio.transport();
+        let position = state.project.tempo_map().position(transport.tick as u32);
+
+        let symbol = match transport.state {
+            PlayState::Playing => '▶',
+            PlayState::Paused => '‖',
+            PlayState::Stopped => '■',
+        };
+        let looping = if state.project.loop_region().is_some() { " ⟳" } else { "" };
+
+        format!("{symbol} {position}{looping}")
+    }
+
     fn command_line(state: &AppState) -> Line<'_> {
         let cmd = &state.command_state;
 
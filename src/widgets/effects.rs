@@ -0,0 +1,213 @@
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::StatefulWidget,
+};
+
+use crate::effect::EffectSpec;
+use crate::input::{Edge, EditorCommand, LocalCommand};
+use crate::project::{Project, TrackId};
+use crate::widgets::theme::UIStyle;
+use crate::window::{Context, Window, WindowKind};
+
+const NAME_WIDTH: u16 = 14;
+const VALUE_WIDTH: u16 = 14;
+
+/// A line of the window: an effect, or one of its parameters.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Effect(usize),
+    Param(usize, usize),
+}
+
+pub struct Effects<'a, 'b> {
+    ctx: &'a Context<'b>,
+}
+
+impl<'a, 'b> Effects<'a, 'b> {
+    pub fn new(ctx: &'a Context<'b>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl StatefulWidget for Effects<'_, '_> {
+    type State = EffectsState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let Some(id) = state.channel(self.ctx.project) else {
+            buf.set_string(area.x + 1, area.y, "No tracks or buses", Style::default().fg(Color::DarkGray));
+            return;
+        };
+        let inserts = self.ctx.project.inserts(id).unwrap_or_default();
+
+        if inserts.is_empty() {
+            let hint = "No effects, add one with :insert";
+            buf.set_string(area.x + 1, area.y, hint, Style::default().fg(Color::DarkGray));
+            return;
+        }
+
+        let rows = EffectsState::rows(inserts);
+        let first = (state.row + 1).saturating_sub(area.height as usize);
+
+        for (i, row) in rows.into_iter().enumerate().skip(first).take(area.height as usize) {
+            let y = area.y + (i - first) as u16;
+            let selected = i == state.row;
+            let highlight = |style: Style| if selected { style.add_modifier(Modifier::REVERSED) } else { style };
+
+            match row {
+                Row::Effect(insert) => {
+                    let effect = &inserts[insert];
+                    let style = match effect.bypassed {
+                        true => Style::default().fg(Color::DarkGray),
+                        false => Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+                    };
+                    let bypassed = if effect.bypassed { " (bypassed)" } else { "" };
+
+                    let text = format!(" {} {}{bypassed}", insert + 1, effect.kind.name());
+                    buf.set_stringn(area.x, y, text, area.width as usize, highlight(style));
+                }
+
+                Row::Param(insert, index) => {
+                    let effect = &inserts[insert];
+                    let param = &effect.kind.params()[index];
+                    let value = effect.value(index);
+
+                    let name = format!("   {}", param.name);
+                    buf.set_stringn(area.x, y, name, NAME_WIDTH as usize, highlight(Style::default().fg(Color::Gray)));
+
+                    let text = param.format(value);
+                    buf.set_stringn(area.x + NAME_WIDTH, y, text, VALUE_WIDTH as usize, Style::default().fg(Color::White));
+
+                    // How far along its range the value is.
+                    let x = area.x + NAME_WIDTH + VALUE_WIDTH;
+                    let width = area.right().saturating_sub(x + 1);
+                    let filled = (param.normalize(value) * width as f64).round() as u16;
+
+                    for dx in 0..width {
+                        let (symbol, color) = if dx < filled { ('█', Color::Green) } else { ('·', Color::DarkGray) };
+                        if let Some(cell) = buf.cell_mut((x + dx, y)) {
+                            cell.set_char(symbol).set_style(Style::default().fg(color));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every effect on a track or bus, with its parameters.
+pub struct EffectsState {
+    /// Which of the project's channels is shown.
+    channel: usize,
+    row: usize,
+}
+
+impl EffectsState {
+    pub fn new() -> Self {
+        Self {
+            channel: 0,
+            row: 0,
+        }
+    }
+
+    fn rows(inserts: &[EffectSpec]) -> Vec<Row> {
+        inserts.iter().enumerate()
+            .flat_map(|(insert, effect)| {
+                let params = (0..effect.kind.params().len()).map(move |index| Row::Param(insert, index));
+                std::iter::once(Row::Effect(insert)).chain(params)
+            })
+            .collect()
+    }
+
+    fn row(&self, project: &Project) -> Option<Row> {
+        let inserts = project.inserts(self.channel(project)?)?;
+        Self::rows(inserts).get(self.row).copied()
+    }
+
+    /// Move the parameter under the cursor by `steps` hundredths of its range.
+    fn adjust(&self, project: &mut Project, steps: i32) {
+        let Some(Row::Param(insert, index)) = self.row(project) else { return };
+        let Some(id) = self.channel(project) else { return };
+        let Some(effect) = project.inserts_mut(id).and_then(|inserts| inserts.get_mut(insert)) else { return };
+
+        let param = effect.kind.params()[index];
+        let value = param.step(effect.value(index), steps);
+        effect.set(param.name, value);
+    }
+
+    fn toggle_bypass(&self, project: &mut Project) {
+        let (Some(Row::Effect(insert)) | Some(Row::Param(insert, _))) = self.row(project) else { return };
+        let Some(id) = self.channel(project) else { return };
+        let Some(effect) = project.inserts_mut(id).and_then(|inserts| inserts.get_mut(insert)) else { return };
+
+        effect.bypassed = !effect.bypassed;
+    }
+}
+
+impl Window for EffectsState {
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::Effects)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context) {
+        self.channel = self.channel.min(ctx.project.channels().len().saturating_sub(1));
+
+        let rows = self.channel(ctx.project)
+            .and_then(|id| ctx.project.inserts(id))
+            .map_or(0, |inserts| Self::rows(inserts).len());
+        self.row = self.row.min(rows.saturating_sub(1));
+
+        let title = match self.channel(ctx.project).and_then(|id| ctx.project.channel_name(id)) {
+            Some(name) => format!("Effects ─ {name}"),
+            None => "Effects".to_string(),
+        };
+
+        let block = UIStyle::window_border(&title, focused);
+        frame.render_widget(&block, area);
+
+        frame.render_stateful_widget(Effects::new(ctx), block.inner(area), self);
+    }
+
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand> {
+        let rows = self.channel(ctx.project)
+            .and_then(|id| ctx.project.inserts(id))
+            .map_or(0, |inserts| Self::rows(inserts).len());
+        let last_row = rows.saturating_sub(1);
+
+        match cmd {
+            LocalCommand::MoveLocalCursor { dx, dy } => {
+                self.row = self.row.saturating_add_signed(-dy as isize).min(last_row);
+
+                if dx != 0 {
+                    self.adjust(ctx.project, dx);
+                }
+            }
+
+            // Beats and bars step through the tracks and buses instead.
+            LocalCommand::MoveTime { count, .. } => {
+                let last = ctx.project.channels().len().saturating_sub(1);
+                self.channel = self.channel.saturating_add_signed(count as isize).min(last);
+                self.row = 0;
+            }
+
+            LocalCommand::MoveToEdge { edge: Edge::Top } => self.row = 0,
+            LocalCommand::MoveToEdge { edge: Edge::Bottom } => self.row = last_row,
+
+            LocalCommand::Select => self.toggle_bypass(ctx.project),
+
+            _ => (),
+        }
+
+        None
+    }
+
+    fn track(&self, project: &Project) -> Option<TrackId> {
+        self.channel(project).filter(|id| project.track(*id).is_some())
+    }
+
+    fn channel(&self, project: &Project) -> Option<usize> {
+        project.channels().get(self.channel).copied()
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::StatefulWidget,
+};
+
+use crate::audio::mixer::Levels;
+use crate::dsp::gain_to_db;
+use crate::input::{Edge, EditorCommand, LocalCommand};
+use crate::project::{
+    Project, TrackId,
+    routing::{MASTER, Output},
+};
+use crate::widgets::theme::UIStyle;
+use crate::window::{Context, Window, WindowKind};
+
+const STRIP_WIDTH: u16 = 10;
+
+/// Range of the faders and meters, in decibels.
+const FLOOR_DB: f64 = -60.0;
+const CEILING_DB: f64 = 6.0;
+
+const GAIN_STEP_DB: f64 = 1.0;
+const PAN_STEP: f64 = 0.05;
+
+/// How much of the shown peak is kept from one frame to the next.
+const PEAK_DECAY: f32 = 0.85;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Gain,
+    Pan,
+}
+
+pub struct Mixer<'a, 'b> {
+    ctx: &'a Context<'b>,
+}
+
+impl<'a, 'b> Mixer<'a, 'b> {
+    pub fn new(ctx: &'a Context<'b>) -> Self {
+        Self { ctx }
+    }
+
+    /// How far up a column of `height` cells a level in decibels reaches.
+    fn height(db: f64, height: u16) -> u16 {
+        let fraction = ((db - FLOOR_DB) / (CEILING_DB - FLOOR_DB)).clamp(0.0, 1.0);
+        (fraction * height as f64).round() as u16
+    }
+
+    fn level_color(db: f64) -> Color {
+        match db {
+            db if db > 0.0 => Color::Red,
+            db if db > -6.0 => Color::Yellow,
+            _ => Color::Green,
+        }
+    }
+
+    fn render_strip(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        id: usize,
+        levels: Levels,
+        state: &MixerState,
+        selected: bool,
+    ) {
+        let project = &*self.ctx.project;
+        let (name, gain, pan, output, buttons) = match (project.track(id), project.bus(id)) {
+            (Some(track), _) => (&track.name, track.gain, track.pan, track.output, vec![
+                ("M", track.muted, Color::Yellow),
+                ("S", track.solo, Color::LightMagenta),
+                ("R", track.armed, Color::Red),
+            ]),
+
+            (None, Some(bus)) => (&bus.name, bus.gain, bus.pan, bus.output, vec![
+                ("M", bus.muted, Color::Yellow),
+            ]),
+
+            (None, None) => return,
+        };
+
+        let name_style = match selected {
+            true => Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            false => Style::default().fg(Color::Gray),
+        };
+        buf.set_stringn(area.x + 1, area.y, name, area.width as usize - 2, name_style);
+
+        for (i, (label, on, color)) in buttons.into_iter().enumerate() {
+            let style = match on {
+                true => Style::default().fg(Color::Black).bg(color),
+                false => Style::default().fg(Color::DarkGray),
+            };
+            buf.set_string(area.x + 1 + i as u16 * 2, area.y + 1, label, style);
+        }
+
+        // The fader and both meters fill what is left above the readouts.
+        let top = area.y + 2;
+        let height = area.height.saturating_sub(5);
+        let fader = Self::height(gain, height);
+
+        for row in 0..height {
+            let y = top + height - 1 - row;
+
+            let fader_style = match selected && state.control == Control::Gain {
+                true => Style::default().fg(Color::LightGreen),
+                false => Style::default().fg(Color::Gray),
+            };
+            let fader_char = if row + 1 == fader.max(1) { '━' } else { '│' };
+            buf.set_string(area.x + 2, y, fader_char.to_string(), fader_style);
+
+            for side in 0..2 {
+                let rms = gain_to_db(levels.rms[side] as f64);
+                let peak = gain_to_db(levels.peak[side] as f64);
+                let row_db = FLOOR_DB + (row as f64 + 0.5) / height as f64 * (CEILING_DB - FLOOR_DB);
+
+                let cell = match () {
+                    _ if row < Self::height(rms, height) => Some('█'),
+                    _ if row + 1 == Self::height(peak, height) => Some('▔'),
+                    _ => None,
+                };
+
+                if let Some(cell) = cell {
+                    let style = Style::default().fg(Self::level_color(row_db));
+                    buf.set_string(area.x + 5 + side as u16, y, cell.to_string(), style);
+                }
+            }
+        }
+
+        let readout = |control: Control| match selected && state.control == control {
+            true => Style::default().fg(Color::White).add_modifier(Modifier::REVERSED),
+            false => Style::default().fg(Color::Gray),
+        };
+
+        let pan = match (pan * 100.0).round() as i32 {
+            0 => "C".to_string(),
+            pan if pan < 0 => format!("L{}", -pan),
+            pan => format!("R{pan}"),
+        };
+
+        let output = match output {
+            _ if id == MASTER => "out".to_string(),
+            Output::Master => "→Master".to_string(),
+            Output::Bus(bus) => format!("→{}", project.channel_name(bus).unwrap_or("?")),
+        };
+
+        buf.set_string(area.x + 1, top + height, format!("{gain:+.1}dB"), readout(Control::Gain));
+        buf.set_string(area.x + 1, top + height + 1, pan, readout(Control::Pan));
+        buf.set_stringn(area.x + 1, top + height + 2, output, area.width as usize - 2, Style::default().fg(Color::DarkGray));
+    }
+}
+
+impl StatefulWidget for Mixer<'_, '_> {
+    type State = MixerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if area.height < 7 {
+            return;
+        }
+
+        let channels = self.ctx.project.channels();
+
+        // Levels fall back slowly rather than flickering with every block.
+        for id in &channels {
+            let Some(meter) = self.ctx.meters.get(id) else { continue };
+            let levels = meter.take();
+            let shown = state.levels.entry(*id).or_default();
+
+            for side in 0..2 {
+                shown.peak[side] = levels.peak[side].max(shown.peak[side] * PEAK_DECAY);
+                shown.rms[side] = levels.rms[side];
+            }
+        }
+
+        // Scroll so that the selected strip is always in view.
+        let shown = (area.width / STRIP_WIDTH).max(1) as usize;
+        let first = (state.strip + 1).saturating_sub(shown);
+
+        for (i, id) in channels.into_iter().enumerate().skip(first).take(shown) {
+            let strip = Rect {
+                x: area.x + (i - first) as u16 * STRIP_WIDTH,
+                width: STRIP_WIDTH,
+                ..area
+            };
+
+            let levels = state.levels.get(&id).copied().unwrap_or_default();
+            self.render_strip(strip, buf, id, levels, state, i == state.strip);
+
+            for y in strip.top()..strip.bottom() {
+                if let Some(cell) = buf.cell_mut((strip.right() - 1, y)) {
+                    cell.set_char('│').set_style(Style::default().fg(Color::DarkGray));
+                }
+            }
+        }
+    }
+}
+
+pub struct MixerState {
+    strip: usize,
+    control: Control,
+    levels: HashMap<TrackId, Levels>,
+}
+
+impl MixerState {
+    pub fn new() -> Self {
+        Self {
+            strip: 0,
+            control: Control::Gain,
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Move the selected control `steps` steps up, or right for the pan.
+    fn adjust(&self, project: &mut Project, steps: i32) {
+        let Some(id) = self.channel(project) else { return };
+        let Some((gain, pan)) = project.fader_mut(id) else { return };
+
+        match self.control {
+            Control::Gain => {
+                *gain = (*gain + steps as f64 * GAIN_STEP_DB).clamp(FLOOR_DB, CEILING_DB);
+            }
+
+            Control::Pan => {
+                let moved = *pan + steps as f64 * PAN_STEP;
+                *pan = ((moved / PAN_STEP).round() * PAN_STEP).clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
+
+impl Window for MixerState {
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::Mixer)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context) {
+        self.strip = self.strip.min(ctx.project.channels().len().saturating_sub(1));
+
+        let block = UIStyle::window_border("Mixer", focused);
+        frame.render_widget(&block, area);
+
+        frame.render_stateful_widget(Mixer::new(ctx), block.inner(area), self);
+    }
+
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand> {
+        let last = ctx.project.channels().len().saturating_sub(1);
+
+        match cmd {
+            LocalCommand::MoveLocalCursor { dx, dy } => {
+                self.strip = self.strip.saturating_add_signed(dx as isize).min(last);
+
+                if dy != 0 {
+                    self.adjust(ctx.project, dy);
+                }
+            }
+
+            LocalCommand::MoveToEdge { edge: Edge::Start } => self.strip = 0,
+            LocalCommand::MoveToEdge { edge: Edge::End } => self.strip = last,
+
+            LocalCommand::Select => {
+                self.control = match self.control {
+                    Control::Gain => Control::Pan,
+                    Control::Pan => Control::Gain,
+                };
+            }
+
+            _ => (),
+        }
+
+        None
+    }
+
+    fn track(&self, project: &Project) -> Option<TrackId> {
+        self.channel(project).filter(|id| project.track(*id).is_some())
+    }
+
+    fn channel(&self, project: &Project) -> Option<usize> {
+        project.channels().get(self.strip).copied()
+    }
+}
@@ -1,6 +1,10 @@
+pub mod automation;
+pub mod effects;
+pub mod mixer;
 pub mod pianoroll;
 pub mod commandline;
 pub mod splashscreen;
 pub mod splitselect;
+pub mod stepsequencer;
 pub mod theme;
 pub mod buttonlist;
@@ -1,91 +1,622 @@
+use std::ops::Range;
+
 use ratatui::{
     Frame,
     buffer::Buffer,
     layout::Rect,
-    style::{Style, Color},
+    style::{Style, Color, Modifier},
     text::Line,
-    widgets::{StatefulWidget, Block, Borders, BorderType}
+    widgets::StatefulWidget,
 };
 
+use crate::dsp::{is_black_key, pitch_name};
+use crate::project::{
+    PPQ, PatternId, Project, TrackId,
+    pattern::{Note, NoteRange, NoteState, Pattern},
+    tempo::{BarPosition, TempoMap, TimeUnit},
+    track::InstrumentSpec,
+};
 use crate::widgets::theme::UIStyle;
-use crate::window::Window;
-use crate::input::LocalCommand;
+use crate::window::{Context, Window, WindowKind};
+use crate::input::{Edge, EditorCommand, LocalCommand, Motion, Operation};
+use crate::operator::{self, Target};
 
-struct Note {
-    freq: u32,
-    start: u32,
-    length: u32,
+/// A column is a 16th note at zoom 1, every zoom level divides it further.
+const COLUMN_TICKS: u32 = PPQ / 4;
+
+/// Pitch name plus the edge of the keyboard.
+const GUTTER_WIDTH: u16 = 5;
+const VELOCITY: u8 = 100;
+
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+struct GridPos {
+    column: u32,
+    pitch: u8,
 }
 
-struct Pattern {
-    notes: Vec<Note>
+/// A visual mode selection, from where it was started to the cursor.
+#[derive(Copy, Clone)]
+struct Selection {
+    anchor: GridPos,
+    /// Only the pitches between the anchor and the cursor, rather than
+    /// every one.
+    block: bool,
 }
 
-#[derive(Default, Copy, Clone, PartialEq, Eq)]
-struct Pos2 {
-    x: u16, y: u16,
+pub struct PianoRoll<'a> {
+    pattern: Option<&'a Pattern>,
+    /// Bar and beat lines follow the time signature where the pattern
+    /// starts in the song.
+    tempo_map: &'a TempoMap,
+    start: u32,
+    /// Tick in the pattern that is playing right now.
+    playhead: Option<u32>,
+    /// The track is muted or soloed out.
+    muted: bool,
 }
 
-#[derive(Default)]
-pub struct PianoRoll;
-impl StatefulWidget for PianoRoll {
+impl<'a> PianoRoll<'a> {
+    pub fn new(
+        pattern: Option<&'a Pattern>,
+        tempo_map: &'a TempoMap,
+        start: u32,
+        playhead: Option<u32>,
+        muted: bool,
+    ) -> Self {
+        Self { pattern, tempo_map, start, playhead, muted }
+    }
+}
+
+impl StatefulWidget for PianoRoll<'_> {
     type State = PianoRollState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if area.width <= GUTTER_WIDTH || area.height == 0 { return; }
+
+        let notes = self.pattern.map_or(&[][..], Pattern::notes);
+        let soloing = self.pattern.is_some_and(Pattern::is_soloing);
+
+        let columns = (area.width - GUTTER_WIDTH) as u32;
+        state.scroll_into_view(columns, area.height);
+
+        let column_ticks = state.column_ticks();
+        let playhead = self.playhead.map(|tick| tick / column_ticks);
+        let selection = state.selection_range();
+
+        let grid: Vec<char> = (0..columns)
+            .map(|dx| {
+                let tick = (state.scroll.column + dx) * column_ticks;
+                let position = self.tempo_map.position(self.start + tick);
+
+                match position {
+                    BarPosition { beat: 0, tick: 0, .. } => '│',
+                    BarPosition { tick: 0, .. } => '·',
+                    _ => ' ',
+                }
+            })
+            .collect();
+
         for dy in 0..area.height {
-            for dx in 0..area.width {
-                let x = area.x + dx;
-                let y = area.y + dy;
+            let pitch = u8::try_from(dy).ok()
+                .and_then(|dy| state.scroll.pitch.checked_sub(dy));
+            let Some(pitch) = pitch else { break };
+            let y = area.y + dy;
 
-                if let Some(cell) = buf.cell_mut((x, y)) {
-                    if dx == 3 {
-                        cell.set_style(Style::default().fg(Color::White));
-                        cell.set_char('▎');
-                    } else if state.selected == (Pos2 {x, y}) {
+            let mut key_style = if is_black_key(pitch) {
+                Style::default().fg(Color::White).bg(Color::Black)
+            } else {
+                Style::default().fg(Color::Black).bg(Color::White)
+            };
+
+            if pitch == state.cursor.pitch {
+                key_style = key_style.add_modifier(Modifier::BOLD);
+            }
+
+            buf.set_stringn(
+                area.x,
+                y,
+                format!("{:<4}", pitch_name(pitch)),
+                GUTTER_WIDTH as usize - 1,
+                key_style,
+            );
+
+            if let Some(cell) = buf.cell_mut((area.x + GUTTER_WIDTH - 1, y)) {
+                cell.set_style(Style::default().fg(Color::White));
+                cell.set_char('▎');
+            }
+
+            for dx in 0..columns {
+                let column = state.scroll.column + dx;
+                let tick = column * column_ticks;
+                let x = area.x + GUTTER_WIDTH + dx as u16;
+
+                let Some(cell) = buf.cell_mut((x, y)) else { continue };
+                let is_cursor = state.cursor == (GridPos { column, pitch });
+                let is_playhead = playhead == Some(column);
+                let is_selected = selection.as_ref()
+                    .is_some_and(|range| range.ticks.contains(&tick) && range.pitches.contains(&pitch));
+
+                match notes.iter().find(|note| note.covers(pitch, &(tick..tick + column_ticks))) {
+                    Some(note) => {
+                        let is_start = note.start >= tick;
+                        let heard = !self.muted && note.is_audible(soloing);
+                        let mut style = Style::default().fg(match note.state {
+                            _ if is_playhead && heard => Color::Yellow,
+                            _ if !heard => Color::DarkGray,
+                            NoteState::Solo if is_start => Color::LightMagenta,
+                            NoteState::Solo => Color::Magenta,
+                            _ if is_start => Color::LightCyan,
+                            _ => Color::Cyan,
+                        });
+
+                        if is_cursor {
+                            style = style.add_modifier(Modifier::REVERSED);
+                        }
+
+                        cell.set_style(style);
+                        cell.set_char(match () {
+                            _ if note.state == NoteState::Muted => '░',
+                            _ if is_start => '▐',
+                            _ => '█',
+                        });
+                    }
+
+                    None if is_cursor => {
                         cell.set_style(Style::default().fg(Color::White));
                         cell.set_char('█');
-                    } else {
+                    }
+
+                    None if is_playhead => {
+                        cell.set_style(Style::default().fg(Color::Yellow));
+                        cell.set_char('│');
+                    }
+
+                    None => {
                         cell.set_style(Style::default().fg(Color::DarkGray));
-                        cell.set_char('·');
+                        cell.set_char(grid[dx as usize]);
                     }
                 }
+
+                if is_selected {
+                    cell.set_bg(Color::Blue);
+                }
             }
         }
     }
 }
 
 pub struct PianoRollState {
-    selected: Pos2, 
+    pattern: Option<PatternId>,
+    cursor: GridPos,
+    /// Top left of the visible part of the grid.
+    scroll: GridPos,
+    /// Length of inserted notes, in columns.
     note_size: u8,
-    notes: Vec<Note>,
     zoom: u8,
+    selection: Option<Selection>,
 }
 
 impl PianoRollState {
-    pub fn new() -> Self {
+    /// Opens the first pattern of the first track that is not a drum kit.
+    pub fn new(project: &Project) -> Self {
+        let pattern = project
+            .find_track(|instrument| *instrument != InstrumentSpec::DrumKit)
+            .and_then(|track| project.first_pattern(track));
+
         Self {
-            selected: Pos2 { x: 2, y: 2 },
+            pattern,
+            cursor: GridPos { column: 0, pitch: 60 },
+            scroll: GridPos { column: 0, pitch: 72 },
             note_size: 4,
-            notes: Vec::new(), 
             zoom: 1,
+            selection: None,
+        }
+    }
+
+    fn column_ticks(&self) -> u32 {
+        COLUMN_TICKS / self.zoom.max(1) as u32
+    }
+
+    fn cursor_tick(&self) -> u32 {
+        self.cursor.column * self.column_ticks()
+    }
+
+    /// The ticks of the column under the cursor.
+    fn cursor_ticks(&self) -> Range<u32> {
+        self.cursor_tick()..self.cursor_tick() + self.column_ticks()
+    }
+
+    fn pattern_mut<'a>(&self, project: &'a mut Project) -> Option<&'a mut Pattern> {
+        project.pattern_mut(self.pattern?)
+    }
+
+    fn scroll_into_view(&mut self, columns: u32, rows: u16) {
+        let rows = rows.min(128) as u8;
+
+        if self.cursor.column < self.scroll.column {
+            self.scroll.column = self.cursor.column;
+        } else if self.cursor.column >= self.scroll.column + columns {
+            self.scroll.column = self.cursor.column + 1 - columns;
+        }
+
+        if self.cursor.pitch > self.scroll.pitch {
+            self.scroll.pitch = self.cursor.pitch;
+        } else if self.scroll.pitch - self.cursor.pitch >= rows {
+            self.scroll.pitch = (self.cursor.pitch + rows - 1).min(127);
+        }
+    }
+
+    /// Add a note of `note_size` at the cursor, replacing whatever it
+    /// overlaps on the same pitch, and move past it.
+    fn insert_note(&mut self, project: &mut Project) {
+        let note = Note {
+            pitch: self.cursor.pitch,
+            velocity: VELOCITY,
+            start: self.cursor_tick(),
+            length: self.note_size as u32 * self.column_ticks(),
+            state: NoteState::Normal,
+        };
+
+        let Some(pattern) = self.pattern_mut(project) else { return };
+        pattern.insert(note);
+
+        self.cursor.column += self.note_size as u32;
+    }
+
+    fn delete_note(&mut self, project: &mut Project) {
+        let (pitch, ticks) = (self.cursor.pitch, self.cursor_ticks());
+        let Some(pattern) = self.pattern_mut(project) else { return };
+
+        if let Some(index) = pattern.note_at(pitch, ticks) {
+            pattern.remove(index);
+        }
+    }
+
+    /// Grow or shrink the note under the cursor in steps of `note_size`,
+    /// never below a single column.
+    fn resize_note(&mut self, project: &mut Project, delta: i32) {
+        let (pitch, ticks) = (self.cursor.pitch, self.cursor_ticks());
+        let column_ticks = self.column_ticks() as i64;
+        let step = self.note_size as i64 * column_ticks;
+
+        let Some(pattern) = self.pattern_mut(project) else { return };
+        let Some(index) = pattern.note_at(pitch, ticks) else { return };
+
+        let length = pattern.notes()[index].length as i64 + delta as i64 * step;
+        pattern.resize(index, length.max(column_ticks) as u32);
+    }
+
+    /// The columns from the anchor to the cursor, both included.
+    fn selection_range(&self) -> Option<NoteRange> {
+        let Selection { anchor, block } = self.selection?;
+        let (cursor, column_ticks) = (self.cursor, self.column_ticks());
+
+        let ticks = anchor.column.min(cursor.column) * column_ticks
+            ..(anchor.column.max(cursor.column) + 1) * column_ticks;
+        let pitches = match block {
+            true => anchor.pitch.min(cursor.pitch)..=anchor.pitch.max(cursor.pitch),
+            false => 0..=127,
+        };
+
+        Some(NoteRange { ticks, pitches })
+    }
+
+    /// What a motion from the cursor covers. Moving in time takes in
+    /// every pitch over the ticks passed, moving in pitch takes whole rows,
+    /// and no motion at all `count` notes from the one under the cursor.
+    fn motion_range(&self, project: &Project, motion: Motion, count: usize) -> Option<NoteRange> {
+        let pattern = project.pattern(self.pattern?)?;
+        let tick = self.cursor_tick();
+        let pitch = self.cursor.pitch;
+        let distance = u32::try_from(count).unwrap_or(u32::MAX).saturating_mul(self.column_ticks());
+        let steps = i32::try_from(count).unwrap_or(i32::MAX);
+
+        let across = |target: u32| NoteRange {
+            ticks: tick.min(target)..tick.max(target),
+            pitches: 0..=127,
+        };
+        let rows = |target: i32| {
+            let target = target.clamp(0, 127) as u8;
+            NoteRange { ticks: 0..pattern.length(), pitches: pitch.min(target)..=pitch.max(target) }
+        };
+
+        Some(match motion {
+            Motion::Left => across(tick.saturating_sub(distance)),
+            Motion::Right => across(tick.saturating_add(distance)),
+
+            Motion::Beat { forward } | Motion::Bar { forward } => {
+                let unit = if matches!(motion, Motion::Bar { .. }) { TimeUnit::Bar } else { TimeUnit::Beat };
+                let count = if forward { steps } else { -steps };
+                let start = self.start(project);
+
+                across(project.tempo_map().step(start + tick, unit, count).saturating_sub(start))
+            }
+
+            Motion::Up => rows((pitch as i32).saturating_add(steps)),
+            Motion::Down => rows((pitch as i32).saturating_sub(steps)),
+
+            Motion::Start => across(0),
+            Motion::End => across(pattern.length()),
+            Motion::Top => rows(127),
+            Motion::Bottom => rows(0),
+
+            Motion::Inner(object) => {
+                return operator::text_object(project, self.pattern?, tick, object, count);
+            }
+
+            Motion::Selection => return self.selection_range(),
+
+            // The note under the cursor and the ones after it on its row,
+            // so that a paste lays copies end to end.
+            Motion::None => {
+                let first = pattern.note_at(pitch, self.cursor_ticks())?;
+                let end = pattern.notes()[first..].iter()
+                    .filter(|note| note.pitch == pitch)
+                    .take(count.max(1))
+                    .map(Note::end)
+                    .last()?;
+
+                NoteRange { ticks: pattern.notes()[first].start..end, pitches: pitch..=pitch }
+            }
+        })
+    }
+
+    /// Start and end go to the first and last column of the pattern, top
+    /// and bottom to its highest and lowest note.
+    fn move_to_edge(&mut self, project: &Project, edge: Edge) {
+        let Some(pattern) = self.pattern.and_then(|id| project.pattern(id)) else { return };
+        let pitches = pattern.notes().iter().map(|note| note.pitch);
+
+        match edge {
+            Edge::Start => self.cursor.column = 0,
+            Edge::End => self.cursor.column = (pattern.length() / self.column_ticks()).saturating_sub(1),
+            Edge::Top => self.cursor.pitch = pitches.max().unwrap_or(127),
+            Edge::Bottom => self.cursor.pitch = pitches.min().unwrap_or(0),
         }
     }
+
+    /// Song tick of the start of the pattern.
+    fn start(&self, project: &Project) -> u32 {
+        self.pattern.and_then(|id| project.pattern_start(id)).unwrap_or(0)
+    }
+
+    /// Jump to the start of a beat or bar of the song, as the pattern is
+    /// placed in it.
+    fn move_time(&mut self, project: &Project, unit: TimeUnit, count: i32) {
+        let start = self.start(project);
+        let tick = project.tempo_map().step(start + self.cursor_tick(), unit, count);
+
+        self.cursor.column = tick.saturating_sub(start) / self.column_ticks();
+    }
 }
 
 impl Window for PianoRollState {
-    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool) {
-        let block = UIStyle::window_border("Piano Roll", focused);
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::PianoRoll)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context) {
+        let pattern = self.pattern.and_then(|id| ctx.project.pattern(id));
+        let playhead = self.pattern.and_then(|id| ctx.playhead(id));
+        let start = self.start(ctx.project);
+        let tempo_map = ctx.project.tempo_map();
+        let track = self.pattern.and_then(|id| ctx.project.pattern_track(id));
+        let muted = track.is_some_and(|track| !ctx.project.is_audible(track));
+        let title = match pattern {
+            Some(pattern) => format!(
+                "Piano Roll ─ {}{}",
+                pattern.name,
+                track.map_or(String::new(), |track| track.flags()),
+            ),
+            None => "Piano Roll".to_string(),
+        };
+
+        let block = UIStyle::window_border(&title, focused)
+            .title_bottom(Line::from(format!(
+                " {} {} len {} ",
+                pitch_name(self.cursor.pitch),
+                tempo_map.position(start + self.cursor_tick()),
+                self.note_size,
+            )).right_aligned());
+
         frame.render_widget(&block, area);
 
         frame.render_stateful_widget(
-            PianoRoll::default(),
+            PianoRoll::new(pattern, tempo_map, start, playhead, muted),
             block.inner(area),
             self,
         );
     }
 
-    fn handle_input(&mut self, cmd: LocalCommand) {
-        
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand> {
+        match cmd {
+            LocalCommand::MoveLocalCursor { dx, dy } => {
+                self.cursor.column = self.cursor.column.saturating_add_signed(dx);
+                self.cursor.pitch = (self.cursor.pitch as i32 + dy).clamp(0, 127) as u8;
+            },
+
+            LocalCommand::MoveTime { unit, count } => self.move_time(ctx.project, unit, count),
+            LocalCommand::MoveToEdge { edge } => self.move_to_edge(ctx.project, edge),
+
+            LocalCommand::Visual { block } => {
+                let anchor = self.selection.map_or(self.cursor, |selection| selection.anchor);
+                self.selection = Some(Selection { anchor, block });
+            }
+
+            LocalCommand::EndVisual => self.selection = None,
+
+            LocalCommand::InsertNote => self.insert_note(ctx.project),
+            LocalCommand::DeleteNote => self.delete_note(ctx.project),
+            LocalCommand::ResizeNote { delta } => self.resize_note(ctx.project, delta),
+
+            LocalCommand::AdjustNoteSize { delta } => {
+                self.note_size = (self.note_size as i32 + delta).clamp(1, 64) as u8;
+            },
+
+            _ => (),
+        }
+
+        None
+    }
+
+    fn operate(&mut self, operation: Operation, ctx: &mut Context) -> Option<String> {
+        let range = self.motion_range(ctx.project, operation.motion, operation.count);
+
+        if operation.motion == Motion::Selection {
+            self.selection = None;
+        }
+
+        let target = Target {
+            pattern: self.pattern?,
+            range,
+            cursor: self.cursor_tick(),
+            grid: self.column_ticks(),
+        };
+
+        operator::apply(operation, target, ctx)
+    }
+
+    fn track(&self, project: &Project) -> Option<TrackId> {
+        Some(project.pattern_track(self.pattern?)?.id)
+    }
+
+    fn selection(&self, project: &Project) -> Option<String> {
+        let range = self.selection_range()?;
+        let start = self.start(project);
+        let tempo_map = project.tempo_map();
+        let notes = self.pattern
+            .and_then(|id| project.pattern(id))
+            .map_or(0, |pattern| pattern.notes_in(&range).count());
+
+        let mut extent = format!(
+            "{}-{}",
+            tempo_map.position(start + range.ticks.start),
+            tempo_map.position(start + range.ticks.end),
+        );
+
+        if self.selection.is_some_and(|selection| selection.block) {
+            extent += &format!(" {}-{}", pitch_name(*range.pitches.start()), pitch_name(*range.pitches.end()));
+        }
+
+        Some(format!("{extent} ({})", operator::notes(notes)))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::audio::{mixer::Meters, transport::{PlayState, TransportStatus}};
+    use crate::input::Operator;
+    use crate::registers::Registers;
+
+    use super::*;
+
+    fn note(pitch: u8, column: u32) -> Note {
+        Note { pitch, velocity: VELOCITY, start: column * COLUMN_TICKS, length: COLUMN_TICKS, state: NoteState::Normal }
+    }
+
+    /// Select from column 2 on the cursor's pitch to column 0 four
+    /// semitones up, then carry out `operator` over the selection.
+    fn select(block: bool, operator: Operator) -> (PianoRollState, Registers, Project) {
+        let mut project = Project::with_default_tracks();
+        let mut registers = Registers::default();
+        let meters = Meters::new();
+        let mut state = PianoRollState::new(&project);
+
+        let pattern = state.pattern_mut(&mut project).unwrap();
+        for (pitch, column) in [(60, 0), (62, 1), (64, 2), (65, 1), (59, 2), (60, 3)] {
+            pattern.insert(note(pitch, column));
+        }
+
+        let mut ctx = Context {
+            project: &mut project,
+            registers: &mut registers,
+            transport: TransportStatus { state: PlayState::Stopped, tick: 0.0 },
+            meters: &meters,
+        };
+
+        state.handle_input(LocalCommand::MoveLocalCursor { dx: 2, dy: 0 }, &mut ctx);
+        state.handle_input(LocalCommand::Visual { block: false }, &mut ctx);
+        state.handle_input(LocalCommand::MoveLocalCursor { dx: -2, dy: 4 }, &mut ctx);
+
+        // Switching shape keeps where the selection started.
+        if block {
+            state.handle_input(LocalCommand::Visual { block: true }, &mut ctx);
+        }
+
+        let operation = Operation { operator, count: 1, motion: Motion::Selection, register: None };
+        state.operate(operation, &mut ctx);
+
+        (state, registers, project)
+    }
+
+    fn yanked(registers: &Registers) -> Vec<(u8, u32)> {
+        registers.get(None).unwrap().notes.iter()
+            .map(|note| (note.pitch, note.start / COLUMN_TICKS))
+            .collect()
+    }
+
+    #[test]
+    fn visual_takes_every_pitch_over_the_columns() {
+        let (state, registers, _) = select(false, Operator::Yank);
+
+        assert!(state.selection.is_none());
+        assert_eq!(registers.get(None).unwrap().length, 3 * COLUMN_TICKS);
+        assert_eq!(yanked(&registers), [(60, 0), (62, 1), (65, 1), (59, 2), (64, 2)]);
+    }
+
+    #[test]
+    fn visual_block_keeps_to_the_pitches_between() {
+        let (state, registers, project) = select(true, Operator::Delete);
+
+        assert_eq!(yanked(&registers), [(60, 0), (62, 1), (64, 2)]);
+
+        let left: Vec<(u8, u32)> = project.pattern(state.pattern.unwrap()).unwrap().notes().iter()
+            .map(|note| (note.pitch, note.start / COLUMN_TICKS))
+            .collect();
+        assert_eq!(left, [(65, 1), (59, 2), (60, 3)]);
+    }
+
+    #[test]
+    fn doubled_operators_take_whole_notes() {
+        let mut project = Project::with_default_tracks();
+        let mut registers = Registers::default();
+        let meters = Meters::new();
+        let mut state = PianoRollState::new(&project);
+        let id = state.pattern.unwrap();
+
+        let pattern = project.pattern_mut(id).unwrap();
+        for column in [0, 4, 8] {
+            pattern.insert(Note { length: 4 * COLUMN_TICKS, ..note(60, column) });
+        }
+        pattern.insert(note(62, 4));
+        // A note in the second bar makes room for the pastes.
+        pattern.insert(note(48, 28));
+
+        let mut ctx = Context {
+            project: &mut project,
+            registers: &mut registers,
+            transport: TransportStatus { state: PlayState::Stopped, tick: 0.0 },
+            meters: &meters,
+        };
+        let mut run = |column, operator, count, ctx: &mut Context| {
+            state.cursor.column = column;
+            let operation = Operation { operator, count, motion: Motion::None, register: None };
+            state.operate(operation, ctx)
+        };
+
+        // 2yy from inside the first note takes it and the one after.
+        run(2, Operator::Yank, 2, &mut ctx);
+        assert_eq!(yanked(ctx.registers), [(60, 0), (60, 4)]);
+
+        run(4, Operator::Yank, 1, &mut ctx);
+        assert_eq!(ctx.registers.get(None).unwrap().length, 4 * COLUMN_TICKS);
+
+        assert_eq!(run(12, Operator::Paste, 3, &mut ctx).as_deref(), Some("3 notes pasted"));
+
+        let notes: Vec<(u8, u32)> = ctx.project.pattern(id).unwrap().notes().iter()
+            .filter(|note| note.pitch == 60)
+            .map(|note| (note.pitch, note.start / COLUMN_TICKS))
+            .collect();
+        assert_eq!(notes, [(60, 0), (60, 4), (60, 8), (60, 12), (60, 16), (60, 20)]);
+    }
+}
@@ -51,8 +51,8 @@
 // ⠀⠀⠤⢆⠆⠈⠉⠳⠤⣄⡀⠀⠀⠀⠙⢻⣿⣿⠿⠿⠿⢻⣿⠙⠇
 // ⠠⠤⠀⣉⣁⣢⣄⣀⣀⣤⣿⠷⠦⠤⣠⡶⠿⣟⠀⠀⠀⠀⠻⡀⠀
 // ⠀⠀⠔⠋⠁⠀⠀⠀⠀⠀⠀⠀⠀⠀⠃⠃⠉⠉⠛⠛⠿⢷⡶⠀⠀
-use crate::{widgets::theme::UIStyle, window::Window};
-use crate::input::LocalCommand;
+use crate::{widgets::theme::UIStyle, window::{Context, Window, WindowKind}};
+use crate::input::{EditorCommand, LocalCommand};
 
 use ratatui::{
     Frame,
@@ -93,11 +93,16 @@ const ICON: &str = r#"
 
 
 impl Window for SplashScreen {
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::Splash)
+    }
+
     fn render(
         &mut self,
         frame: &mut Frame,
         area: Rect,
-        focused: bool
+        focused: bool,
+        _ctx: &Context,
     ) {
         let icon_lines = ICON.trim_matches('\n').lines().count();
         let title_lines = TITLE.trim_matches('\n').lines().count();
@@ -131,9 +136,7 @@ impl Window for SplashScreen {
         );
     }
 
-    fn handle_input(&mut self, cmd: LocalCommand) {
-        match cmd {
-            _ => ()
-        }
+    fn handle_input(&mut self, _cmd: LocalCommand, _ctx: &mut Context) -> Option<EditorCommand> {
+        None
     }
 }
@@ -1,5 +1,5 @@
-use crate::window::Window;
-use crate::input::LocalCommand;
+use crate::window::{Context, Window, WindowKind};
+use crate::input::{Edge, EditorCommand, LocalCommand};
 use crate::widgets::theme::UIStyle;
 use crate::widgets::buttonlist::{ButtonList, ButtonListState, Button};
 
@@ -21,23 +21,30 @@ pub struct SplitSelect<'a> {
 impl SplitSelect<'_> {
     pub fn new(direction: Direction) -> Self {
         let mut buttons = Vec::new();
-        for i in 0..5 {
+        for kind in WindowKind::ALL {
             buttons.push(Button {
-                label: Line::from(format!("Button {}", i)).centered(),
+                label: Line::from(kind.title()).centered(),
                 height: 1,
                 style: Style::default(),
             })
         }
 
+        let mut list_state = ButtonListState::new(buttons);
+        list_state.first_button();
+
         Self {
-            list_state: ButtonListState::new(buttons),
+            list_state,
             direction,
         }
     }
 }
 
 impl Window for SplitSelect<'_> {
-    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool) {
+    fn kind(&self) -> Option<WindowKind> {
+        None
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, _ctx: &Context) {
         let block = UIStyle::window_border("New Window", focused);
 
         let list_area = UIStyle::centered_rect(50, 50, area);
@@ -50,13 +57,29 @@ impl Window for SplitSelect<'_> {
         list.render(list_area, frame.buffer_mut(), &mut self.list_state);
     }
 
-    fn handle_input(&mut self, cmd: LocalCommand) {
+    fn handle_input(&mut self, cmd: LocalCommand, _ctx: &mut Context) -> Option<EditorCommand> {
         match cmd {
             LocalCommand::MoveLocalCursor { dx: _, dy } => {
                 self.list_state.jump_buttons(-dy);
+                None
+            },
+
+            LocalCommand::MoveToEdge { edge: Edge::Start | Edge::Top } => {
+                self.list_state.first_button();
+                None
             },
-            
-            // _ => (),
+
+            LocalCommand::MoveToEdge { edge: Edge::End | Edge::Bottom } => {
+                self.list_state.last_button();
+                None
+            },
+
+            LocalCommand::Select => {
+                let kind = WindowKind::ALL[self.list_state.hovered?];
+                Some(EditorCommand::Open { kind, direction: self.direction })
+            },
+
+            _ => None,
         }
     }
 }
@@ -0,0 +1,369 @@
+use std::ops::Range;
+
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::StatefulWidget,
+};
+
+use crate::instrument::drums::DrumSound;
+use crate::project::{
+    PPQ, Project, TrackId,
+    pattern::{Note, NoteRange, NoteState, Pattern},
+    tempo::TimeUnit,
+    track::InstrumentSpec,
+};
+use crate::widgets::theme::UIStyle;
+use crate::window::{Context, Window, WindowKind};
+use crate::input::{Edge, EditorCommand, LocalCommand, Motion, Operation};
+use crate::operator::{self, Target};
+
+/// Every step is a 16th note.
+const STEP_TICKS: u32 = PPQ / 4;
+const VELOCITY: u8 = 100;
+
+const NAME_WIDTH: u16 = 12;
+
+/// One drum sound: a pitch played on the instrument of a track.
+pub struct StepRow {
+    pub name: String,
+    pub track: TrackId,
+    pub pitch: u8,
+}
+
+pub struct StepSequencer<'a, 'b> {
+    ctx: &'a Context<'b>,
+}
+
+impl<'a, 'b> StepSequencer<'a, 'b> {
+    pub fn new(ctx: &'a Context<'b>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl StatefulWidget for StepSequencer<'_, '_> {
+    type State = StepSequencerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let tempo_map = self.ctx.project.tempo_map();
+        let rows = state.rows.iter()
+            .enumerate()
+            .take(area.height as usize);
+
+        for (i, row) in rows {
+            let y = area.y + i as u16;
+            let on_cursor_row = i == state.row;
+
+            let name_style = if on_cursor_row {
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+
+            buf.set_stringn(
+                area.x,
+                y,
+                format!(" {}", row.name),
+                NAME_WIDTH.min(area.width) as usize,
+                name_style,
+            );
+
+            let Some(id) = self.ctx.project.first_pattern(row.track) else { continue };
+            let Some(pattern) = self.ctx.project.pattern(id) else { continue };
+            let playhead = self.ctx.playhead(id).map(|tick| (tick / STEP_TICKS) as usize);
+            let start = self.ctx.project.pattern_start(id).unwrap_or(0);
+            let muted = self.ctx.project.track(row.track)
+                .is_some_and(|track| !self.ctx.project.is_audible(track));
+            let soloing = pattern.is_soloing();
+            let mut x = area.x + NAME_WIDTH;
+
+            for step in 0..StepSequencerState::steps(pattern) {
+                let hit = StepSequencerState::hit(pattern, row, step).map(|index| pattern.notes()[index]);
+                let position = tempo_map.position(start + step as u32 * STEP_TICKS);
+
+                // A line before every beat of the time signature in effect.
+                if step > 0 && position.tick == 0 {
+                    if let Some(cell) = buf.cell_mut((x, y)) {
+                        cell.set_style(Style::default().fg(Color::DarkGray));
+                        cell.set_char('│');
+                    }
+
+                    x += 2;
+                }
+
+                let Some(cell) = buf.cell_mut((x, y)) else { break };
+
+                let mut style = match hit {
+                    Some(note) if muted || !note.is_audible(soloing) => Style::default().fg(Color::Gray),
+                    Some(note) if note.state == NoteState::Solo => Style::default().fg(Color::LightMagenta),
+                    Some(_) => Style::default().fg(Color::Yellow),
+                    None if playhead == Some(step) => Style::default().fg(Color::White),
+                    None => Style::default().fg(Color::DarkGray),
+                };
+
+                if playhead == Some(step) {
+                    style = style.bg(Color::DarkGray);
+                }
+
+                if on_cursor_row && step == state.step {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+
+                cell.set_style(style);
+                cell.set_char(match hit {
+                    Some(note) if note.state == NoteState::Muted => '□',
+                    Some(_) => '■',
+                    None => '·',
+                });
+
+                x += 2;
+            }
+        }
+    }
+}
+
+pub struct StepSequencerState {
+    rows: Vec<StepRow>,
+    row: usize,
+    step: usize,
+}
+
+impl StepSequencerState {
+    pub fn new(project: &Project) -> Self {
+        let mut state = Self {
+            rows: Vec::new(),
+            row: 0,
+            step: 0,
+        };
+
+        state.refresh(project);
+        state
+    }
+
+    fn drum_track(project: &Project) -> Option<TrackId> {
+        project.find_track(|instrument| *instrument == InstrumentSpec::DrumKit)
+    }
+
+    /// A row for every piece of the first drum kit in the project, looked
+    /// up again before use so that a kit added or removed later shows.
+    fn refresh(&mut self, project: &Project) {
+        let track = Self::drum_track(project);
+        if self.rows.first().map(|row| row.track) == track {
+            return;
+        }
+
+        self.rows = track.map(|track| DrumSound::ALL.into_iter()
+                .map(|sound| StepRow {
+                    name: sound.name().to_string(),
+                    track,
+                    pitch: sound.pitch(),
+                })
+                .collect())
+            .unwrap_or_default();
+        self.row = self.row.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Rows edit the pattern their track opens with.
+    fn pattern<'a>(project: &'a Project, row: &StepRow) -> Option<&'a Pattern> {
+        project.pattern(project.first_pattern(row.track)?)
+    }
+
+    fn steps(pattern: &Pattern) -> usize {
+        (pattern.length() / STEP_TICKS) as usize
+    }
+
+    /// The first note on a row starting anywhere in a step, so that hits
+    /// moved off the grid still show and toggle.
+    fn hit(pattern: &Pattern, row: &StepRow, step: usize) -> Option<usize> {
+        let ticks = Self::ticks(step);
+
+        pattern.notes().iter()
+            .position(|note| note.pitch == row.pitch && ticks.contains(&note.start))
+    }
+
+    fn ticks(step: usize) -> Range<u32> {
+        let tick = step as u32 * STEP_TICKS;
+        tick..tick + STEP_TICKS
+    }
+
+    fn toggle(&self, project: &mut Project) {
+        let Some(row) = self.rows.get(self.row) else { return };
+        let Some(id) = project.first_pattern(row.track) else { return };
+        let Some(pattern) = project.pattern_mut(id) else { return };
+
+        match Self::hit(pattern, row, self.step) {
+            Some(index) => {
+                pattern.remove(index);
+            }
+
+            None => pattern.insert(Note {
+                pitch: row.pitch,
+                velocity: VELOCITY,
+                start: self.step as u32 * STEP_TICKS,
+                length: STEP_TICKS,
+                state: NoteState::Normal,
+            }),
+        }
+    }
+
+    /// The step a beat or bar away, as the pattern is placed in the song.
+    fn step_time(&self, project: &Project, unit: TimeUnit, count: i32) -> Option<usize> {
+        let row = self.rows.get(self.row)?;
+        let id = project.first_pattern(row.track)?;
+        let pattern = project.pattern(id)?;
+
+        let start = project.pattern_start(id).unwrap_or(0);
+        let tick = project.tempo_map().step(start + self.step as u32 * STEP_TICKS, unit, count);
+        let step = (tick.saturating_sub(start) / STEP_TICKS) as usize;
+
+        Some(step.min(Self::steps(pattern)))
+    }
+
+    fn move_time(&mut self, project: &Project, unit: TimeUnit, count: i32) {
+        let Some(step) = self.step_time(project, unit, count) else { return };
+        let last = self.rows.get(self.row)
+            .and_then(|row| Self::pattern(project, row))
+            .map_or(0, |pattern| Self::steps(pattern).saturating_sub(1));
+
+        self.step = step.min(last);
+    }
+
+    /// What a motion from the cursor covers: moving along takes every row
+    /// over the steps passed, moving across takes the rows passed over
+    /// the whole pattern, and no motion the hit under the cursor.
+    fn motion_range(&self, project: &Project, motion: Motion, count: usize) -> Option<NoteRange> {
+        let row = self.rows.get(self.row)?;
+        let pattern = Self::pattern(project, row)?;
+        let tick = self.step as u32 * STEP_TICKS;
+
+        let across = |step: usize| {
+            let target = step as u32 * STEP_TICKS;
+            NoteRange { ticks: tick.min(target)..tick.max(target), pitches: 0..=127 }
+        };
+        let rows = |target: usize| {
+            let pitches = self.rows[self.row.min(target)..=self.row.max(target)].iter()
+                .map(|row| row.pitch);
+            let (low, high) = (pitches.clone().min()?, pitches.max()?);
+
+            Some(NoteRange { ticks: 0..pattern.length(), pitches: low..=high })
+        };
+        let last_row = self.rows.len() - 1;
+
+        match motion {
+            Motion::Left => Some(across(self.step.saturating_sub(count))),
+            Motion::Right => Some(across(self.step + count)),
+
+            Motion::Beat { forward } | Motion::Bar { forward } => {
+                let unit = if matches!(motion, Motion::Bar { .. }) { TimeUnit::Bar } else { TimeUnit::Beat };
+                let count = if forward { count as i32 } else { -(count as i32) };
+
+                Some(across(self.step_time(project, unit, count)?))
+            }
+
+            // Rows go down the screen, so up is towards the first one.
+            Motion::Up => rows(self.row.saturating_sub(count)),
+            Motion::Down => rows((self.row + count).min(last_row)),
+
+            Motion::Start => Some(across(0)),
+            Motion::End => Some(across(Self::steps(pattern))),
+            Motion::Top => rows(0),
+            Motion::Bottom => rows(last_row),
+
+            Motion::Inner(object) => {
+                let id = project.first_pattern(row.track)?;
+                operator::text_object(project, id, tick, object, count)
+            }
+
+            Motion::None => Some(NoteRange {
+                ticks: Self::ticks(self.step).start..Self::ticks(self.step + count.max(1) - 1).end,
+                pitches: row.pitch..=row.pitch,
+            }),
+
+            Motion::Selection => None,
+        }
+    }
+
+    fn move_to_edge(&mut self, project: &Project, edge: Edge) {
+        let steps = self.rows.get(self.row)
+            .and_then(|row| Self::pattern(project, row))
+            .map_or(0, Self::steps);
+
+        match edge {
+            Edge::Start => self.step = 0,
+            Edge::End => self.step = steps.saturating_sub(1),
+            Edge::Top => self.row = 0,
+            Edge::Bottom => self.row = self.rows.len().saturating_sub(1),
+        }
+    }
+}
+
+impl Window for StepSequencerState {
+    fn kind(&self) -> Option<WindowKind> {
+        Some(WindowKind::StepSequencer)
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context) {
+        self.refresh(ctx.project);
+
+        let title = match self.track(ctx.project).and_then(|id| ctx.project.track(id)) {
+            Some(track) => format!("Step Sequencer ─ {}{}", track.name, track.flags()),
+            None => "Step Sequencer".to_string(),
+        };
+
+        let block = UIStyle::window_border(&title, focused);
+        frame.render_widget(&block, area);
+
+        frame.render_stateful_widget(
+            StepSequencer::new(ctx),
+            block.inner(area),
+            self,
+        );
+    }
+
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand> {
+        self.refresh(ctx.project);
+
+        match cmd {
+            LocalCommand::MoveLocalCursor { dx, dy } => {
+                let last_row = self.rows.len().saturating_sub(1);
+                self.row = self.row.saturating_add_signed(-dy as isize).min(last_row);
+
+                let steps = self.rows.get(self.row)
+                    .and_then(|row| Self::pattern(ctx.project, row))
+                    .map_or(0, Self::steps);
+                self.step = self.step.saturating_add_signed(dx as isize).min(steps.saturating_sub(1));
+            },
+
+            LocalCommand::MoveTime { unit, count } => self.move_time(ctx.project, unit, count),
+            LocalCommand::MoveToEdge { edge } => self.move_to_edge(ctx.project, edge),
+
+            LocalCommand::Select => self.toggle(ctx.project),
+
+            _ => (),
+        }
+
+        None
+    }
+
+    fn track(&self, project: &Project) -> Option<TrackId> {
+        Self::drum_track(project)
+    }
+
+    fn operate(&mut self, operation: Operation, ctx: &mut Context) -> Option<String> {
+        self.refresh(ctx.project);
+        let row = self.rows.get(self.row)?;
+        let pattern = ctx.project.first_pattern(row.track)?;
+        let range = self.motion_range(ctx.project, operation.motion, operation.count);
+
+        let target = Target {
+            pattern,
+            range,
+            cursor: self.step as u32 * STEP_TICKS,
+            grid: STEP_TICKS,
+        };
+
+        operator::apply(operation, target, ctx)
+    }
+}
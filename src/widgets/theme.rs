@@ -1,5 +1,5 @@
 use ratatui::{
-    style::{Color, Modifier, Style},
+    style::{Color, Style},
     widgets::{Block, Borders, BorderType},
     text::Line,
     layout::Rect,
@@ -1,13 +1,115 @@
 use std::collections::HashMap;
 
-use crate::input::LocalCommand;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{EditorCommand, LocalCommand, Operation};
 
 use ratatui::{
     layout::{ Rect, Direction, Layout, Constraint },
     Frame,
 };
 
-use crate::widgets::splashscreen::SplashScreen;
+use crate::audio::{mixer::Meters, transport::TransportStatus};
+use crate::project::{PatternId, Project, TrackId};
+use crate::registers::Registers;
+use crate::widgets::{
+    automation::AutomationState,
+    effects::EffectsState,
+    mixer::MixerState,
+    pianoroll::PianoRollState,
+    splashscreen::SplashScreen,
+    stepsequencer::StepSequencerState,
+};
+
+/// Session data that windows are views onto.
+pub struct Context<'a> {
+    pub project: &'a mut Project,
+    pub registers: &'a mut Registers,
+    pub transport: TransportStatus,
+    pub meters: &'a Meters,
+}
+
+impl Context<'_> {
+    /// Where the playhead is inside a pattern, while the transport is not
+    /// stopped and a clip of the pattern is playing.
+    pub fn playhead(&self, pattern: PatternId) -> Option<u32> {
+        if self.transport.is_stopped() {
+            return None;
+        }
+
+        self.project.pattern_tick(pattern, self.transport.tick as u32)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowKind {
+    Splash,
+    PianoRoll,
+    StepSequencer,
+    Mixer,
+    Effects,
+    Automation,
+}
+
+impl WindowKind {
+    /// Windows that can be opened from the split popup.
+    pub const ALL: [WindowKind; 5] = [
+        WindowKind::PianoRoll,
+        WindowKind::StepSequencer,
+        WindowKind::Mixer,
+        WindowKind::Effects,
+        WindowKind::Automation,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            WindowKind::Splash => "Splash Screen",
+            WindowKind::PianoRoll => "Piano Roll",
+            WindowKind::StepSequencer => "Step Sequencer",
+            WindowKind::Mixer => "Mixer",
+            WindowKind::Effects => "Effects",
+            WindowKind::Automation => "Automation",
+        }
+    }
+
+    pub fn build(self, project: &Project) -> Box<dyn Window> {
+        match self {
+            WindowKind::Splash => Box::new(SplashScreen),
+            WindowKind::PianoRoll => Box::new(PianoRollState::new(project)),
+            WindowKind::StepSequencer => Box::new(StepSequencerState::new(project)),
+            WindowKind::Mixer => Box::new(MixerState::new()),
+            WindowKind::Effects => Box::new(EffectsState::new()),
+            WindowKind::Automation => Box::new(AutomationState::new()),
+        }
+    }
+}
+
+/// The shape of the window layout, as saved with a project.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LayoutSpec {
+    Window(WindowKind),
+
+    Split {
+        #[serde(with = "DirectionDef")]
+        direction: Direction,
+        ratio: f32,
+        first: Box<LayoutSpec>,
+        second: Box<LayoutSpec>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Direction")]
+enum DirectionDef {
+    Horizontal,
+    Vertical,
+}
+
+impl Default for LayoutSpec {
+    fn default() -> Self {
+        LayoutSpec::Window(WindowKind::Splash)
+    }
+}
 
 pub enum LayoutNode {
     Window(usize),
@@ -32,21 +134,76 @@ impl WindowManager {
     pub fn new() -> Self {
         let base_id = 0;
         let mut windows: HashMap<usize, Box<dyn Window>> = HashMap::new();
-        windows.insert(base_id, Box::new(SplashScreen::default()));
+        windows.insert(base_id, Box::new(SplashScreen));
 
         Self {
             focused: Some(base_id),
-            windows: windows,
+            windows,
             layout_tree: LayoutNode::Window(base_id),
             last_window_id: base_id,
             popup_stack: Vec::new(),
         }
     }
 
-    fn push_window<W>(&mut self, window: W) -> usize
-    where W: Window + 'static {
+    /// Windows laid out as saved, with the first one focused.
+    pub fn from_layout(spec: &LayoutSpec, project: &Project) -> Self {
+        let mut manager = Self {
+            focused: None,
+            windows: HashMap::new(),
+            layout_tree: LayoutNode::Window(0),
+            last_window_id: 0,
+            popup_stack: Vec::new(),
+        };
+
+        manager.layout_tree = manager.build_layout(spec, project);
+        manager.focused = Some(Self::first_window(&manager.layout_tree));
+        manager
+    }
+
+    fn build_layout(&mut self, spec: &LayoutSpec, project: &Project) -> LayoutNode {
+        match spec {
+            LayoutSpec::Window(kind) => LayoutNode::Window(self.push_window(kind.build(project))),
+
+            LayoutSpec::Split { direction, ratio, first, second } => LayoutNode::Split {
+                direction: *direction,
+                ratio: *ratio,
+                first: Box::new(self.build_layout(first, project)),
+                second: Box::new(self.build_layout(second, project)),
+            },
+        }
+    }
+
+    fn first_window(node: &LayoutNode) -> usize {
+        match node {
+            LayoutNode::Window(id) => *id,
+            LayoutNode::Split { first, .. } => Self::first_window(first),
+        }
+    }
+
+    pub fn layout(&self) -> LayoutSpec {
+        self.layout_spec(&self.layout_tree)
+    }
+
+    fn layout_spec(&self, node: &LayoutNode) -> LayoutSpec {
+        match node {
+            LayoutNode::Window(id) => LayoutSpec::Window(
+                self.windows.get(id)
+                    .and_then(|window| window.kind())
+                    .unwrap_or(WindowKind::Splash)
+            ),
+
+            LayoutNode::Split { direction, ratio, first, second } => LayoutSpec::Split {
+                direction: *direction,
+                ratio: *ratio,
+                first: Box::new(self.layout_spec(first)),
+                second: Box::new(self.layout_spec(second)),
+            },
+        }
+    }
+
+    fn push_window(&mut self, window: Box<dyn Window>) -> usize {
         self.last_window_id += 1;
-        self.windows.insert(self.last_window_id, Box::new(window));
+        self.windows.insert(self.last_window_id, window);
         self.last_window_id
     }
 
@@ -60,7 +217,7 @@ impl WindowManager {
 
     pub fn push_popup<W>(&mut self, window: W)
     where W: Window + 'static {
-        let id = self.push_window(window);
+        let id = self.push_window(Box::new(window));
         self.popup_stack.push(id);
     }
 
@@ -76,13 +233,11 @@ impl WindowManager {
         !self.popup_stack.is_empty()
     }
 
-    pub fn split_current_window<W>(
+    pub fn split_current_window(
         &mut self,
         direction: Direction,
-        new_window: W
-    ) -> bool 
-    where W: Window + 'static
-    {
+        new_window: Box<dyn Window>
+    ) -> bool {
         let Some(focus) = self.focused else { return false };
 
         let old_id = match Self::get_focused_node(&mut self.layout_tree, focus) {
@@ -96,7 +251,7 @@ impl WindowManager {
             &mut self.layout_tree, focus
         ) {
             *node = LayoutNode::Split {
-                direction: direction,
+                direction,
                 ratio: 0.5,
                 first: Box::new(LayoutNode::Window(old_id)),
                 second: Box::new(LayoutNode::Window(new_id)),
@@ -110,7 +265,7 @@ impl WindowManager {
         false
     }
 
-    pub fn render_layout(&mut self, frame: &mut Frame, area: Rect) {
+    pub fn render_layout(&mut self, frame: &mut Frame, area: Rect, ctx: &Context) {
         let window_id = self.popup_stack.last();
         let focused = window_id.copied().or(self.focused);
 
@@ -120,12 +275,13 @@ impl WindowManager {
             area,
             &mut self.windows,
             focused,
+            ctx,
         );
 
         if self.popup_stack.is_empty() { return; }
 
         let window = self.windows.get_mut(window_id.unwrap()).unwrap();
-        window.render(frame, area, true);
+        window.render(frame, area, true, ctx);
     }
 
     fn do_render_layout(
@@ -134,13 +290,14 @@ impl WindowManager {
         area: Rect,
         windows: &mut HashMap<usize, Box<dyn Window>>,
         focused: Option<usize>,
+        ctx: &Context,
     ) {
         match node {
             LayoutNode::Window(id) => {
-                let window = windows.get_mut(&id).unwrap();
+                let window = windows.get_mut(id).unwrap();
                 let is_focused = focused == Some(*id);
 
-                window.render(frame, area, is_focused);
+                window.render(frame, area, is_focused, ctx);
             },
 
             LayoutNode::Split { direction, ratio, first, second } => {
@@ -152,8 +309,8 @@ impl WindowManager {
                     ])
                     .split(area);
 
-                Self::do_render_layout(frame, &first, layout[0], windows, focused);
-                Self::do_render_layout(frame, &second, layout[1], windows, focused);
+                Self::do_render_layout(frame, first, layout[0], windows, focused, ctx);
+                Self::do_render_layout(frame, second, layout[1], windows, focused, ctx);
             }
         }
     }
@@ -175,18 +332,74 @@ impl WindowManager {
         self.focused = Some(id);
     }
 
-    pub fn handle_input(&mut self, cmd: LocalCommand) {
+    pub fn handle_input(
+        &mut self,
+        cmd: LocalCommand,
+        ctx: &mut Context,
+    ) -> Option<EditorCommand> {
         let window_id = self.popup_stack.last();
-        let focused = window_id.copied().or(self.focused);
+        let focused = window_id.copied().or(self.focused)?;
 
-        if let Some(id) = focused {
-            let window = self.windows.get_mut(&id).unwrap();
-            window.handle_input(cmd);
-        }
+        let window = self.windows.get_mut(&focused).unwrap();
+        window.handle_input(cmd, ctx)
+    }
+
+    pub fn operate(&mut self, operation: Operation, ctx: &mut Context) -> Option<String> {
+        let window_id = self.popup_stack.last();
+        let focused = window_id.copied().or(self.focused)?;
+
+        let window = self.windows.get_mut(&focused).unwrap();
+        window.operate(operation, ctx)
+    }
+
+    pub fn track(&self, project: &Project) -> Option<TrackId> {
+        let window_id = self.popup_stack.last();
+        let focused = window_id.copied().or(self.focused)?;
+
+        self.windows.get(&focused)?.track(project)
+    }
+
+    pub fn channel(&self, project: &Project) -> Option<usize> {
+        let window_id = self.popup_stack.last();
+        let focused = window_id.copied().or(self.focused)?;
+
+        self.windows.get(&focused)?.channel(project)
+    }
+
+    pub fn selection(&self, project: &Project) -> Option<String> {
+        let window_id = self.popup_stack.last();
+        let focused = window_id.copied().or(self.focused)?;
+
+        self.windows.get(&focused)?.selection(project)
     }
 }
 
 pub trait Window {
-    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool);
-    fn handle_input(&mut self, cmd: LocalCommand);
+    /// What to save this window as, popups are not saved.
+    fn kind(&self) -> Option<WindowKind>;
+    fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, ctx: &Context);
+    /// Windows can hand a command back for the editor to carry out, for
+    /// things that reach beyond the window itself.
+    fn handle_input(&mut self, cmd: LocalCommand, ctx: &mut Context) -> Option<EditorCommand>;
+
+    /// Carry out an operator, returning what to tell the user. Windows
+    /// without anything to operate on ignore them.
+    fn operate(&mut self, _operation: Operation, _ctx: &mut Context) -> Option<String> {
+        None
+    }
+
+    /// The track being edited, for track commands without a track.
+    fn track(&self, _project: &Project) -> Option<TrackId> {
+        None
+    }
+
+    /// The track or bus being edited, for routing commands.
+    fn channel(&self, project: &Project) -> Option<usize> {
+        self.track(project)
+    }
+
+    /// What the visual mode selection covers, for the command line.
+    fn selection(&self, _project: &Project) -> Option<String> {
+        None
+    }
 }